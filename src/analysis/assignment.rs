@@ -0,0 +1,147 @@
+use super::subroutines;
+use crate::utils::symbol_table::{create_subroutine_symbol_table, create_symbol_table};
+use crate::utils::{Diagnostic, Grouping, Span, SymbolKind, SymbolTable, Token};
+use std::collections::HashSet;
+
+// The locals definitely assigned at a point of a subroutine, or None if the point is unreachable
+type Assigned<'a> = Option<HashSet<&'a str>>;
+
+// Returns the locals assigned on both of two control paths
+fn merge<'a>(a: Assigned<'a>, b: Assigned<'a>) -> Assigned<'a> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.intersection(&b).copied().collect()),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+// Walks the statements of a subroutine in control flow order, tracking which locals are
+// definitely assigned
+struct AssignmentWalker<'a, 'b> {
+    symbol_table: &'b SymbolTable<'a>,
+    reported: HashSet<&'a str>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> AssignmentWalker<'a, '_> {
+    // Checks the variables read by the terms of an expression
+    fn reads(&mut self, grouping: &'a Grouping, assigned: &Assigned<'a>) {
+        if grouping.name == "term" {
+            if let Some((Token::Identifier(name), span)) = grouping.spanned_tokens().first() {
+                self.read(name, *span, assigned);
+            }
+        }
+        for g in grouping.subgroupings() {
+            self.reads(g, assigned);
+        }
+    }
+
+    // Checks a read of a variable, warning if it is a local which may not be assigned
+    fn read(&mut self, name: &'a str, span: Span, assigned: &Assigned<'a>) {
+        let symbol = match self.symbol_table.get(name) {
+            Ok(symbol) if symbol.kind() == &SymbolKind::Local => symbol,
+            _ => return,
+        };
+        match assigned {
+            Some(assigned) if !assigned.contains(name) && self.reported.insert(name) => {
+                self.diagnostics.push(
+                    Diagnostic::warning(
+                        "uninitialized-local",
+                        format!(
+                            "Local variable '{}' may be read before it is assigned",
+                            name
+                        ),
+                        span,
+                    )
+                    .with_note(format!("'{}' is declared here", name), Some(symbol.span())),
+                )
+            }
+            _ => (),
+        }
+    }
+
+    // Walks a statements grouping, returning the locals assigned after it
+    fn statements(&mut self, statements: &'a Grouping, mut assigned: Assigned<'a>) -> Assigned<'a> {
+        for statement in statements.subgroupings() {
+            let tokens = statement.spanned_tokens();
+            let blocks = statement.subgroupings();
+            match statement.name {
+                "letStatement" => {
+                    for expression in &blocks {
+                        self.reads(expression, &assigned);
+                    }
+                    if let (Token::Identifier(name), span) = tokens[1] {
+                        if blocks.len() == 2 {
+                            // assigning an array element reads the array
+                            self.read(name, span, &assigned);
+                        } else if let Some(assigned) = &mut assigned {
+                            assigned.insert(name);
+                        }
+                    }
+                }
+                "ifStatement" => {
+                    self.reads(blocks[0], &assigned);
+                    let then = self.statements(blocks[1], assigned.clone());
+                    let otherwise = match blocks.get(2) {
+                        Some(block) => self.statements(block, assigned.clone()),
+                        None => assigned.clone(),
+                    };
+                    assigned = merge(then, otherwise);
+                }
+                "whileStatement" => {
+                    // the body may not run at all, so it assigns nothing definitely
+                    self.reads(blocks[0], &assigned);
+                    self.statements(blocks[1], assigned.clone());
+                }
+                "doStatement" => {
+                    if let (Token::Identifier(name), span) = tokens[1] {
+                        if matches!(tokens.get(2), Some((Token::Symbol('.'), _))) {
+                            self.read(name, span, &assigned);
+                        }
+                    }
+                    for expression in blocks {
+                        self.reads(expression, &assigned);
+                    }
+                }
+                "returnStatement" => {
+                    for expression in blocks {
+                        self.reads(expression, &assigned);
+                    }
+                    assigned = None;
+                }
+                _ => (),
+            }
+        }
+        assigned
+    }
+}
+
+// Warns about locals of the subroutines of a class which may be read before they are assigned
+pub fn check_assignments(class_grouping: &Grouping) -> Vec<Diagnostic> {
+    let class_symbol_table = match create_symbol_table(class_grouping) {
+        Ok(st) => st,
+        Err(_) => return vec![],
+    };
+    let mut diagnostics = vec![];
+    for subroutine in subroutines(class_grouping) {
+        let symbol_table = match create_subroutine_symbol_table(subroutine, &class_symbol_table) {
+            Ok(st) => st,
+            Err(_) => continue,
+        };
+        let mut walker = AssignmentWalker {
+            symbol_table: &symbol_table,
+            reported: HashSet::new(),
+            diagnostics: vec![],
+        };
+        if let Some(statements) = subroutine.subgroupings()[1]
+            .subgroupings()
+            .into_iter()
+            .find(|x| x.name == "statements")
+        {
+            walker.statements(statements, Some(HashSet::new()));
+        }
+        diagnostics.extend(walker.diagnostics);
+    }
+    diagnostics.sort_by_key(|x| x.span.start);
+    diagnostics
+}
@@ -0,0 +1,447 @@
+use super::os::OS_CLASSES;
+use crate::tokenizer::lex;
+use crate::utils::{Datatype, LexemeKind, Span, Symbol, SymbolKind, SymbolTable};
+
+static STATEMENT_KEYWORDS: &[&str] = &["let", "do", "if", "while", "return"];
+static CLASS_KEYWORDS: &[&str] = &["static", "field", "constructor", "function", "method"];
+static KEYWORD_CONSTANTS: &[&str] = &["true", "false", "null", "this"];
+static PRIMITIVE_TYPES: &[&str] = &["int", "char", "boolean"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Represents what a completion candidate inserts
+pub enum CompletionKind {
+    Keyword,
+    Variable,
+    Subroutine,
+    Class,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents a completion candidate, e.g. a variable with its type and segment as detail
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    pub detail: String,
+}
+
+impl Completion {
+    // Create a new Completion
+    fn new(label: &str, kind: CompletionKind, detail: String) -> Self {
+        Completion {
+            label: label.to_string(),
+            kind,
+            detail,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+// Represents a significant lexeme of source code which may not parse
+struct Word<'a> {
+    kind: LexemeKind,
+    text: &'a str,
+    span: Span,
+}
+
+// Represents a variable declaration found by scanning the words of a class
+struct Declaration<'a> {
+    kind: SymbolKind,
+    type_name: &'a str,
+    name: &'a str,
+    span: Span,
+}
+
+impl<'a> Declaration<'a> {
+    // Returns the symbol declared
+    fn symbol(&self) -> Symbol<'a> {
+        Symbol::new(
+            self.name,
+            Datatype::keyword_to_datatype(self.type_name),
+            self.kind.clone(),
+            self.span,
+        )
+    }
+}
+
+// Represents the declarations of a subroutine found by scanning the words of a class
+struct SubroutineOutline<'a> {
+    kind: &'a str,
+    return_type: &'a str,
+    name: &'a str,
+    parameters: Vec<Declaration<'a>>,
+    locals: Vec<Declaration<'a>>,
+    // the offset of the subroutine keyword
+    start: usize,
+}
+
+impl SubroutineOutline<'_> {
+    // Returns the declaration line of the subroutine, e.g. "method void run(int x)"
+    fn signature(&self) -> String {
+        let parameters = self
+            .parameters
+            .iter()
+            .map(|x| format!("{} {}", x.type_name, x.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "{} {} {}({})",
+            self.kind, self.return_type, self.name, parameters
+        )
+    }
+
+    // Returns the completion candidate of the subroutine
+    fn completion(&self) -> Completion {
+        Completion::new(self.name, CompletionKind::Subroutine, self.signature())
+    }
+}
+
+// Represents the declarations of a class found by scanning its words, which tolerates
+// incomplete code
+struct ClassOutline<'a> {
+    name: &'a str,
+    variables: Vec<Declaration<'a>>,
+    subroutines: Vec<SubroutineOutline<'a>>,
+}
+
+// Returns the significant words of source code, skipping trivia and invalid lexemes
+fn words(contents: &str) -> Vec<Word<'_>> {
+    lex(contents)
+        .into_iter()
+        .filter(|x| !x.kind.is_trivia() && x.kind != LexemeKind::Error)
+        .map(|x| Word {
+            kind: x.kind,
+            text: x.span.text(contents),
+            span: x.span,
+        })
+        .collect()
+}
+
+// Checks if a word can be the type of a declaration
+fn is_type(word: Option<&Word>) -> bool {
+    match word {
+        Some(w) if w.kind == LexemeKind::Identifier => true,
+        Some(w) => matches!(w.text, "int" | "char" | "boolean" | "void"),
+        None => false,
+    }
+}
+
+// Returns the comma separated identifiers starting at the word at i
+fn declared_names<'a>(words: &[Word<'a>], mut i: usize) -> Vec<(&'a str, Span)> {
+    let mut res = vec![];
+    while let Some(w) = words.get(i).filter(|x| x.kind == LexemeKind::Identifier) {
+        res.push((w.text, w.span));
+        if words.get(i + 1).map(|x| x.text) != Some(",") {
+            break;
+        }
+        i += 2;
+    }
+    res
+}
+
+// Returns the parameters of a parameter list starting at the word at i
+fn parameters<'a>(words: &[Word<'a>], mut i: usize) -> Vec<Declaration<'a>> {
+    let mut res = vec![];
+    while is_type(words.get(i)) && words.get(i + 1).map(|x| x.kind) == Some(LexemeKind::Identifier)
+    {
+        res.push(Declaration {
+            kind: SymbolKind::Argument,
+            type_name: words[i].text,
+            name: words[i + 1].text,
+            span: words[i + 1].span,
+        });
+        if words.get(i + 2).map(|x| x.text) != Some(",") {
+            break;
+        }
+        i += 3;
+    }
+    res
+}
+
+// Scans the words of a class for its declarations
+fn outline<'a>(words: &[Word<'a>]) -> ClassOutline<'a> {
+    let mut res = ClassOutline {
+        name: "",
+        variables: vec![],
+        subroutines: vec![],
+    };
+    for (i, w) in words.iter().enumerate() {
+        if w.kind != LexemeKind::Keyword {
+            continue;
+        }
+        match w.text {
+            "class" => match words.get(i + 1) {
+                Some(name) if name.kind == LexemeKind::Identifier && res.name.is_empty() => {
+                    res.name = name.text
+                }
+                _ => (),
+            },
+            "static" | "field" | "var" if is_type(words.get(i + 1)) => {
+                let kind = match w.text {
+                    "static" => SymbolKind::Static,
+                    "field" => SymbolKind::Field,
+                    _ => SymbolKind::Local,
+                };
+                let declarations = declared_names(words, i + 2)
+                    .into_iter()
+                    .map(|(name, span)| Declaration {
+                        kind: kind.clone(),
+                        type_name: words[i + 1].text,
+                        name,
+                        span,
+                    });
+                match res.subroutines.last_mut() {
+                    Some(subroutine) if kind == SymbolKind::Local => {
+                        subroutine.locals.extend(declarations)
+                    }
+                    _ => res.variables.extend(declarations),
+                }
+            }
+            "constructor" | "function" | "method"
+                if is_type(words.get(i + 1))
+                    && words.get(i + 2).map(|x| x.kind) == Some(LexemeKind::Identifier) =>
+            {
+                let parameters = match words.get(i + 3) {
+                    Some(x) if x.text == "(" => parameters(words, i + 4),
+                    _ => vec![],
+                };
+                res.subroutines.push(SubroutineOutline {
+                    kind: w.text,
+                    return_type: words[i + 1].text,
+                    name: words[i + 2].text,
+                    parameters,
+                    locals: vec![],
+                    start: w.span.start,
+                });
+            }
+            _ => (),
+        }
+    }
+    res
+}
+
+// Returns the keyword candidates
+fn keywords(keywords: &[&str]) -> Vec<Completion> {
+    keywords
+        .iter()
+        .map(|x| Completion::new(x, CompletionKind::Keyword, String::new()))
+        .collect()
+}
+
+// Returns the variables visible in a scope, innermost first
+fn variables(symbol_table: &SymbolTable) -> Vec<Completion> {
+    let mut res = vec![];
+    let mut scope = Some(symbol_table);
+    while let Some(st) = scope {
+        for symbol in st.symbols().iter().filter(|x| x.name() != "this") {
+            res.push(Completion::new(
+                symbol.name(),
+                CompletionKind::Variable,
+                format!("{} {}", symbol.class(), symbol.literal()),
+            ));
+        }
+        scope = st.parent();
+    }
+    res
+}
+
+// Represents the classes completions are looked up in: the class being edited, the other
+// classes of the program and the OS classes
+struct Program<'a> {
+    classes: Vec<ClassOutline<'a>>,
+}
+
+impl<'a> Program<'a> {
+    // Returns the class with a name, preferring the earliest source
+    fn class(&self, name: &str) -> Option<&ClassOutline<'a>> {
+        self.classes.iter().find(|x| x.name == name)
+    }
+
+    // Returns the names of all classes
+    fn class_names(&self) -> Vec<Completion> {
+        self.classes
+            .iter()
+            .filter(|x| !x.name.is_empty())
+            .map(|x| Completion::new(x.name, CompletionKind::Class, "class".to_string()))
+            .collect()
+    }
+
+    // Returns the subroutines callable as qualifier.f(), where the qualifier is either a
+    // variable whose methods are called or a class whose functions and constructors are called
+    fn members(&self, qualifier: &str, symbol_table: &SymbolTable) -> Vec<Completion> {
+        let (class, kinds): (&str, &[&str]) = match symbol_table.get(qualifier) {
+            Ok(symbol) => match symbol.dtype() {
+                Datatype::Custom(class) => (class, &["method"]),
+                _ => return vec![],
+            },
+            Err(_) => (qualifier, &["function", "constructor"]),
+        };
+        match self.class(class) {
+            Some(outline) => outline
+                .subroutines
+                .iter()
+                .filter(|x| kinds.contains(&x.kind))
+                .map(|x| x.completion())
+                .collect(),
+            None => vec![],
+        }
+    }
+}
+
+// Returns the subroutine a cursor is in, along with the symbol table of its scope
+fn scope<'a, 'b>(
+    class: &'b ClassOutline<'a>,
+    before: &[Word],
+    offset: usize,
+) -> (Option<&'b SubroutineOutline<'a>>, SymbolTable<'a>) {
+    let class_symbol_table = SymbolTable::new(
+        class.name,
+        class.variables.iter().map(|x| x.symbol()).collect(),
+    );
+    let depth = before.iter().fold(0isize, |depth, w| match w.text {
+        "{" => depth + 1,
+        "}" => depth - 1,
+        _ => depth,
+    });
+    let subroutine = match depth {
+        d if d >= 2 => class.subroutines.iter().rev().find(|x| x.start < offset),
+        _ => None,
+    };
+    let symbol_table = match subroutine {
+        Some(subroutine) => {
+            let mut symbols = vec![];
+            if subroutine.kind == "method" {
+                let dtype = Datatype::Custom(class.name.to_string());
+                symbols.push(Symbol::new(
+                    "this",
+                    dtype,
+                    SymbolKind::Argument,
+                    Span::default(),
+                ));
+            }
+            symbols.extend(subroutine.parameters.iter().map(|x| x.symbol()));
+            symbols.extend(subroutine.locals.iter().map(|x| x.symbol()));
+            SymbolTable::new(class.name, symbols).with_parent(class_symbol_table)
+        }
+        None if depth <= 0 => SymbolTable::new(class.name, vec![]),
+        None => class_symbol_table,
+    };
+    (subroutine, symbol_table)
+}
+
+// Checks if the offset is inside a comment or string constant
+fn in_literal(contents: &str, offset: usize) -> bool {
+    lex(contents)
+        .iter()
+        .find(|x| x.span.start < offset && offset <= x.span.end)
+        .map(|x| match x.kind {
+            LexemeKind::LineComment => true,
+            LexemeKind::BlockComment | LexemeKind::StringConst => offset < x.span.end,
+            LexemeKind::Error => {
+                let text = x.span.text(contents);
+                text.starts_with('"') || text.starts_with("/*")
+            }
+            _ => false,
+        })
+        .unwrap_or(false)
+}
+
+// Returns the completion candidates at an offset of a .jack file. The other classes of the
+// program are given as sources so their members can be offered too. The source code around the
+// cursor does not need to parse.
+pub fn complete(contents: &str, offset: usize, program: &[&str]) -> Vec<Completion> {
+    let offset = offset.min(contents.len());
+    if in_literal(contents, offset) {
+        return vec![];
+    }
+    let words = words(contents);
+    // the word being typed is the prefix the candidates must start with
+    let (start, prefix) = match words
+        .iter()
+        .find(|x| x.span.start < offset && offset <= x.span.end)
+    {
+        Some(w) if matches!(w.kind, LexemeKind::Identifier | LexemeKind::Keyword) => {
+            (w.span.start, &contents[w.span.start..offset])
+        }
+        _ => (offset, ""),
+    };
+    let before = words
+        .iter()
+        .filter(|x| x.span.end <= start)
+        .copied()
+        .collect::<Vec<_>>();
+
+    let program = Program {
+        classes: std::iter::once(outline(&words))
+            .chain(program.iter().map(|x| outline(&self::words(x))))
+            .chain(OS_CLASSES.iter().map(|x| outline(&self::words(x))))
+            .collect(),
+    };
+    let class = &program.classes[0];
+    let (subroutine, symbol_table) = scope(class, &before, start);
+    let own_subroutines = || {
+        class
+            .subroutines
+            .iter()
+            .map(|x| x.completion())
+            .collect::<Vec<_>>()
+    };
+    let types = |void: bool| {
+        let mut res = keywords(PRIMITIVE_TYPES);
+        if void {
+            res.extend(keywords(&["void"]));
+        }
+        res.extend(program.class_names());
+        res
+    };
+    let previous = before.last().map(|x| x.text).unwrap_or_default();
+
+    let candidates = match (subroutine, previous) {
+        (_, ".") => match before.iter().rev().nth(1) {
+            Some(q) if q.kind == LexemeKind::Identifier => program.members(q.text, &symbol_table),
+            _ => vec![],
+        },
+        (None, _) if before.iter().all(|x| x.text != "{") => keywords(&["class"]),
+        (None, "static" | "field" | "(" | ",") => types(false),
+        (None, "constructor" | "function" | "method") => types(true),
+        (None, _) => keywords(CLASS_KEYWORDS),
+        (Some(_), "var") => types(false),
+        (Some(_), "let") => variables(&symbol_table),
+        (Some(_), "do") => [
+            own_subroutines(),
+            variables(&symbol_table),
+            program.class_names(),
+        ]
+        .concat(),
+        (Some(subroutine), ";" | "{" | "}") => {
+            let mut res = keywords(STATEMENT_KEYWORDS);
+            // local variables can only be declared before the first statement
+            let has_statements = before.iter().any(|x| {
+                x.span.start > subroutine.start
+                    && x.kind == LexemeKind::Keyword
+                    && STATEMENT_KEYWORDS.contains(&x.text)
+            });
+            if !has_statements {
+                res.extend(keywords(&["var"]));
+            }
+            if previous == "}" {
+                res.extend(keywords(&["else"]));
+            }
+            res.extend(variables(&symbol_table));
+            res
+        }
+        (Some(_), _) => [
+            variables(&symbol_table),
+            keywords(KEYWORD_CONSTANTS),
+            own_subroutines(),
+            program.class_names(),
+        ]
+        .concat(),
+    };
+    let mut res: Vec<Completion> = vec![];
+    for candidate in candidates {
+        if candidate.label.starts_with(prefix) && res.iter().all(|x| x.label != candidate.label) {
+            res.push(candidate);
+        }
+    }
+    res
+}
@@ -0,0 +1,190 @@
+use super::{class_name, subroutines};
+use crate::utils::symbol_table::{create_subroutine_symbol_table, create_symbol_table};
+use crate::utils::{Datatype, GroupItem, Grouping, Span, Symbol, SymbolTable, Token};
+
+#[derive(Debug, Clone)]
+// Represents what an identifier names
+pub enum Category<'a> {
+    // A variable, along with its symbol if it is declared
+    Variable(Option<Symbol<'a>>),
+    Class,
+    Subroutine,
+}
+
+#[derive(Debug, Clone)]
+// Represents an occurrence of an identifier in a class
+pub struct Identifier<'a> {
+    pub name: &'a str,
+    pub span: Span,
+    pub category: Category<'a>,
+    // Whether this occurrence declares the identifier rather than uses it
+    pub defined: bool,
+    // For subroutines, the class the subroutine is looked up in
+    pub class: Option<String>,
+    // For subroutines called as x.f(), the variable or class name x
+    pub qualifier: Option<&'a str>,
+}
+
+// Walks a class and classifies its identifiers, resolving variables against the symbol tables
+struct IdentifierWalker<'a> {
+    class_name: &'a str,
+    res: Vec<Identifier<'a>>,
+}
+
+impl<'a> IdentifierWalker<'a> {
+    // Records an identifier occurrence
+    fn push(&mut self, name: &'a str, span: Span, category: Category<'a>, defined: bool) {
+        self.res.push(Identifier {
+            name,
+            span,
+            category,
+            defined,
+            class: None,
+            qualifier: None,
+        });
+    }
+
+    // Records a variable occurrence, resolving it in the symbol table
+    fn variable(&mut self, name: &'a str, span: Span, symbol_table: &SymbolTable<'a>) {
+        let symbol = symbol_table.get(name).ok().cloned();
+        self.push(name, span, Category::Variable(symbol), false);
+    }
+
+    // Records a type occurrence, which only names a class if it is not a primitive type
+    fn type_name(&mut self, token: (&'a Token, Span)) {
+        if let (Token::Identifier(name), span) = token {
+            self.push(name, span, Category::Class, false);
+        }
+    }
+
+    // Records the identifiers of a variable use or subroutine call starting at the token at i
+    fn reference(
+        &mut self,
+        tokens: &[(&'a Token, Span)],
+        i: usize,
+        symbol_table: &SymbolTable<'a>,
+    ) {
+        let (name, span) = match tokens[i] {
+            (Token::Identifier(name), span) => (name.as_str(), span),
+            _ => return,
+        };
+        match tokens.get(i + 1) {
+            Some((Token::Symbol('('), _)) => {
+                self.push(name, span, Category::Subroutine, false);
+                self.res.last_mut().unwrap().class = Some(self.class_name.to_string());
+            }
+            Some((Token::Symbol('.'), _)) => {
+                let class = match symbol_table.get(name) {
+                    Ok(symbol) => {
+                        self.variable(name, span, symbol_table);
+                        match symbol.dtype() {
+                            Datatype::Custom(class) => Some(class.to_string()),
+                            _ => None,
+                        }
+                    }
+                    Err(_) => {
+                        self.push(name, span, Category::Class, false);
+                        Some(name.to_string())
+                    }
+                };
+                if let Some((Token::Identifier(sub), sub_span)) = tokens.get(i + 2) {
+                    self.push(sub, *sub_span, Category::Subroutine, false);
+                    let id = self.res.last_mut().unwrap();
+                    id.class = class;
+                    id.qualifier = Some(name);
+                }
+            }
+            _ => self.variable(name, span, symbol_table),
+        }
+    }
+
+    // Walks the statements and expressions of a subroutine body
+    fn body(&mut self, grouping: &'a Grouping, symbol_table: &SymbolTable<'a>) {
+        let tokens = grouping.spanned_tokens();
+        match grouping.name {
+            "varDec" => {
+                self.type_name(tokens[1]);
+                for (token, span) in &tokens[2..] {
+                    if let Token::Identifier(name) = token {
+                        let symbol = symbol_table.get(name).ok().cloned();
+                        self.push(name, *span, Category::Variable(symbol), true);
+                    }
+                }
+            }
+            "letStatement" => self.variable_at(&tokens, 1, symbol_table),
+            "doStatement" => self.reference(&tokens, 1, symbol_table),
+            "term" => self.reference(&tokens, 0, symbol_table),
+            _ => (),
+        }
+        for item in &grouping.items {
+            if let GroupItem::Grouping(g) = item {
+                self.body(g, symbol_table);
+            }
+        }
+    }
+
+    // Records the variable at the token at i
+    fn variable_at(
+        &mut self,
+        tokens: &[(&'a Token, Span)],
+        i: usize,
+        symbol_table: &SymbolTable<'a>,
+    ) {
+        if let Some((Token::Identifier(name), span)) = tokens.get(i) {
+            self.variable(name, *span, symbol_table);
+        }
+    }
+}
+
+// Returns every identifier occurring in a class, in source order
+pub fn identifiers(class_grouping: &Grouping) -> Result<Vec<Identifier<'_>>, String> {
+    let class_symbol_table = create_symbol_table(class_grouping)?;
+    let class_name = class_name(class_grouping).ok_or("Class doesn't have a name")?;
+    let mut walker = IdentifierWalker {
+        class_name,
+        res: vec![],
+    };
+    let class_tokens = class_grouping.spanned_tokens();
+    walker.push(class_name, class_tokens[1].1, Category::Class, true);
+
+    for class_var in class_grouping
+        .subgroupings()
+        .into_iter()
+        .filter(|x| x.name == "classVarDec")
+    {
+        let tokens = class_var.spanned_tokens();
+        walker.type_name(tokens[1]);
+        for (token, span) in &tokens[2..] {
+            if let Token::Identifier(name) = token {
+                let symbol = class_symbol_table.get(name).ok().cloned();
+                walker.push(name, *span, Category::Variable(symbol), true);
+            }
+        }
+    }
+
+    for subroutine in subroutines(class_grouping) {
+        let symbol_table = create_subroutine_symbol_table(subroutine, &class_symbol_table)?;
+        let tokens = subroutine.spanned_tokens();
+        walker.type_name(tokens[1]);
+        if let (Token::Identifier(name), span) = tokens[2] {
+            walker.push(name, span, Category::Subroutine, true);
+            walker.res.last_mut().unwrap().class = Some(class_name.to_string());
+        }
+        let parameters = subroutine.subgroupings()[0]
+            .spanned_tokens()
+            .into_iter()
+            .filter(|(x, _)| !matches!(x, Token::Symbol(',')))
+            .collect::<Vec<_>>();
+        for pair in parameters.chunks(2) {
+            walker.type_name(pair[0]);
+            if let (Token::Identifier(name), span) = pair[1] {
+                let symbol = symbol_table.get(name).ok().cloned();
+                walker.push(name, span, Category::Variable(symbol), true);
+            }
+        }
+        walker.body(subroutine.subgroupings()[1], &symbol_table);
+    }
+    let mut res = walker.res;
+    res.sort_by_key(|x| x.span.start);
+    Ok(res)
+}
@@ -0,0 +1,169 @@
+pub mod assignment;
+pub mod completion;
+pub mod identifiers;
+pub mod os;
+pub mod program;
+pub mod references;
+pub mod returns;
+pub mod types;
+
+pub use assignment::*;
+pub use completion::*;
+pub use identifiers::*;
+pub use program::*;
+pub use references::*;
+pub use returns::*;
+pub use types::*;
+
+use crate::utils::symbol_table::{create_subroutine_symbol_table, create_symbol_table};
+use crate::utils::{Diagnostic, Grouping, Span, SymbolTable, Token};
+
+// Returns the name of a class grouping
+pub fn class_name(class_grouping: &Grouping) -> Option<&str> {
+    match class_grouping.tokens().get(1) {
+        Some(Token::Identifier(name)) => Some(name),
+        _ => None,
+    }
+}
+
+// Returns the name of a subroutine declaration grouping along with its span
+pub fn subroutine_name(subroutine_grouping: &Grouping) -> Option<(&str, Span)> {
+    match subroutine_grouping.spanned_tokens().get(2) {
+        Some((Token::Identifier(name), span)) => Some((name, *span)),
+        _ => None,
+    }
+}
+
+// Returns the subroutine declaration groupings of a class grouping
+pub fn subroutines(class_grouping: &Grouping) -> Vec<&Grouping> {
+    class_grouping
+        .subgroupings()
+        .into_iter()
+        .filter(|x| x.name == "subroutineDec")
+        .collect()
+}
+
+// Reports symbols declared more than once in the same scope
+fn duplicate_symbols(symbol_table: &SymbolTable, diagnostics: &mut Vec<Diagnostic>) {
+    let symbols = symbol_table.symbols();
+    for (i, symbol) in symbols.iter().enumerate() {
+        if let Some(first) = symbols[..i].iter().find(|x| x.name() == symbol.name()) {
+            diagnostics.push(
+                Diagnostic::error(
+                    "duplicate-symbol",
+                    format!("'{}' is already declared in this scope", symbol.name()),
+                    symbol.span(),
+                )
+                .with_note("first declared here".to_string(), Some(first.span())),
+            );
+        }
+    }
+}
+
+// Checks the declarations, references, return statements and local variable assignments of a
+// parsed class, returning the diagnostics found
+pub fn check_class(class_grouping: &Grouping) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let class_symbol_table = match create_symbol_table(class_grouping) {
+        Ok(st) => st,
+        Err(e) => return vec![Diagnostic::error("invalid-class", e, class_grouping.span())],
+    };
+    duplicate_symbols(&class_symbol_table, &mut diagnostics);
+
+    let subroutines = subroutines(class_grouping);
+    let names = subroutines
+        .iter()
+        .filter_map(|x| subroutine_name(x))
+        .collect::<Vec<_>>();
+    for (i, (name, span)) in names.iter().enumerate() {
+        if let Some((_, first)) = names[..i].iter().find(|x| &x.0 == name) {
+            diagnostics.push(
+                Diagnostic::error(
+                    "duplicate-subroutine",
+                    format!("Subroutine '{}' is already declared", name),
+                    *span,
+                )
+                .with_note("first declared here".to_string(), Some(*first)),
+            );
+        }
+    }
+    for subroutine in &subroutines {
+        match create_subroutine_symbol_table(subroutine, &class_symbol_table) {
+            Ok(st) => duplicate_symbols(&st, &mut diagnostics),
+            Err(e) => diagnostics.push(Diagnostic::error(
+                "invalid-subroutine",
+                e,
+                subroutine.span(),
+            )),
+        }
+    }
+
+    let identifiers = match identifiers(class_grouping) {
+        Ok(ids) => ids,
+        Err(e) => {
+            diagnostics.push(Diagnostic::error("invalid-class", e, class_grouping.span()));
+            return diagnostics;
+        }
+    };
+    for id in identifiers.iter().filter(|x| !x.defined) {
+        match id.category {
+            Category::Variable(None) => diagnostics.push(Diagnostic::error(
+                "undefined-variable",
+                format!("Undefined variable '{}'", id.name),
+                id.span,
+            )),
+            Category::Subroutine
+                if id.qualifier.is_none() && !names.iter().any(|x| x.0 == id.name) =>
+            {
+                diagnostics.push(Diagnostic::error(
+                    "undefined-subroutine",
+                    format!(
+                        "Class {} has no subroutine '{}'",
+                        class_name(class_grouping).unwrap_or_default(),
+                        id.name
+                    ),
+                    id.span,
+                ))
+            }
+            _ => (),
+        }
+    }
+    // a class named bool is far less likely than a misspelled boolean
+    if class_name(class_grouping) != Some("bool") {
+        for id in identifiers.iter().filter(|x| x.name == "bool") {
+            if let Category::Class = id.category {
+                diagnostics.push(
+                    Diagnostic::error("unknown-type", "Unknown type 'bool'".to_string(), id.span)
+                        .with_suggestion(
+                            "Jack's boolean type is spelled 'boolean'".to_string(),
+                            id.span,
+                            "boolean".to_string(),
+                        ),
+                );
+            }
+        }
+    }
+    diagnostics.extend(check_returns(class_grouping));
+    diagnostics.extend(check_assignments(class_grouping));
+    diagnostics.sort_by_key(|x| x.span.start);
+    diagnostics
+}
+
+// Returns the declaration line of a subroutine, e.g. "method void run(int x)"
+pub fn subroutine_signature(subroutine_grouping: &Grouping) -> String {
+    let tokens = subroutine_grouping.tokens();
+    let parameters = subroutine_grouping.subgroupings()[0]
+        .tokens()
+        .iter()
+        .map(|x| x.as_text())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace(" ,", ",");
+    format!(
+        "{} {} {}({})",
+        tokens[0].as_text(),
+        tokens[1].as_text(),
+        tokens[2].as_text(),
+        parameters
+    )
+}
@@ -0,0 +1,302 @@
+use super::os::os_interfaces;
+use super::types::Type;
+use super::{class_name, subroutine_name, subroutines};
+use crate::utils::{Datatype, Grouping, Json, SymbolTable, Token};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Represents how a subroutine is called
+pub enum SubroutineKind {
+    Constructor,
+    Function,
+    Method,
+}
+
+impl SubroutineKind {
+    // Returns the kind named by the keyword of a subroutine declaration
+    pub fn from_keyword(keyword: &str) -> Option<SubroutineKind> {
+        match keyword {
+            "constructor" => Some(SubroutineKind::Constructor),
+            "function" => Some(SubroutineKind::Function),
+            "method" => Some(SubroutineKind::Method),
+            _ => None,
+        }
+    }
+
+    // Returns the keyword declaring a subroutine of this kind
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            SubroutineKind::Constructor => "constructor",
+            SubroutineKind::Function => "function",
+            SubroutineKind::Method => "method",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents the declared kind and types of a subroutine
+pub struct Signature {
+    pub kind: SubroutineKind,
+    pub return_type: Type,
+    pub parameters: Vec<Type>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents what other classes can see of a class: its variables and subroutine signatures,
+// in declaration order
+pub struct ClassInterface {
+    pub name: String,
+    pub fields: Vec<(String, Type)>,
+    pub statics: Vec<(String, Type)>,
+    pub subroutines: Vec<(String, Signature)>,
+}
+
+impl ClassInterface {
+    // Collects the interface of a parsed class
+    pub fn from_grouping(class_grouping: &Grouping) -> ClassInterface {
+        let mut fields = vec![];
+        let mut statics = vec![];
+        for declaration in class_grouping
+            .subgroupings()
+            .into_iter()
+            .filter(|x| x.name == "classVarDec")
+        {
+            let tokens = declaration.tokens();
+            let dtype = Type::from_name(&tokens[1].as_text());
+            let variables = tokens[2..].iter().filter_map(|x| match x {
+                Token::Identifier(name) => Some((name.to_string(), dtype.clone())),
+                _ => None,
+            });
+            match tokens[0] {
+                Token::Keyword("static") => statics.extend(variables),
+                _ => fields.extend(variables),
+            }
+        }
+        let subroutines = subroutines(class_grouping)
+            .into_iter()
+            .filter_map(|subroutine| {
+                let (name, _) = subroutine_name(subroutine)?;
+                let tokens = subroutine.tokens();
+                let parameters = subroutine.subgroupings()[0]
+                    .tokens()
+                    .into_iter()
+                    .filter(|x| !matches!(x, Token::Symbol(',')))
+                    .step_by(2)
+                    .map(|x| Type::from_name(&x.as_text()))
+                    .collect();
+                let signature = Signature {
+                    kind: SubroutineKind::from_keyword(&tokens[0].as_text())?,
+                    return_type: Type::from_name(&tokens[1].as_text()),
+                    parameters,
+                };
+                Some((name.to_string(), signature))
+            })
+            .collect();
+        ClassInterface {
+            name: class_name(class_grouping).unwrap_or_default().to_string(),
+            fields,
+            statics,
+            subroutines,
+        }
+    }
+
+    // Returns the JSON representation of the interface, which from_json reads back
+    pub fn to_json(&self) -> Json {
+        let variables = |variables: &[(String, Type)]| {
+            variables
+                .iter()
+                .map(|(name, dtype)| {
+                    Json::Array(vec![name.as_str().into(), dtype.to_string().into()])
+                })
+                .collect::<Vec<_>>()
+        };
+        let subroutines = self
+            .subroutines
+            .iter()
+            .map(|(name, signature)| {
+                Json::object(vec![
+                    ("name", name.as_str().into()),
+                    ("kind", signature.kind.keyword().into()),
+                    ("returnType", signature.return_type.to_string().into()),
+                    (
+                        "parameters",
+                        signature
+                            .parameters
+                            .iter()
+                            .map(|x| x.to_string())
+                            .collect::<Vec<_>>()
+                            .into(),
+                    ),
+                ])
+            })
+            .collect::<Vec<_>>();
+        Json::object(vec![
+            ("name", self.name.as_str().into()),
+            ("fields", variables(&self.fields).into()),
+            ("statics", variables(&self.statics).into()),
+            ("subroutines", subroutines.into()),
+        ])
+    }
+
+    // Reads an interface from its JSON representation
+    pub fn from_json(json: &Json) -> Option<ClassInterface> {
+        let variables = |key: &str| {
+            json.get(key)?
+                .as_array()?
+                .iter()
+                .map(|x| match x.as_array()?.as_slice() {
+                    [name, dtype] => {
+                        Some((name.as_str()?.to_string(), Type::from_name(dtype.as_str()?)))
+                    }
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()
+        };
+        let subroutines = json
+            .get("subroutines")?
+            .as_array()?
+            .iter()
+            .map(|x| {
+                let signature = Signature {
+                    kind: SubroutineKind::from_keyword(x.get("kind")?.as_str()?)?,
+                    return_type: Type::from_name(x.get("returnType")?.as_str()?),
+                    parameters: x
+                        .get("parameters")?
+                        .as_array()?
+                        .iter()
+                        .map(|x| x.as_str().map(Type::from_name))
+                        .collect::<Option<Vec<_>>>()?,
+                };
+                Some((x.get("name")?.as_str()?.to_string(), signature))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(ClassInterface {
+            name: json.get("name")?.as_str()?.to_string(),
+            fields: variables("fields")?,
+            statics: variables("statics")?,
+            subroutines,
+        })
+    }
+
+    // Returns the signature of a subroutine of the class
+    pub fn subroutine(&self, name: &str) -> Option<&Signature> {
+        self.subroutines
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, signature)| signature)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents what a subroutine call is made on
+pub enum Receiver {
+    // f(...), called on the current object if f is a method
+    This,
+    // x.f(...) where x is a variable holding an object
+    Variable(String),
+    // C.f(...) where C is a class
+    Class,
+}
+
+#[derive(Debug, Clone)]
+// Represents a subroutine call resolved against the program
+pub struct Call<'a> {
+    pub class: String,
+    pub name: String,
+    pub receiver: Receiver,
+    // The signature of the subroutine, unknown when its class isn't part of the program
+    pub signature: Option<&'a Signature>,
+}
+
+impl Call<'_> {
+    // Checks if the call passes an object as the hidden first argument
+    pub fn is_method_call(&self) -> bool {
+        match self.receiver {
+            Receiver::Variable(_) => true,
+            Receiver::This => self
+                .signature
+                .is_none_or(|x| x.kind == SubroutineKind::Method),
+            Receiver::Class => false,
+        }
+    }
+}
+
+// Represents every class of a program, including the OS classes, so that code in one class
+// can be checked and compiled against the others
+pub struct Program {
+    classes: Vec<ClassInterface>,
+}
+
+impl Program {
+    // Indexes the classes of a program. Earlier classes take precedence over later classes of
+    // the same name, and program classes take precedence over the OS classes.
+    pub fn new(classes: &[&Grouping]) -> Program {
+        Program::from_interfaces(
+            classes
+                .iter()
+                .map(|x| ClassInterface::from_grouping(x))
+                .collect(),
+        )
+    }
+
+    // Creates a program from class interfaces which were already collected, adding the OS
+    pub fn from_interfaces(interfaces: Vec<ClassInterface>) -> Program {
+        let mut classes: Vec<ClassInterface> = vec![];
+        for interface in interfaces
+            .into_iter()
+            .chain(os_interfaces().iter().cloned())
+        {
+            if !classes.iter().any(|x| x.name == interface.name) {
+                classes.push(interface);
+            }
+        }
+        Program { classes }
+    }
+
+    // Returns the classes of the program
+    pub fn classes(&self) -> &[ClassInterface] {
+        &self.classes
+    }
+
+    // Returns a class of the program by name
+    pub fn class(&self, name: &str) -> Option<&ClassInterface> {
+        self.classes.iter().find(|x| x.name == name)
+    }
+
+    // Returns the signature of a subroutine of a class of the program
+    pub fn signature(&self, class: &str, name: &str) -> Option<&Signature> {
+        self.class(class)?.subroutine(name)
+    }
+
+    // Resolves a subroutine call written as the tokens f(...) or x.f(...) inside a subroutine
+    // with the given symbol table. Calls on variables of a primitive type are errors.
+    pub fn resolve_call(&self, tokens: &[&Token], st: &SymbolTable) -> Result<Call<'_>, String> {
+        let (class, name, receiver) = match tokens {
+            [Token::Identifier(name), Token::Symbol('('), ..] => {
+                (st.class_name().to_string(), name, Receiver::This)
+            }
+            [Token::Identifier(qualifier), Token::Symbol('.'), Token::Identifier(name), ..] => {
+                match st.get(qualifier).map(|x| x.dtype()) {
+                    Ok(Datatype::Custom(class)) => (
+                        class.to_string(),
+                        name,
+                        Receiver::Variable(qualifier.to_string()),
+                    ),
+                    Ok(_) => {
+                        return Err(format!(
+                            "Cannot call method '{}' on '{}' of a primitive type",
+                            name, qualifier
+                        ))
+                    }
+                    Err(_) => (qualifier.to_string(), name, Receiver::Class),
+                }
+            }
+            _ => return Err("Invalid subroutine call".to_string()),
+        };
+        Ok(Call {
+            signature: self.signature(&class, name),
+            class,
+            name: name.to_string(),
+            receiver,
+        })
+    }
+}
@@ -0,0 +1,210 @@
+use super::os::os_class_names;
+use super::{class_name, identifiers, subroutine_name, subroutines, Category};
+use crate::parser::parse;
+use crate::tokenizer::is_valid_identifier;
+use crate::utils::{Span, SymbolKind, KEYWORDS};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents what an identifier refers to, which is the same across all of its occurrences
+enum Target {
+    // A variable, named by its class, the subroutine declaring it (for locals and arguments) and
+    // its name
+    Variable(String, Option<String>, String),
+    // A subroutine, named by its class and its name
+    Subroutine(String, String),
+    Class(String),
+}
+
+// Represents an occurrence of an identifier in one of the files, along with what it refers to
+struct Reference {
+    file: usize,
+    span: Span,
+    defined: bool,
+    target: Target,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents a range of source code in a file
+pub struct Location {
+    pub path: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents the new contents of a file changed by a rename, along with its new path if the
+// file is named after a renamed class
+pub struct FileEdit {
+    pub path: String,
+    pub new_path: Option<String>,
+    pub contents: String,
+}
+
+// Resolves the identifiers of all files, which are given as (path, contents) pairs
+fn references(files: &[(&str, &str)]) -> Result<Vec<Reference>, String> {
+    let mut res = vec![];
+    for (file, (path, contents)) in files.iter().enumerate() {
+        let class_grouping = parse(contents).map_err(|e| format!("{}:{}", path, e))?;
+        let class = class_name(&class_grouping).unwrap_or_default().to_string();
+        let subroutine_spans = subroutines(&class_grouping)
+            .into_iter()
+            .filter_map(|x| Some((subroutine_name(x)?.0, x.span())))
+            .collect::<Vec<_>>();
+        for id in identifiers(&class_grouping)? {
+            let target = match &id.category {
+                Category::Variable(Some(symbol)) => {
+                    let subroutine = match symbol.kind() {
+                        SymbolKind::Local | SymbolKind::Argument => subroutine_spans
+                            .iter()
+                            .find(|(_, span)| span.contains(id.span.start))
+                            .map(|(name, _)| name.to_string()),
+                        _ => None,
+                    };
+                    Target::Variable(class.clone(), subroutine, id.name.to_string())
+                }
+                Category::Variable(None) => continue,
+                Category::Class => Target::Class(id.name.to_string()),
+                Category::Subroutine => match &id.class {
+                    Some(class) => Target::Subroutine(class.clone(), id.name.to_string()),
+                    None => continue,
+                },
+            };
+            res.push(Reference {
+                file,
+                span: id.span,
+                defined: id.defined,
+                target,
+            });
+        }
+    }
+    Ok(res)
+}
+
+// Returns what the identifier at an offset of a file refers to
+fn target_at(
+    files: &[(&str, &str)],
+    references: &[Reference],
+    path: &str,
+    offset: usize,
+) -> Result<Target, String> {
+    let file = files
+        .iter()
+        .position(|(x, _)| *x == path)
+        .ok_or(format!("{} is not one of the files", path))?;
+    references
+        .iter()
+        .find(|x| x.file == file && x.span.contains(offset))
+        .map(|x| x.target.clone())
+        .ok_or("No symbol at the cursor".to_string())
+}
+
+// Returns the declarations and uses of the identifier at an offset of a file across all files,
+// which are given as (path, contents) pairs
+pub fn find_references(
+    files: &[(&str, &str)],
+    path: &str,
+    offset: usize,
+) -> Result<Vec<Location>, String> {
+    let references = references(files)?;
+    let target = target_at(files, &references, path, offset)?;
+    Ok(references
+        .iter()
+        .filter(|x| x.target == target)
+        .map(|x| Location {
+            path: files[x.file].0.to_string(),
+            span: x.span,
+        })
+        .collect())
+}
+
+// Renames the identifier at an offset of a file across all files, which are given as
+// (path, contents) pairs, returning the files that change. Renaming a class also renames the
+// file named after it.
+pub fn rename(
+    files: &[(&str, &str)],
+    path: &str,
+    offset: usize,
+    new_name: &str,
+) -> Result<Vec<FileEdit>, String> {
+    if KEYWORDS.contains(&new_name) {
+        return Err(format!("'{}' is a keyword", new_name));
+    }
+    if !is_valid_identifier(new_name) {
+        return Err(format!("'{}' is not a valid identifier", new_name));
+    }
+    let references = references(files)?;
+    let target = target_at(files, &references, path, offset)?;
+    let (old_name, collides) = {
+        let declared = |target: &dyn Fn(&Target) -> bool| {
+            references.iter().any(|x| x.defined && target(&x.target))
+        };
+        match &target {
+            // renaming a field or static must not shadow it with a local or argument either
+            Target::Variable(class, subroutine, name) => (
+                name,
+                declared(&|x| match x {
+                    Target::Variable(c, s, n) => {
+                        c == class
+                            && n == new_name
+                            && (subroutine.is_none() || s.is_none() || s == subroutine)
+                    }
+                    _ => false,
+                }),
+            ),
+            Target::Subroutine(class, name) => (
+                name,
+                declared(&|x| matches!(x, Target::Subroutine(c, n) if c == class && n == new_name)),
+            ),
+            Target::Class(name) => (
+                name,
+                declared(&|x| matches!(x, Target::Class(n) if n == new_name))
+                    || os_class_names().contains(&new_name),
+            ),
+        }
+    };
+    if !references.iter().any(|x| x.defined && x.target == target) {
+        return Err(format!("'{}' is not declared in the program", old_name));
+    }
+    if collides {
+        return Err(format!(
+            "Cannot rename '{}' to '{}', which is already declared in the same scope",
+            old_name, new_name
+        ));
+    }
+
+    let mut res = vec![];
+    for (file, (path, contents)) in files.iter().enumerate() {
+        let mut spans = references
+            .iter()
+            .filter(|x| x.file == file && x.target == target)
+            .map(|x| x.span)
+            .collect::<Vec<_>>();
+        if spans.is_empty() {
+            continue;
+        }
+        spans.sort_by_key(|x| x.start);
+        let mut contents = contents.to_string();
+        for span in spans.iter().rev() {
+            contents.replace_range(span.start..span.end, new_name);
+        }
+        let new_path = match &target {
+            Target::Class(name)
+                if Path::new(path).file_stem().and_then(|x| x.to_str()) == Some(name) =>
+            {
+                Some(
+                    Path::new(path)
+                        .with_file_name(format!("{}.jack", new_name))
+                        .to_string_lossy()
+                        .to_string(),
+                )
+            }
+            _ => None,
+        };
+        res.push(FileEdit {
+            path: path.to_string(),
+            new_path,
+            contents,
+        });
+    }
+    Ok(res)
+}
@@ -0,0 +1,132 @@
+use super::{class_name, subroutine_name, subroutines};
+use crate::utils::{Diagnostic, GroupItem, Grouping, Span, Token};
+
+// Returns the return statements of a statements grouping, including nested ones
+pub fn return_statements(statements: &Grouping) -> Vec<&Grouping> {
+    let mut res = vec![];
+    for statement in statements.subgroupings() {
+        match statement.name {
+            "returnStatement" => res.push(statement),
+            _ => {
+                for block in statement
+                    .subgroupings()
+                    .into_iter()
+                    .filter(|x| x.name == "statements")
+                {
+                    res.extend(return_statements(block));
+                }
+            }
+        }
+    }
+    res
+}
+
+// Checks if an expression is only the given keyword constant
+pub fn is_keyword(expression: &Grouping, keyword: &str) -> bool {
+    match expression.items.as_slice() {
+        [GroupItem::Grouping(term)] => {
+            matches!(term.tokens()[..], [Token::Keyword(k)] if *k == keyword)
+        }
+        _ => false,
+    }
+}
+
+// Checks if no control path through a statement reaches the statement after it
+pub fn never_completes(statement: &Grouping) -> bool {
+    let blocks = statement.subgroupings();
+    match statement.name {
+        "returnStatement" => true,
+        // an if statement only returns on every path if it has an else branch
+        "ifStatement" => {
+            blocks.len() == 3 && always_returns(blocks[1]) && always_returns(blocks[2])
+        }
+        // only while (true) can't be left by its condition becoming false
+        "whileStatement" => is_keyword(blocks[0], "true"),
+        _ => false,
+    }
+}
+
+// Checks if every control path through the statements ends in a return statement
+fn always_returns(statements: &Grouping) -> bool {
+    statements
+        .subgroupings()
+        .iter()
+        .any(|statement| never_completes(statement))
+}
+
+// Checks the return statements of the subroutines of a class against their kinds and return
+// types
+pub fn check_returns(class_grouping: &Grouping) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let class = class_name(class_grouping).unwrap_or_default();
+    for subroutine in subroutines(class_grouping) {
+        let (name, name_span) = match subroutine_name(subroutine) {
+            Some(name) => name,
+            None => continue,
+        };
+        let tokens = subroutine.spanned_tokens();
+        let kind = tokens[0].0.as_text();
+        let (return_type, type_span) = (tokens[1].0.as_text(), tokens[1].1);
+        let statements = match subroutine.subgroupings()[1]
+            .subgroupings()
+            .into_iter()
+            .find(|x| x.name == "statements")
+        {
+            Some(statements) => statements,
+            None => continue,
+        };
+
+        if kind == "constructor" && return_type != class {
+            diagnostics.push(Diagnostic::error(
+                "constructor-type",
+                format!(
+                    "Constructor '{}' must return {}, not {}",
+                    name, class, return_type
+                ),
+                type_span,
+            ));
+        }
+        for statement in return_statements(statements) {
+            match (statement.subgroupings().first(), return_type.as_str()) {
+                (Some(value), "void") => diagnostics.push(Diagnostic::error(
+                    "void-return-value",
+                    format!("Void subroutine '{}' cannot return a value", name),
+                    value.span(),
+                )),
+                (None, "void") => (),
+                (None, _) => diagnostics.push(Diagnostic::error(
+                    "missing-return-value",
+                    format!("'{}' must return a value of type {}", name, return_type),
+                    statement.span(),
+                )),
+                (Some(value), _) if kind == "constructor" && !is_keyword(value, "this") => {
+                    diagnostics.push(Diagnostic::error(
+                        "constructor-return",
+                        format!("Constructor '{}' must return this", name),
+                        value.span(),
+                    ))
+                }
+                _ => (),
+            }
+        }
+        if return_type != "void" && !always_returns(statements) {
+            let end = subroutine.span().end;
+            diagnostics.push(
+                Diagnostic::error(
+                    "missing-return",
+                    format!(
+                        "Not every path through '{}' returns a value of type {}",
+                        name, return_type
+                    ),
+                    name_span,
+                )
+                .with_note(
+                    "the end of the subroutine is reachable".to_string(),
+                    Some(Span::new(end - 1, end)),
+                ),
+            );
+        }
+    }
+    diagnostics.sort_by_key(|x| x.span.start);
+    diagnostics
+}
@@ -0,0 +1,442 @@
+use super::program::{Program, Receiver, SubroutineKind};
+use super::subroutines;
+use crate::utils::symbol_table::{create_subroutine_symbol_table, create_symbol_table};
+use crate::utils::{Datatype, Diagnostic, Grouping, Span, SymbolTable, Token};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Represents how strictly types are compared
+pub enum TypeMode {
+    // Allows the conversions Jack programs rely on: between int and char, between int and any
+    // object, and between Array and any object
+    Lenient,
+    // Only allows values of the expected type, or null for objects
+    Strict,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents the type of an expression
+pub enum Type {
+    Int,
+    Char,
+    Boolean,
+    Void,
+    Null,
+    Class(String),
+    // The type of expressions which can't be typed, e.g. array elements
+    Unknown,
+}
+
+impl Type {
+    // Returns the type named in a declaration
+    pub fn from_name(name: &str) -> Type {
+        match name {
+            "int" => Type::Int,
+            "char" => Type::Char,
+            "boolean" => Type::Boolean,
+            "void" => Type::Void,
+            _ => Type::Class(name.to_string()),
+        }
+    }
+
+    // Returns the type of a symbol
+    pub fn from_datatype(dtype: &Datatype) -> Type {
+        match dtype {
+            Datatype::Int => Type::Int,
+            Datatype::Char => Type::Char,
+            Datatype::Boolean => Type::Boolean,
+            Datatype::Custom(name) => Type::Class(name.to_string()),
+        }
+    }
+
+    // Checks if a value of this type can be used where a value of another type is expected
+    fn assignable_to(&self, to: &Type, mode: TypeMode) -> bool {
+        match (self, to) {
+            (Type::Unknown, _) | (_, Type::Unknown) => true,
+            (a, b) if a == b => true,
+            (Type::Null, Type::Class(_)) => true,
+            _ if mode == TypeMode::Strict => false,
+            (Type::Null, Type::Int | Type::Char) => true,
+            (Type::Int, Type::Char) | (Type::Char, Type::Int) => true,
+            (Type::Int, Type::Class(_)) | (Type::Class(_), Type::Int) => true,
+            (Type::Class(a), Type::Class(b)) => a == "Array" || b == "Array",
+            _ => false,
+        }
+    }
+
+    // Checks if a value of this type can be used as a number
+    fn is_integer(&self, mode: TypeMode) -> bool {
+        self.assignable_to(&Type::Int, mode)
+    }
+
+    // Checks if a value of this type can be indexed as an array
+    fn is_indexable(&self, mode: TypeMode) -> bool {
+        match self {
+            Type::Unknown => true,
+            Type::Class(c) if c == "Array" => true,
+            Type::Class(_) | Type::Int => mode == TypeMode::Lenient,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Char => write!(f, "char"),
+            Type::Boolean => write!(f, "boolean"),
+            Type::Void => write!(f, "void"),
+            Type::Null => write!(f, "null"),
+            Type::Class(name) => write!(f, "{}", name),
+            Type::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+// Infers the types of the expressions of a class and checks them against their uses
+struct TypeChecker<'a> {
+    class_name: &'a str,
+    program: Program,
+    mode: TypeMode,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl TypeChecker<'_> {
+    // Reports a type error
+    fn error(&mut self, code: &'static str, message: String, span: Span) {
+        self.diagnostics
+            .push(Diagnostic::error(code, message, span));
+    }
+
+    // Reports an operand of an operator which isn't a number
+    fn integer_operand(&mut self, op: char, operand: &Type, span: Span) {
+        if !operand.is_integer(self.mode) {
+            self.error(
+                "operand-type",
+                format!("Operator '{}' expects int, found {}", op, operand),
+                span,
+            );
+        }
+    }
+
+    // Returns the type of an expression grouping
+    fn expression(&mut self, expression: &Grouping, st: &SymbolTable) -> Type {
+        let terms = expression.subgroupings();
+        let ops = expression.tokens();
+        let mut lhs = self.term(terms[0], st);
+        let mut lhs_span = terms[0].span();
+        for (op, term) in ops.iter().zip(&terms[1..]) {
+            let rhs = self.term(term, st);
+            let rhs_span = term.span();
+            let op = match op {
+                Token::Symbol(op) => *op,
+                _ => continue,
+            };
+            lhs = match op {
+                '+' | '-' | '*' | '/' | '<' | '>' => {
+                    self.integer_operand(op, &lhs, lhs_span);
+                    self.integer_operand(op, &rhs, rhs_span);
+                    if matches!(op, '<' | '>') {
+                        Type::Boolean
+                    } else {
+                        Type::Int
+                    }
+                }
+                '&' | '|' if lhs == Type::Boolean || rhs == Type::Boolean => {
+                    for (operand, span) in [(&lhs, lhs_span), (&rhs, rhs_span)] {
+                        if !operand.assignable_to(&Type::Boolean, self.mode) {
+                            self.error(
+                                "operand-type",
+                                format!("Operator '{}' expects boolean, found {}", op, operand),
+                                span,
+                            );
+                        }
+                    }
+                    Type::Boolean
+                }
+                '&' | '|' => {
+                    self.integer_operand(op, &lhs, lhs_span);
+                    self.integer_operand(op, &rhs, rhs_span);
+                    Type::Int
+                }
+                _ => {
+                    if !lhs.assignable_to(&rhs, self.mode) && !rhs.assignable_to(&lhs, self.mode) {
+                        self.error(
+                            "operand-type",
+                            format!("Cannot compare {} with {}", lhs, rhs),
+                            lhs_span.cover(rhs_span),
+                        );
+                    }
+                    Type::Boolean
+                }
+            };
+            lhs_span = lhs_span.cover(rhs_span);
+        }
+        lhs
+    }
+
+    // Returns the type of a term grouping
+    fn term(&mut self, term: &Grouping, st: &SymbolTable) -> Type {
+        let tokens = term.spanned_tokens();
+        let subgroupings = term.subgroupings();
+        match tokens[0].0 {
+            Token::IntConst(_) => Type::Int,
+            Token::StringConst(_) => Type::Class("String".to_string()),
+            Token::Keyword("true") | Token::Keyword("false") => Type::Boolean,
+            Token::Keyword("null") => Type::Null,
+            Token::Keyword("this") => Type::Class(self.class_name.to_string()),
+            Token::Symbol('(') => self.expression(subgroupings[0], st),
+            Token::Symbol('-') => {
+                let operand = self.term(subgroupings[0], st);
+                self.integer_operand('-', &operand, subgroupings[0].span());
+                Type::Int
+            }
+            Token::Symbol('~') => match self.term(subgroupings[0], st) {
+                Type::Boolean => Type::Boolean,
+                operand => {
+                    self.integer_operand('~', &operand, subgroupings[0].span());
+                    Type::Int
+                }
+            },
+            Token::Identifier(name) => match tokens.get(1) {
+                Some((Token::Symbol('['), _)) => {
+                    self.array_access(name, tokens[0].1, subgroupings[0], st);
+                    Type::Unknown
+                }
+                Some((Token::Symbol('(' | '.'), _)) => self.call(&tokens, subgroupings[0], st),
+                _ => match st.get(name) {
+                    Ok(symbol) => Type::from_datatype(symbol.dtype()),
+                    Err(_) => Type::Unknown,
+                },
+            },
+            _ => Type::Unknown,
+        }
+    }
+
+    // Checks an array access of the variable name[index]
+    fn array_access(&mut self, name: &str, span: Span, index: &Grouping, st: &SymbolTable) {
+        let base = match st.get(name) {
+            Ok(symbol) => Type::from_datatype(symbol.dtype()),
+            Err(_) => Type::Unknown,
+        };
+        if !base.is_indexable(self.mode) {
+            self.error(
+                "index-type",
+                format!("Cannot index '{}' of type {}", name, base),
+                span,
+            );
+        }
+        let index_type = self.expression(index, st);
+        if !index_type.is_integer(self.mode) {
+            self.error(
+                "index-type",
+                format!("Array index must be int, found {}", index_type),
+                index.span(),
+            );
+        }
+    }
+
+    // Checks the arguments of a subroutine call written as the tokens f(...) or x.f(...),
+    // returning the type of its return value
+    fn call(&mut self, tokens: &[(&Token, Span)], arguments: &Grouping, st: &SymbolTable) -> Type {
+        let arguments = arguments
+            .subgroupings()
+            .into_iter()
+            .map(|x| (self.expression(x, st), x.span()))
+            .collect::<Vec<_>>();
+        let (class, (name, span), receiver) = match (tokens[0], tokens.get(1), tokens.get(2)) {
+            ((Token::Identifier(name), span), Some((Token::Symbol('('), _)), _) => {
+                (self.class_name.to_string(), (name, span), Receiver::This)
+            }
+            (
+                (Token::Identifier(qualifier), _),
+                Some((Token::Symbol('.'), _)),
+                Some((Token::Identifier(name), span)),
+            ) => match st.get(qualifier) {
+                Ok(symbol) => match symbol.dtype() {
+                    Datatype::Custom(class) => (
+                        class.to_string(),
+                        (name, *span),
+                        Receiver::Variable(qualifier.to_string()),
+                    ),
+                    dtype => {
+                        self.error(
+                            "method-on-primitive",
+                            format!(
+                                "Cannot call method '{}' on '{}' of type {}",
+                                name,
+                                qualifier,
+                                Type::from_datatype(dtype)
+                            ),
+                            *span,
+                        );
+                        return Type::Unknown;
+                    }
+                },
+                Err(_) => (qualifier.to_string(), (name, *span), Receiver::Class),
+            },
+            _ => return Type::Unknown,
+        };
+        // calls of undefined subroutines of the class itself are reported with its declarations
+        let signature = match self.program.signature(&class, name).cloned() {
+            Some(signature) => signature,
+            None if receiver != Receiver::This && self.program.class(&class).is_some() => {
+                self.error(
+                    "undefined-subroutine",
+                    format!("Class {} has no subroutine '{}'", class, name),
+                    span,
+                );
+                return Type::Unknown;
+            }
+            None => return Type::Unknown,
+        };
+        if receiver == Receiver::Class && signature.kind == SubroutineKind::Method {
+            let message = format!(
+                "'{}.{}' is a method and must be called on an object",
+                class, name
+            );
+            self.error("method-without-object", message, span);
+        }
+        let return_type = signature.return_type.clone();
+        if signature.parameters.len() != arguments.len() {
+            let message = format!(
+                "'{}.{}' expects {} arguments, found {}",
+                class,
+                name,
+                signature.parameters.len(),
+                arguments.len()
+            );
+            self.error("argument-count", message, span);
+            return return_type;
+        }
+        let mismatches = signature
+            .parameters
+            .iter()
+            .zip(&arguments)
+            .enumerate()
+            .filter(|(_, (parameter, (argument, _)))| !argument.assignable_to(parameter, self.mode))
+            .map(|(i, (parameter, (argument, span)))| {
+                let message = format!(
+                    "Argument {} of '{}.{}' expects {}, found {}",
+                    i + 1,
+                    class,
+                    name,
+                    parameter,
+                    argument
+                );
+                (message, *span)
+            })
+            .collect::<Vec<_>>();
+        for (message, span) in mismatches {
+            self.error("argument-type", message, span);
+        }
+        return_type
+    }
+
+    // Checks a condition of an if or while statement
+    fn condition(&mut self, condition: &Grouping, st: &SymbolTable) {
+        let t = self.expression(condition, st);
+        if !t.assignable_to(&Type::Boolean, self.mode) {
+            self.error(
+                "condition-type",
+                format!("Condition must be boolean, found {}", t),
+                condition.span(),
+            );
+        }
+    }
+
+    // Checks the statements of a statements grouping
+    fn statements(&mut self, statements: &Grouping, st: &SymbolTable, return_type: &Type) {
+        for statement in statements.subgroupings() {
+            let tokens = statement.spanned_tokens();
+            let subgroupings = statement.subgroupings();
+            match statement.name {
+                "letStatement" => {
+                    let name = tokens[1].0.as_text();
+                    if subgroupings.len() == 2 {
+                        self.array_access(&name, tokens[1].1, subgroupings[0], st);
+                        self.expression(subgroupings[1], st);
+                        continue;
+                    }
+                    let value = self.expression(subgroupings[0], st);
+                    if let Ok(symbol) = st.get(&name) {
+                        let variable = Type::from_datatype(symbol.dtype());
+                        if !value.assignable_to(&variable, self.mode) {
+                            self.error(
+                                "type-mismatch",
+                                format!(
+                                    "Cannot assign {} to '{}' of type {}",
+                                    value, name, variable
+                                ),
+                                subgroupings[0].span(),
+                            );
+                        }
+                    }
+                }
+                "ifStatement" | "whileStatement" => {
+                    self.condition(subgroupings[0], st);
+                    for block in &subgroupings[1..] {
+                        self.statements(block, st, return_type);
+                    }
+                }
+                "doStatement" => {
+                    self.call(&tokens[1..], subgroupings[0], st);
+                }
+                "returnStatement" => {
+                    if let Some(value) = subgroupings.first() {
+                        let t = self.expression(value, st);
+                        if *return_type != Type::Void && !t.assignable_to(return_type, self.mode) {
+                            self.error(
+                                "return-type",
+                                format!(
+                                    "Cannot return {} from a subroutine returning {}",
+                                    t, return_type
+                                ),
+                                value.span(),
+                            );
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+// Checks the types of the expressions and statements of a class. The other classes of the
+// program are given to check calls to their subroutines; the OS classes are always known.
+pub fn check_types(
+    class_grouping: &Grouping,
+    program: &[&Grouping],
+    mode: TypeMode,
+) -> Vec<Diagnostic> {
+    let class_symbol_table = match create_symbol_table(class_grouping) {
+        Ok(st) => st,
+        Err(_) => return vec![],
+    };
+    let mut checker = TypeChecker {
+        class_name: class_symbol_table.class_name(),
+        program: Program::new(&[&[class_grouping], program].concat()),
+        mode,
+        diagnostics: vec![],
+    };
+    for subroutine in subroutines(class_grouping) {
+        let st = match create_subroutine_symbol_table(subroutine, &class_symbol_table) {
+            Ok(st) => st,
+            Err(_) => continue,
+        };
+        let return_type = Type::from_name(&subroutine.tokens()[1].as_text());
+        let body = subroutine.subgroupings()[1];
+        if let Some(statements) = body
+            .subgroupings()
+            .into_iter()
+            .find(|x| x.name == "statements")
+        {
+            checker.statements(statements, &st, &return_type);
+        }
+    }
+    let mut diagnostics = checker.diagnostics;
+    diagnostics.sort_by_key(|x| x.span.start);
+    diagnostics
+}
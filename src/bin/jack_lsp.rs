@@ -0,0 +1,16 @@
+use std::io;
+use std::process::ExitCode;
+
+use lib::internal::serve_lsp;
+
+fn main() -> ExitCode {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    match serve_lsp(&mut stdin.lock(), &mut stdout.lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("jack-lsp: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
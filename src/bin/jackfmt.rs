@@ -0,0 +1,81 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use lib::internal::{format_source, jack_files};
+
+static USAGE: &str = "Usage: jackfmt [--check] [FILE_OR_DIRECTORY ...]
+Formats .jack files in place, or stdin to stdout when no paths are given.
+With --check, files are left untouched and the exit code is 1 if any file is not formatted.";
+
+// Formats a single file, returning whether it was already formatted
+fn format_file(path: &Path, check: bool) -> Result<bool, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let formatted = format_source(&contents)?;
+    if formatted == contents {
+        return Ok(true);
+    }
+    if check {
+        println!("{} is not formatted", path.display());
+    } else {
+        fs::write(path, formatted).map_err(|e| e.to_string())?;
+    }
+    Ok(false)
+}
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.iter().any(|x| x == "-h" || x == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let check = args.iter().any(|x| x == "--check");
+    let paths = args.iter().filter(|x| *x != "--check").collect::<Vec<_>>();
+
+    if paths.is_empty() {
+        let mut contents = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut contents) {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+        return match format_source(&contents) {
+            Ok(formatted) if check => ExitCode::from((formatted != contents) as u8),
+            Ok(formatted) => {
+                io::stdout().write_all(formatted.as_bytes()).unwrap();
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("error: {}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let mut success = true;
+    for path in paths {
+        let files = match jack_files(Path::new(path)) {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("error: {}: {}", path, e);
+                success = false;
+                continue;
+            }
+        };
+        for file in files {
+            match format_file(&file, check) {
+                Ok(formatted) => success &= formatted || !check,
+                Err(e) => {
+                    eprintln!("error: {}: {}", file.display(), e);
+                    success = false;
+                }
+            }
+        }
+    }
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
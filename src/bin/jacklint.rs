@@ -0,0 +1,143 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use lib::internal::{jack_files, lint, parse_class, Json, LintConfig, RULES};
+
+static USAGE: &str = "Usage: jacklint [OPTIONS] [FILE_OR_DIRECTORY ...]
+Checks .jack files for suspicious code, in the current directory when no paths are given.
+The exit code is 1 if any warnings or errors were found.
+
+Options:
+  --format human|json   How to print the results (default: human)
+  --config PATH         Read the enabled rules from PATH (default: ./jacklint.toml if present)
+  --enable RULE         Enable a rule
+  --disable RULE        Disable a rule
+  --list-rules          Print the available rules";
+
+static DEFAULT_CONFIG: &str = "jacklint.toml";
+
+// Represents the parsed command line
+struct Options {
+    json: bool,
+    config: LintConfig,
+    paths: Vec<String>,
+}
+
+// Parses the command line arguments, applying --enable and --disable after the config file
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut json = false;
+    let mut config_path = None;
+    let mut toggles = vec![];
+    let mut paths = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--format" => match value()?.as_str() {
+                "human" => json = false,
+                "json" => json = true,
+                format => return Err(format!("Unknown format '{}'", format)),
+            },
+            "--config" => config_path = Some(PathBuf::from(value()?)),
+            "--enable" => toggles.push((value()?, true)),
+            "--disable" => toggles.push((value()?, false)),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option '{}'", arg)),
+            _ => paths.push(arg.clone()),
+        }
+    }
+    let config_path = config_path.or_else(|| {
+        let default = PathBuf::from(DEFAULT_CONFIG);
+        default.is_file().then_some(default)
+    });
+    let mut config = match config_path {
+        Some(path) => {
+            let contents =
+                fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            LintConfig::from_toml(&contents).map_err(|e| format!("{}: {}", path.display(), e))?
+        }
+        None => LintConfig::default(),
+    };
+    for (name, enabled) in toggles {
+        config.set_enabled(name, enabled)?;
+    }
+    if paths.is_empty() {
+        paths.push(".".to_string());
+    }
+    Ok(Options {
+        json,
+        config,
+        paths,
+    })
+}
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.iter().any(|x| x == "-h" || x == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    if args.iter().any(|x| x == "--list-rules") {
+        for rule in RULES {
+            println!("{:<20}{}", rule.name, rule.description);
+        }
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut sources = vec![];
+    for path in &options.paths {
+        let files = match jack_files(Path::new(path)) {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("error: {}: {}", path, e);
+                return ExitCode::from(2);
+            }
+        };
+        for file in files {
+            match fs::read_to_string(&file) {
+                Ok(contents) => sources.push((file.display().to_string(), contents)),
+                Err(e) => {
+                    eprintln!("error: {}: {}", file.display(), e);
+                    return ExitCode::from(2);
+                }
+            }
+        }
+    }
+
+    // every class of the program is needed to know what the subroutines return
+    let classes = sources
+        .iter()
+        .filter_map(|(_, contents)| parse_class(contents).ok())
+        .collect::<Vec<_>>();
+    let program = classes.iter().collect::<Vec<_>>();
+
+    let mut found = false;
+    let mut results = vec![];
+    for (file, contents) in &sources {
+        let diagnostics = lint(contents, &program, &options.config).unwrap_or_else(|e| vec![e]);
+        found |= !diagnostics.is_empty();
+        for diagnostic in diagnostics {
+            if options.json {
+                results.push(diagnostic.to_json(file, contents));
+            } else {
+                println!("{}", diagnostic.render(file, contents));
+            }
+        }
+    }
+    if options.json {
+        println!("{}", Json::Array(results));
+    }
+    if found {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
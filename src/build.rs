@@ -0,0 +1,392 @@
+use crate::analysis::{identifiers, Category, ClassInterface, Program};
+use crate::codewriter::{compile_class_with, CodegenOptions};
+use crate::parser::parse;
+use crate::utils::{default_jobs, parallel_map, Grouping, Json};
+use crate::vfs::{DiskFiles, FileSystem};
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Returns the 64-bit FNV-1a hash of a text, which unlike the std hashers is the same in every
+// run and on every platform
+pub fn content_hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents why a file was compiled, or that it was up to date
+pub enum Rebuild {
+    Fresh,
+    NotCached,
+    SourceChanged,
+    // The interface of a class the file uses changed, or the class was added or removed
+    InterfaceChanged(String),
+    OptionsChanged,
+}
+
+impl fmt::Display for Rebuild {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rebuild::Fresh => write!(f, "up to date"),
+            Rebuild::NotCached => write!(f, "not in the build cache"),
+            Rebuild::SourceChanged => write!(f, "source changed"),
+            Rebuild::InterfaceChanged(class) => write!(f, "interface of {} changed", class),
+            Rebuild::OptionsChanged => write!(f, "code generation options changed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+// Represents what happened to one .jack file during a build
+pub struct BuildStep {
+    pub source: PathBuf,
+    pub output: PathBuf,
+    pub reason: Rebuild,
+    // The number of VM commands of the output, and the number before it was optimized
+    pub commands: usize,
+    pub unoptimized_commands: usize,
+}
+
+impl fmt::Display for BuildStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reason {
+            Rebuild::Fresh => write!(f, "Fresh {}", self.source.display()),
+            _ => write!(f, "Compiled {} ({})", self.source.display(), self.reason),
+        }
+    }
+}
+
+#[derive(Clone)]
+// Represents the result of compiling a .jack file, stored in the build cache
+struct CacheEntry {
+    // The source file, which is known by its canonical path
+    source: PathBuf,
+    hash: String,
+    interface: ClassInterface,
+    // The classes the file uses, with the hash of their interface when it was compiled. Classes
+    // which weren't part of the build have an empty hash.
+    dependencies: Vec<(String, String)>,
+    // The code generation options the file was compiled with
+    options: Json,
+    // The .vm and .vm.map files written for the file
+    outputs: Vec<PathBuf>,
+    vm: String,
+    source_map: Option<String>,
+    commands: usize,
+    unoptimized_commands: usize,
+}
+
+impl CacheEntry {
+    fn to_json(&self) -> Json {
+        let dependencies = self
+            .dependencies
+            .iter()
+            .map(|(class, hash)| Json::Array(vec![class.as_str().into(), hash.as_str().into()]))
+            .collect::<Vec<_>>();
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|x| Json::string(&x.to_string_lossy()))
+            .collect::<Vec<_>>();
+        let mut members = vec![
+            ("source", self.source.to_string_lossy().as_ref().into()),
+            ("hash", self.hash.as_str().into()),
+            ("interface", self.interface.to_json()),
+            ("dependencies", dependencies.into()),
+            ("options", self.options.clone()),
+            ("outputs", outputs.into()),
+            ("vm", self.vm.as_str().into()),
+            ("commands", self.commands.into()),
+            ("unoptimizedCommands", self.unoptimized_commands.into()),
+        ];
+        if let Some(source_map) = &self.source_map {
+            members.push(("sourceMap", source_map.as_str().into()));
+        }
+        Json::object(members)
+    }
+
+    fn from_json(json: &Json) -> Option<CacheEntry> {
+        let dependencies = json
+            .get("dependencies")?
+            .as_array()?
+            .iter()
+            .map(|x| match x.as_array()?.as_slice() {
+                [class, hash] => Some((class.as_str()?.to_string(), hash.as_str()?.to_string())),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let outputs = json
+            .get("outputs")?
+            .as_array()?
+            .iter()
+            .map(|x| Some(PathBuf::from(x.as_str()?)))
+            .collect::<Option<Vec<_>>>()?;
+        Some(CacheEntry {
+            source: PathBuf::from(json.get("source")?.as_str()?),
+            hash: json.get("hash")?.as_str()?.to_string(),
+            interface: ClassInterface::from_json(json.get("interface")?)?,
+            dependencies,
+            options: json.get("options")?.clone(),
+            outputs,
+            vm: json.get("vm")?.as_str()?.to_string(),
+            source_map: match json.get("sourceMap") {
+                Some(x) => Some(x.as_str()?.to_string()),
+                None => None,
+            },
+            commands: json.get("commands")?.as_usize()?,
+            unoptimized_commands: json.get("unoptimizedCommands")?.as_usize()?,
+        })
+    }
+}
+
+// Returns the path of the cache entry of a source file, named after the file and its path
+fn entry_path(files: &dyn FileSystem, cache_dir: &Path, source: &Path) -> PathBuf {
+    let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+    let path = files.canonicalize(source);
+    cache_dir.join(format!(
+        "{}.{}.json",
+        stem,
+        content_hash(&path.to_string_lossy())
+    ))
+}
+
+// Removes the cache entries of source files which no longer exist, and the outputs written for
+// them. Entries of files which still exist are kept even when they aren't part of this build.
+fn prune(files: &dyn FileSystem, cache_dir: &Path, sources: &[PathBuf]) -> Result<(), String> {
+    let error = |path: &Path, e: io::Error| format!("{}: {}", path.display(), e);
+    let current = sources
+        .iter()
+        .map(|x| entry_path(files, cache_dir, x))
+        .collect::<Vec<_>>();
+    let paths = files.read_dir(cache_dir).map_err(|e| error(cache_dir, e))?;
+    for path in paths {
+        if current.contains(&path) || path.extension().and_then(|x| x.to_str()) != Some("json") {
+            continue;
+        }
+        let entry = files
+            .read(&path)
+            .ok()
+            .and_then(|x| CacheEntry::from_json(&Json::parse(&x).ok()?));
+        let Some(entry) = entry else { continue };
+        if files.read(&entry.source).is_ok() {
+            continue;
+        }
+        for output in entry.outputs.iter().chain([&path]) {
+            match files.remove_file(output) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(error(output, e)),
+                _ => (),
+            }
+        }
+    }
+    Ok(())
+}
+
+// Returns the names of the other classes a class refers to
+fn referenced_classes(class_grouping: &Grouping, own_name: &str) -> Vec<String> {
+    let mut res = identifiers(class_grouping)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|x| match x.category {
+            Category::Class => Some(x.name.to_string()),
+            Category::Subroutine => x.class,
+            Category::Variable(_) => None,
+        })
+        .filter(|x| x != own_name)
+        .collect::<Vec<_>>();
+    res.sort();
+    res.dedup();
+    res
+}
+
+#[derive(Debug, Clone)]
+// Represents the settings of a build
+pub struct BuildOptions {
+    // Where the .vm files are written, next to their sources if not set
+    pub out_dir: Option<PathBuf>,
+    pub cache_dir: PathBuf,
+    // The number of files compiled at the same time
+    pub jobs: usize,
+    pub codegen: CodegenOptions,
+}
+
+impl BuildOptions {
+    // Create build options with a cache directory, using all available CPUs
+    pub fn new(cache_dir: PathBuf) -> Self {
+        BuildOptions {
+            out_dir: None,
+            cache_dir,
+            jobs: default_jobs(),
+            codegen: CodegenOptions::default(),
+        }
+    }
+}
+
+// Joins the errors of the files of a build, which are in the order of the files
+fn collect_errors<T>(results: Vec<Result<T, String>>) -> Result<Vec<T>, String> {
+    let (ok, errors): (Vec<_>, Vec<_>) = results.into_iter().partition(|x| x.is_ok());
+    if !errors.is_empty() {
+        return Err(errors
+            .into_iter()
+            .filter_map(|x| x.err())
+            .collect::<Vec<_>>()
+            .join("\n"));
+    }
+    Ok(ok.into_iter().flatten().collect())
+}
+
+// Compiles .jack files as one program, writing the .vm file of each to the output directory or
+// next to the source. A build cache directory keeps the VM and class interface of every file,
+// so that a file is only compiled again when it changed or when the interface of a class it uses
+// changed. The outputs and cache entries of source files which were deleted are removed.
+//
+// Once the interfaces of all classes are known, the files are compiled independently on a pool
+// of worker threads. The results and errors are in the order of the files, so the outcome is
+// the same as compiling the files one after another.
+pub fn build(sources: &[PathBuf], options: &BuildOptions) -> Result<Vec<BuildStep>, String> {
+    build_in(&DiskFiles, sources, options)
+}
+
+// Builds .jack files of a file system, reading and writing the sources, outputs and build cache
+// through it
+pub fn build_in(
+    files: &dyn FileSystem,
+    sources: &[PathBuf],
+    options: &BuildOptions,
+) -> Result<Vec<BuildStep>, String> {
+    let error = |path: &Path, e: String| format!("{}: {}", path.display(), e);
+    let cache_dir = options.cache_dir.as_path();
+    let out_dir = options.out_dir.as_deref();
+    for dir in [Some(cache_dir), out_dir].into_iter().flatten() {
+        files
+            .create_dir_all(dir)
+            .map_err(|e| error(dir, e.to_string()))?;
+    }
+    prune(files, cache_dir, sources)?;
+    let contents = collect_errors(
+        sources
+            .iter()
+            .map(|x| files.read(x).map_err(|e| error(x, e.to_string())))
+            .collect(),
+    )?;
+    let hashes = contents.iter().map(|x| content_hash(x)).collect::<Vec<_>>();
+    let entries = sources
+        .iter()
+        .map(|x| {
+            let json = files.read(&entry_path(files, cache_dir, x)).ok()?;
+            CacheEntry::from_json(&Json::parse(&json).ok()?)
+        })
+        .collect::<Vec<_>>();
+
+    // only files which changed are parsed to find the interfaces of the classes
+    let parsed = collect_errors(parallel_map(
+        (0..sources.len()).collect(),
+        options.jobs,
+        |i| match &entries[i] {
+            Some(entry) if entry.hash == hashes[i] => Ok((None, entry.interface.clone())),
+            _ => {
+                let class_grouping = parse(&contents[i]).map_err(|e| error(&sources[i], e))?;
+                let interface = ClassInterface::from_grouping(&class_grouping);
+                Ok((Some(class_grouping), interface))
+            }
+        },
+    ))?;
+    let (groupings, interfaces): (Vec<_>, Vec<_>) = parsed.into_iter().unzip();
+    let interface_hashes = interfaces
+        .iter()
+        .map(|x| (x.name.clone(), content_hash(&x.to_json().to_string())))
+        .collect::<HashMap<_, _>>();
+    let interface_hash = |class: &str| interface_hashes.get(class).cloned().unwrap_or_default();
+    let program = Program::from_interfaces(interfaces.clone());
+    let codegen_options = options.codegen.to_json();
+
+    let jobs = groupings.into_iter().enumerate().collect::<Vec<_>>();
+    collect_errors(parallel_map(jobs, options.jobs, |(i, class_grouping)| {
+        let source = &sources[i];
+        let reason = match &entries[i] {
+            None => Rebuild::NotCached,
+            Some(entry) if entry.hash != hashes[i] => Rebuild::SourceChanged,
+            Some(entry) if entry.options != codegen_options => Rebuild::OptionsChanged,
+            Some(entry) => entry
+                .dependencies
+                .iter()
+                .find(|(class, hash)| interface_hash(class) != *hash)
+                .map(|(class, _)| Rebuild::InterfaceChanged(class.clone()))
+                .unwrap_or(Rebuild::Fresh),
+        };
+        let output = out_dir
+            .unwrap_or(source.parent().unwrap_or(Path::new(".")))
+            .join(source.with_extension("vm").file_name().unwrap_or_default());
+        // the source map is written next to the VM code as Name.vm.map
+        let map_output = output.with_extension("vm.map");
+        let mut entry = match (&reason, &entries[i]) {
+            (Rebuild::Fresh, Some(entry)) => entry.clone(),
+            _ => {
+                let class_grouping = match class_grouping {
+                    Some(g) => g,
+                    None => parse(&contents[i]).map_err(|e| error(source, e))?,
+                };
+                let jack_file = source.file_name().unwrap_or_default().to_string_lossy();
+                let output = compile_class_with(
+                    &class_grouping,
+                    &program,
+                    (&jack_file, &contents[i]),
+                    &options.codegen,
+                )
+                .map_err(|e| error(source, e))?;
+                CacheEntry {
+                    source: files.canonicalize(source),
+                    hash: hashes[i].clone(),
+                    interface: interfaces[i].clone(),
+                    dependencies: referenced_classes(&class_grouping, &interfaces[i].name)
+                        .into_iter()
+                        .map(|x| {
+                            let hash = interface_hash(&x);
+                            (x, hash)
+                        })
+                        .collect(),
+                    options: codegen_options.clone(),
+                    outputs: vec![],
+                    vm: output.vm,
+                    source_map: output.source_map.map(|x| x.to_string()),
+                    commands: output.commands,
+                    unoptimized_commands: output.unoptimized_commands,
+                }
+            }
+        };
+        let outputs = match entry.source_map {
+            Some(_) => vec![output.clone(), map_output.clone()],
+            None => vec![output.clone()],
+        };
+        // the entry is written when the file was compiled or its outputs moved
+        if reason != Rebuild::Fresh || entry.outputs != outputs {
+            entry.outputs = outputs;
+            let path = entry_path(files, cache_dir, source);
+            files
+                .write(&path, &entry.to_json().to_string())
+                .map_err(|e| error(&path, e.to_string()))?;
+        }
+        for (path, text) in [
+            (&output, Some(&entry.vm)),
+            (&map_output, entry.source_map.as_ref()),
+        ] {
+            if let Some(text) = text {
+                if files.read(path).ok().as_ref() != Some(text) {
+                    files
+                        .write(path, text)
+                        .map_err(|e| error(path, e.to_string()))?;
+                }
+            }
+        }
+        Ok(BuildStep {
+            source: source.clone(),
+            output,
+            reason,
+            commands: entry.commands,
+            unoptimized_commands: entry.unoptimized_commands,
+        })
+    }))
+}
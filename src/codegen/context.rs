@@ -0,0 +1,78 @@
+use crate::analysis::Program;
+use crate::codewriter::CodegenOptions;
+use crate::utils::SymbolTable;
+
+// Represents what the code of a subroutine is generated with
+pub struct Context<'a> {
+    pub symbol_table: &'a SymbolTable<'a>,
+    pub program: &'a Program,
+    pub options: &'a CodegenOptions,
+    // The numbers of the next labels of the subroutine. If and while statements share a counter,
+    // except in the reference mode which counts them separately like the official compiler.
+    if_labels: usize,
+    while_labels: usize,
+    string_labels: usize,
+    // The interned string literals of the class, held by the statics after those it declares
+    pub strings: Vec<String>,
+    first_string_static: usize,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(
+        symbol_table: &'a SymbolTable<'a>,
+        program: &'a Program,
+        options: &'a CodegenOptions,
+    ) -> Self {
+        Context {
+            symbol_table,
+            program,
+            options,
+            if_labels: 0,
+            while_labels: 0,
+            string_labels: 0,
+            strings: vec![],
+            first_string_static: 0,
+        }
+    }
+
+    // Sets the string literals interned by the other subroutines of the class, and the number
+    // of statics the class declares
+    pub fn with_strings(mut self, strings: Vec<String>, static_count: usize) -> Self {
+        self.strings = strings;
+        self.first_string_static = static_count;
+        self
+    }
+
+    // Returns the index of the static holding a string literal, interning it if it is new
+    pub fn string_static(&mut self, literal: &str) -> usize {
+        let i = match self.strings.iter().position(|x| x == literal) {
+            Some(i) => i,
+            None => {
+                self.strings.push(literal.to_string());
+                self.strings.len() - 1
+            }
+        };
+        self.first_string_static + i
+    }
+
+    // Returns the label number of a new use of an interned string
+    pub fn string_label(&mut self) -> usize {
+        self.string_labels += 1;
+        self.string_labels - 1
+    }
+
+    // Returns the label number of a new if statement
+    pub fn if_label(&mut self) -> usize {
+        self.if_labels += 1;
+        self.if_labels - 1
+    }
+
+    // Returns the label number of a new while statement
+    pub fn while_label(&mut self) -> usize {
+        if !self.options.reference {
+            return self.if_label();
+        }
+        self.while_labels += 1;
+        self.while_labels - 1
+    }
+}
@@ -1,62 +1,127 @@
-use super::*;
+use super::Context;
+use crate::analysis::{Receiver, SubroutineKind};
 use crate::utils::*;
 
-pub fn expression(
-    expression_grouping: &Grouping,
-    symbol_table: &SymbolTable,
-) -> Result<String, String> {
-    if expression_grouping.subgroupings().len() == 1 {
-        return term(expression_grouping.subgroupings()[0], symbol_table);
+// Returns the code of an operator applied to the two values on top of the stack
+fn operator(op: &Token) -> Result<&'static str, String> {
+    Ok(match op {
+        Token::Symbol('+') => "add",
+        Token::Symbol('-') => "sub",
+        Token::Symbol('*') => "call Math.multiply 2",
+        Token::Symbol('/') => "call Math.divide 2",
+        Token::Symbol('&') => "and",
+        Token::Symbol('|') => "or",
+        Token::Symbol('<') => "lt",
+        Token::Symbol('>') => "gt",
+        Token::Symbol('=') => "eq",
+        _ => {
+            return Err("Missing operand between two terms".to_string());
+        }
+    })
+}
+
+// Returns the code of an expression, whose operators are applied from left to right
+pub fn expression(expression_grouping: &Grouping, cx: &mut Context) -> Result<String, String> {
+    let terms = expression_grouping.subgroupings();
+    let mut res = term(terms[0], cx)?;
+    for (op, t) in expression_grouping.tokens().into_iter().zip(&terms[1..]) {
+        res += &format!("\n{}\n{}", term(t, cx)?, operator(op)?);
     }
-    Ok(term(expression_grouping.subgroupings()[0], symbol_table)?
-        + "\n"
-        + &expression(&expression_grouping.slice(2), symbol_table)?
-        + "\n"
-        + match expression_grouping.tokens()[0] {
-            Token::Symbol('+') => "add",
-            Token::Symbol('-') => "sub",
-            Token::Symbol('*') => "call Math.multiply",
-            Token::Symbol('/') => "call Math.divide",
-            Token::Symbol('&') => "and",
-            Token::Symbol('|') => "or",
-            Token::Symbol('<') => "lt",
-            Token::Symbol('>') => "gt",
-            Token::Symbol('=') => "eq",
-            _ => {
-                return Err("Missing operand between two terms".to_string());
-            }
-        })
+    Ok(res)
 }
 
 fn expression_list(
     expression_list_grouping: &Grouping,
-    symbol_table: &SymbolTable,
+    cx: &mut Context,
 ) -> Result<String, String> {
     Ok(expression_list_grouping
         .subgroupings()
         .iter()
-        .map(|x| expression(x, symbol_table))
+        .map(|x| expression(x, cx))
         .collect::<Result<Vec<_>, String>>()?
-        .into_iter()
-        .fold(String::new(), |a, b| a + "\n" + &b))
+        .join("\n"))
 }
 
-fn term(term_grouping: &Grouping, symbol_table: &SymbolTable) -> Result<String, String> {
+// Returns the code of a subroutine call term or do statement. Method calls push the object
+// they are called on as the first argument.
+pub fn subroutine_call(
+    tokens: &[&Token],
+    args: &Grouping,
+    cx: &mut Context,
+) -> Result<String, String> {
+    let call = cx.program.resolve_call(tokens, cx.symbol_table)?;
+    let mut arg_count = args.subgroupings().len();
+    let mut res = vec![];
+    if call.is_method_call() {
+        arg_count += 1;
+        res.push(match &call.receiver {
+            Receiver::Variable(var) => format!("push {}", cx.symbol_table.get(var)?.literal()),
+            _ => "push pointer 0".to_string(),
+        });
+    } else if call
+        .signature
+        .is_some_and(|x| x.kind == SubroutineKind::Method)
+    {
+        return Err(format!(
+            "'{}.{}' is a method and must be called on an object",
+            call.class, call.name
+        ));
+    }
+    if !args.subgroupings().is_empty() {
+        res.push(expression_list(args, cx)?);
+    }
+    res.push(format!("call {}.{} {}", call.class, call.name, arg_count));
+    Ok(res.join("\n"))
+}
+
+// Returns the code making a new String with the characters of a literal
+fn new_string(literal: &str) -> String {
+    format!(
+        "push constant {}\ncall String.new 1",
+        literal.chars().count()
+    ) + &literal
+        .chars()
+        .map(|x| format!("push constant {}\ncall String.appendChar 2", x as usize))
+        .fold(String::new(), |a, b| a + "\n" + &b)
+}
+
+pub fn term(term_grouping: &Grouping, cx: &mut Context) -> Result<String, String> {
     let tokens = term_grouping.tokens();
     let groupings = term_grouping.subgroupings();
     Ok(match tokens[0] {
         Token::IntConst(v) => format!("push constant {}", v),
-        Token::Keyword("null") | Token::Keyword("false") => format!("push constant 0"),
-        Token::StringConst(s) => {
-            format!("push constant {}\ncall String.new", s.len())
-                + &s.chars()
-                    .map(|x| format!("push constant {}\ncall String.appendChar", x as usize))
-                    .fold(String::new(), |a, b| a + "\n" + &b)
+        Token::Keyword("null") | Token::Keyword("false") => "push constant 0".to_string(),
+        Token::Keyword("this") => "push pointer 0".to_string(),
+        // an interned string is made the first time it is used, when its static is still null
+        Token::StringConst(s) if cx.options.intern_strings => {
+            let index = cx.string_static(s);
+            let n = cx.string_label();
+            format!(
+                "push static {}\nif-goto STRING_READY{}\n{}\npop static {}\nlabel STRING_READY{}\npush static {}",
+                index,
+                n,
+                new_string(s),
+                index,
+                n,
+                index
+            )
         }
+        Token::StringConst(s) => new_string(s),
+        Token::Keyword("true") if cx.options.reference => "push constant 0\nnot".to_string(),
         Token::Keyword("true") => "push constant 1\nneg".to_string(),
-        Token::Identifier(name) => "varname".to_string(),
+        Token::Identifier(name) => match tokens.get(1) {
+            Some(Token::Symbol('[')) => format!(
+                "{}\npush {}\nadd\npop pointer 1\npush that 0",
+                expression(groupings[0], cx)?,
+                cx.symbol_table.get(name)?.literal()
+            ),
+            Some(Token::Symbol('(')) | Some(Token::Symbol('.')) => {
+                subroutine_call(&tokens, groupings[0], cx)?
+            }
+            _ => format!("push {}", cx.symbol_table.get(name)?.literal()),
+        },
         Token::Symbol(s) if (s == &'-' || s == &'~') => {
-            term(groupings[0], symbol_table)?
+            term(groupings[0], cx)?
                 + "\n"
                 + match s {
                     '-' => "neg",
@@ -64,7 +129,7 @@ fn term(term_grouping: &Grouping, symbol_table: &SymbolTable) -> Result<String,
                     _ => return Err("Invalid unary operator".to_string())?,
                 }
         }
-        Token::Symbol('(') => expression(groupings[0], symbol_table)?,
+        Token::Symbol('(') => expression(groupings[0], cx)?,
         _ => {
             return Err("Invalid expression term".to_string());
         }
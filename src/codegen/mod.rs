@@ -1,3 +1,7 @@
+pub mod context;
 pub mod expressions;
+pub mod peephole;
+pub mod statements;
 
-pub use expressions::*;
+pub use context::*;
+pub use statements::*;
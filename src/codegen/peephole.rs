@@ -0,0 +1,244 @@
+use crate::codewriter::VmCommand;
+use std::collections::{HashMap, HashSet};
+
+// Returns the label a goto or if-goto command jumps to
+fn jump_target(command: &VmCommand) -> Option<&str> {
+    let text = command.text.as_str();
+    text.strip_prefix("goto ")
+        .or_else(|| text.strip_prefix("if-goto "))
+}
+
+fn label_name(command: &VmCommand) -> Option<&str> {
+    command.text.strip_prefix("label ")
+}
+
+// Checks if a command leaves 0 or -1 on the stack. The branches of other conditions can't be
+// inverted with not, since if-goto jumps on any value other than 0 and not is bitwise.
+fn is_comparison(command: &VmCommand) -> bool {
+    matches!(command.text.as_str(), "eq" | "lt" | "gt")
+}
+
+// Returns a command with other text, from the same Jack code
+fn with_text(command: &VmCommand, text: String) -> VmCommand {
+    VmCommand {
+        text,
+        ..command.clone()
+    }
+}
+
+// Removes the commands of a function which cancel out, and shortens its branches
+fn simplify(code: Vec<VmCommand>) -> Vec<VmCommand> {
+    let mut res: Vec<VmCommand> = vec![];
+    let mut i = 0;
+    while i < code.len() {
+        let command = &code[i];
+        let text = command.text.as_str();
+        let previous = res.last().map(|x| x.text.as_str());
+        let next = |n: usize| code.get(i + n).map(|x| x.text.as_str());
+        match (previous, text) {
+            // push x; pop x
+            (Some(previous), _)
+                if previous.strip_prefix("push ").is_some()
+                    && previous.strip_prefix("push ") == text.strip_prefix("pop ") =>
+            {
+                res.pop();
+            }
+            // push constant 0; add
+            (Some("push constant 0"), "add") | (Some("not"), "not") => {
+                res.pop();
+            }
+            // cmp; not; if-goto A; goto B; label A jumps to B on the comparison instead
+            (_, "not")
+                if res.last().is_some_and(is_comparison)
+                    && next(1)
+                        .and_then(|x| x.strip_prefix("if-goto "))
+                        .is_some_and(|x| next(3) == Some(&format!("label {}", x)))
+                    && next(2).is_some_and(|x| x.starts_with("goto ")) =>
+            {
+                let target = jump_target(&code[i + 2]).unwrap_or_default();
+                res.push(with_text(&code[i + 1], format!("if-goto {}", target)));
+                i += 3;
+                continue;
+            }
+            // cmp; if-goto A; goto B; label A jumps to B on the negated comparison, leaving A
+            // unused
+            (_, _)
+                if res.last().is_some_and(is_comparison)
+                    && text
+                        .strip_prefix("if-goto ")
+                        .is_some_and(|x| next(2) == Some(&format!("label {}", x)))
+                    && next(1).is_some_and(|x| x.starts_with("goto ")) =>
+            {
+                let target = jump_target(&code[i + 1]).unwrap_or_default();
+                res.push(with_text(command, "not".to_string()));
+                res.push(with_text(command, format!("if-goto {}", target)));
+                i += 2;
+                continue;
+            }
+            // goto L; label L, looking through the labels right after the goto, which it would
+            // fall through to. Only the command before a run of labels reads it.
+            (_, _)
+                if text.strip_prefix("goto ").is_some_and(|x| {
+                    code[i + 1..]
+                        .iter()
+                        .map_while(label_name)
+                        .any(|label| label == x)
+                }) => {}
+            _ => res.push(command.clone()),
+        }
+        i += 1;
+    }
+    res
+}
+
+// The labels of a function, and where each goto right before a label is, by its target
+struct Jumps<'a> {
+    labels: HashSet<&'a str>,
+    gotos_before_labels: HashMap<&'a str, usize>,
+}
+
+// Moves the condition of the loop starting at index start to its end, if it's a comparison:
+//
+//     label S; cond; not; if-goto E; body; goto S; label E
+//
+// becomes goto S; label S_BODY; body; label S; cond; if-goto S_BODY; label E, which saves the
+// not, and the goto on every iteration. Returns the new commands and the index after the loop.
+fn rotate_loop(code: &[VmCommand], jumps: &Jumps, start: usize) -> Option<(Vec<VmCommand>, usize)> {
+    let start_label = label_name(&code[start])?;
+    // the condition runs straight through to the branch out of the loop
+    let branch = start
+        + 1
+        + code[start + 1..]
+            .iter()
+            .position(|x| label_name(x).is_some() || jump_target(x).is_some())?;
+    let end_label = code[branch].text.strip_prefix("if-goto ")?;
+    if branch < start + 3 || code[branch - 1].text != "not" || !is_comparison(&code[branch - 2]) {
+        return None;
+    }
+    let end = *jumps.gotos_before_labels.get(start_label)?;
+    let body_label = format!("{}_BODY", start_label);
+    if end < branch
+        || label_name(&code[end + 1]) != Some(end_label)
+        || jumps.labels.contains(body_label.as_str())
+    {
+        return None;
+    }
+    let mut res = vec![
+        with_text(&code[start], format!("goto {}", start_label)),
+        with_text(&code[start], format!("label {}", body_label)),
+    ];
+    res.extend_from_slice(&code[branch + 1..end]);
+    res.extend_from_slice(&code[start..branch - 1]);
+    res.push(with_text(&code[branch], format!("if-goto {}", body_label)));
+    res.push(code[end + 1].clone());
+    Some((res, end + 2))
+}
+
+// Tests the conditions of the loops of a function at their end instead of their start
+fn rotate_loops(code: Vec<VmCommand>) -> Vec<VmCommand> {
+    let jumps = Jumps {
+        labels: code.iter().filter_map(label_name).collect(),
+        gotos_before_labels: code
+            .windows(2)
+            .enumerate()
+            .filter(|(_, x)| label_name(&x[1]).is_some())
+            .filter_map(|(i, x)| Some((x[0].text.strip_prefix("goto ")?, i)))
+            .collect(),
+    };
+    let mut res = vec![];
+    let mut i = 0;
+    while i < code.len() {
+        match rotate_loop(&code, &jumps, i) {
+            Some((commands, next)) => {
+                res.extend(commands);
+                i = next;
+            }
+            None => {
+                res.push(code[i].clone());
+                i += 1;
+            }
+        }
+    }
+    res
+}
+
+// Makes jumps to a goto command jump to where it goes instead, and removes the labels which
+// nothing jumps to
+fn thread_jumps(code: Vec<VmCommand>) -> Vec<VmCommand> {
+    let mut gotos = HashMap::new();
+    // the first command after the labels, going backwards so each run of labels is read once
+    let mut after: Option<&VmCommand> = None;
+    for command in code.iter().rev() {
+        match label_name(command) {
+            Some(label) => {
+                if let Some(target) = after.and_then(|x| x.text.strip_prefix("goto ")) {
+                    gotos.insert(label, target);
+                }
+            }
+            None => after = Some(command),
+        }
+    }
+    // jumps around a loop of gotos are left alone
+    let resolve = |label: &str| {
+        let mut seen = HashSet::new();
+        let mut label = label;
+        while let Some(target) = gotos.get(label) {
+            if !seen.insert(label) {
+                return None;
+            }
+            label = target;
+        }
+        Some(label.to_string())
+    };
+    let code = code
+        .iter()
+        .map(|command| match jump_target(command) {
+            Some(target) => match resolve(target) {
+                Some(resolved) if resolved != target => {
+                    let jump = command.text.split(' ').next().unwrap_or_default();
+                    with_text(command, format!("{} {}", jump, resolved))
+                }
+                _ => command.clone(),
+            },
+            None => command.clone(),
+        })
+        .collect::<Vec<_>>();
+    let targets = code
+        .iter()
+        .filter_map(|x| jump_target(x).map(|x| x.to_string()))
+        .collect::<HashSet<_>>();
+    code.into_iter()
+        .filter(|x| label_name(x).is_none_or(|x| targets.contains(x)))
+        .collect()
+}
+
+// Removes redundant commands from the VM code of a class, without changing what it does:
+//
+// - push x; pop x and push constant 0; add, which leave everything as it was
+// - not; not, and not before a branch on a comparison, by branching the other way
+// - goto L right before label L
+// - not before the branch out of a loop on a comparison, by testing it at the end of the loop
+// - jumps to a goto, which jump to its target instead
+// - labels which nothing jumps to
+//
+// Labels belong to their function, so each function is optimized on its own. The rules are
+// applied until none of them changes the code.
+pub fn optimize(commands: Vec<VmCommand>) -> Vec<VmCommand> {
+    let mut functions: Vec<Vec<VmCommand>> = vec![];
+    for command in commands {
+        match functions.last_mut() {
+            Some(function) if !command.text.starts_with("function ") => function.push(command),
+            _ => functions.push(vec![command]),
+        }
+    }
+    functions
+        .into_iter()
+        .flat_map(|mut code| loop {
+            let optimized = thread_jumps(simplify(rotate_loops(code.clone())));
+            if optimized == code {
+                break code;
+            }
+            code = optimized;
+        })
+        .collect()
+}
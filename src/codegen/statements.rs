@@ -0,0 +1,120 @@
+use super::expressions::*;
+use super::Context;
+use crate::utils::*;
+
+// Represents VM commands, each with the span of the Jack code it was compiled from
+pub type Code = Vec<(String, Span)>;
+
+// Returns the lines of a piece of code as commands compiled from a span
+pub fn code_at(code: &str, span: Span) -> Code {
+    code.lines()
+        .filter(|x| !x.is_empty())
+        .map(|x| (x.to_string(), span))
+        .collect()
+}
+
+// Returns the code of a statements grouping. Labels are numbered with a counter shared by the
+// statements of a subroutine.
+pub fn statements(statements_grouping: &Grouping, cx: &mut Context) -> Result<Code, String> {
+    Ok(statements_grouping
+        .subgroupings()
+        .into_iter()
+        .map(|x| statement(x, cx))
+        .collect::<Result<Vec<_>, String>>()?
+        .concat())
+}
+
+// Returns the code of a statement, whose commands come from the span of the statement apart
+// from those of the statements nested in it
+fn statement(statement_grouping: &Grouping, cx: &mut Context) -> Result<Code, String> {
+    let tokens = statement_grouping.tokens();
+    let groupings = statement_grouping.subgroupings();
+    let at = |code: String| code_at(&code, statement_grouping.span());
+    Ok(match statement_grouping.name {
+        "letStatement" => {
+            let name = tokens[1].as_text();
+            let variable = cx.symbol_table.get(&name)?.literal();
+            at(match groupings[..] {
+                [value] => format!("{}\npop {}", expression(value, cx)?, variable),
+                // the value is stored before setting that, since it may index an array itself
+                [index, value] => format!(
+                    "{}\npush {}\nadd\n{}\npop temp 0\npop pointer 1\npush temp 0\npop that 0",
+                    expression(index, cx)?,
+                    variable,
+                    expression(value, cx)?
+                ),
+                _ => return Err("Invalid let statement".to_string()),
+            })
+        }
+        "ifStatement" => {
+            let n = cx.if_label();
+            let condition = expression(groupings[0], cx)?;
+            let then = statements(groupings[1], cx)?;
+            let otherwise = match groupings.get(2) {
+                Some(x) => Some(statements(x, cx)?),
+                None => None,
+            };
+            // the reference compiler jumps over a goto to the else branch
+            match (otherwise, cx.options.reference) {
+                (Some(otherwise), false) => [
+                    at(format!("{}\nnot\nif-goto IF_ELSE{}", condition, n)),
+                    then,
+                    at(format!("goto IF_END{}\nlabel IF_ELSE{}", n, n)),
+                    otherwise,
+                    at(format!("label IF_END{}", n)),
+                ]
+                .concat(),
+                (None, false) => [
+                    at(format!("{}\nnot\nif-goto IF_END{}", condition, n)),
+                    then,
+                    at(format!("label IF_END{}", n)),
+                ]
+                .concat(),
+                (otherwise, true) => [
+                    at(format!(
+                        "{}\nif-goto IF_TRUE{}\ngoto IF_FALSE{}\nlabel IF_TRUE{}",
+                        condition, n, n, n
+                    )),
+                    then,
+                    match &otherwise {
+                        Some(_) => at(format!("goto IF_END{}\nlabel IF_FALSE{}", n, n)),
+                        None => at(format!("label IF_FALSE{}", n)),
+                    },
+                    match otherwise {
+                        Some(otherwise) => [otherwise, at(format!("label IF_END{}", n))].concat(),
+                        None => vec![],
+                    },
+                ]
+                .concat(),
+            }
+        }
+        "whileStatement" => {
+            let n = cx.while_label();
+            let start = match cx.options.reference {
+                true => "WHILE_EXP",
+                false => "WHILE_START",
+            };
+            [
+                at(format!(
+                    "label {}{}\n{}\nnot\nif-goto WHILE_END{}",
+                    start,
+                    n,
+                    expression(groupings[0], cx)?,
+                    n
+                )),
+                statements(groupings[1], cx)?,
+                at(format!("goto {}{}\nlabel WHILE_END{}", start, n, n)),
+            ]
+            .concat()
+        }
+        "doStatement" => at(format!(
+            "{}\npop temp 0",
+            subroutine_call(&tokens[1..], groupings[0], cx)?
+        )),
+        "returnStatement" => at(match groupings.first() {
+            Some(value) => format!("{}\nreturn", expression(value, cx)?),
+            None => "push constant 0\nreturn".to_string(),
+        }),
+        _ => return Err("Invalid statement".to_string()),
+    })
+}
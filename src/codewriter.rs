@@ -1,22 +1,206 @@
-use super::*;
-use crate::codegen::expressions::expression;
-use crate::utils::symbol_table::create_symbol_table;
-use crate::utils::Grouping;
-use crate::utils::SymbolTable;
+use crate::analysis::{Program, SubroutineKind};
+use crate::codegen::peephole::optimize;
+use crate::codegen::{code_at, statements, Context};
+use crate::utils::symbol_table::{create_subroutine_symbol_table, create_symbol_table};
+use crate::utils::{Grouping, Json, LineIndex, Span, SymbolKind};
+use std::collections::HashSet;
 
-pub fn codewrite(class_grouping: &Grouping) -> Result<String, String> {
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+// Represents the settings of code generation
+pub struct CodegenOptions {
+    // Whether the first line of each Jack statement is written as a comment before its commands
+    pub source_comments: bool,
+    // Whether a source map of the VM code is made
+    pub source_map: bool,
+    // Whether the code is the same as that of the official nand2tetris JackCompiler, byte for
+    // byte, so that the two can be compared with diff
+    pub reference: bool,
+    // How much the code is optimized: 0 for not at all, 1 for the peephole optimizations
+    pub opt_level: u8,
+    // Whether each distinct string literal of a class is made once, the first time it is used,
+    // and kept in a static added to the class. Every evaluation of the literal then gives the
+    // same String object rather than a new one, so changes made to it through appendChar,
+    // setCharAt or eraseLastChar show up wherever the literal is used next, and disposing it
+    // leaves the static pointing at freed memory. Literals with the same text in different
+    // classes are still different objects.
+    pub intern_strings: bool,
+}
+
+impl CodegenOptions {
+    // Returns the options as a JSON object with one field per option, which the build cache
+    // compares to tell if a file was compiled with other options
+    pub fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("sourceComments", self.source_comments.into()),
+            ("sourceMap", self.source_map.into()),
+            ("reference", self.reference.into()),
+            ("optLevel", (self.opt_level as usize).into()),
+            ("internStrings", self.intern_strings.into()),
+        ])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents a VM command with the Jack code it was compiled from
+pub struct VmCommand {
+    pub text: String,
+    // The span of the statement, or of the subroutine declaration for the commands which set up
+    // the subroutine
+    pub span: Span,
+    // The full name of the subroutine, e.g. Main.main
+    pub subroutine: String,
+}
+
+#[derive(Debug, Clone)]
+// Represents the VM code of a class, with its source map if it was asked for
+pub struct ClassOutput {
+    pub vm: String,
+    pub source_map: Option<Json>,
+    // The number of VM commands of the class, and the number before it was optimized
+    pub commands: usize,
+    pub unoptimized_commands: usize,
+}
+
+// Returns the VM code of a class. The other classes of the program are given to tell calls of
+// methods from calls of functions; the OS classes are always known.
+pub fn codewrite(class_grouping: &Grouping, program: &[&Grouping]) -> Result<String, String> {
+    compile_class(
+        class_grouping,
+        &Program::new(&[&[class_grouping], program].concat()),
+    )
+}
+
+// Returns the VM code of a class of a program
+pub fn compile_class(class_grouping: &Grouping, program: &Program) -> Result<String, String> {
+    let commands = compile_commands(class_grouping, program, &CodegenOptions::default())?;
+    Ok(commands.iter().map(|x| x.text.clone() + "\n").collect())
+}
+
+// Returns the VM commands of a class of a program
+pub fn compile_commands(
+    class_grouping: &Grouping,
+    program: &Program,
+    options: &CodegenOptions,
+) -> Result<Vec<VmCommand>, String> {
     let class_symbol_table = create_symbol_table(class_grouping)?;
-    compile_expressions(class_grouping, &class_symbol_table);
-    return Ok("".to_string());
+    let class_name = class_symbol_table.class_name();
+    let count = |kind: SymbolKind| {
+        class_symbol_table
+            .symbols()
+            .iter()
+            .filter(|x| *x.kind() == kind)
+            .count()
+    };
+    let field_count = count(SymbolKind::Field);
+    let static_count = count(SymbolKind::Static);
+    let mut strings = vec![];
+    let mut res = vec![];
+    for subroutine in class_grouping
+        .subgroupings()
+        .into_iter()
+        .filter(|x| x.name == "subroutineDec")
+    {
+        let symbol_table = create_subroutine_symbol_table(subroutine, &class_symbol_table)?;
+        let tokens = subroutine.tokens();
+        let name = format!("{}.{}", class_name, tokens[2].as_text());
+        let local_count = symbol_table
+            .symbols()
+            .iter()
+            .filter(|x| *x.kind() == SymbolKind::Local)
+            .count();
+        let mut code = code_at(
+            &format!("function {} {}", name, local_count),
+            subroutine.span(),
+        );
+        // the object is allocated by constructors and passed as the first argument of methods
+        let setup = match SubroutineKind::from_keyword(&tokens[0].as_text()) {
+            Some(SubroutineKind::Constructor) => format!(
+                "push constant {}\ncall Memory.alloc 1\npop pointer 0",
+                field_count
+            ),
+            Some(SubroutineKind::Method) => "push argument 0\npop pointer 0".to_string(),
+            _ => String::new(),
+        };
+        code.extend(code_at(&setup, subroutine.span()));
+        let body = subroutine.subgroupings()[1];
+        if let Some(body_statements) = body
+            .subgroupings()
+            .into_iter()
+            .find(|x| x.name == "statements")
+        {
+            let mut cx =
+                Context::new(&symbol_table, program, options).with_strings(strings, static_count);
+            code.extend(statements(body_statements, &mut cx)?);
+            strings = cx.strings;
+        }
+        res.extend(code.into_iter().map(|(text, span)| VmCommand {
+            text,
+            span,
+            subroutine: name.clone(),
+        }));
+    }
+    Ok(res)
 }
 
-fn compile_expressions(grouping: &Grouping, symbol_table: &SymbolTable) {
-    for sg in grouping.subgroupings() {
-        if sg.name == "expression" {
-            println!("{}", sg.as_xml());
-            println!("{}", expression(sg, symbol_table).unwrap());
-        } else {
-            compile_expressions(sg, symbol_table);
+// Returns the VM code of a class of a program with the debug information asked for. Comments
+// hold the first line of each statement, before the first of its commands. The source map
+// records the Jack line and column and the subroutine of every command, by the line of the
+// command in the VM code:
+//
+// {"version":1,"source":"Main.jack","mappings":[
+//     {"vmLine":1,"line":2,"column":5,"subroutine":"Main.main"}, ...]}
+pub fn compile_class_with(
+    class_grouping: &Grouping,
+    program: &Program,
+    source: (&str, &str),
+    options: &CodegenOptions,
+) -> Result<ClassOutput, String> {
+    let (jack_file, contents) = source;
+    let mut vm = String::new();
+    let mut lines = 0;
+    let mut mappings = vec![];
+    let mut commented = HashSet::new();
+    let line_index = options.source_map.then(|| LineIndex::new(contents));
+    let mut commands = compile_commands(class_grouping, program, options)?;
+    let unoptimized_commands = commands.len();
+    if options.opt_level >= 1 {
+        commands = optimize(commands);
+    }
+    for command in &commands {
+        if options.source_comments && commented.insert(command.span) {
+            let text = command
+                .span
+                .text(contents)
+                .lines()
+                .next()
+                .unwrap_or_default();
+            vm += &format!("// {}\n", text.trim());
+            lines += 1;
+        }
+        vm += &command.text;
+        vm += "\n";
+        lines += 1;
+        if let Some(line_index) = &line_index {
+            let (line, column) = line_index.line_col(command.span.start);
+            mappings.push(Json::object(vec![
+                ("vmLine", lines.into()),
+                ("line", line.into()),
+                ("column", column.into()),
+                ("subroutine", command.subroutine.as_str().into()),
+            ]));
         }
     }
+    let source_map = options.source_map.then(|| {
+        Json::object(vec![
+            ("version", 1usize.into()),
+            ("source", jack_file.into()),
+            ("mappings", mappings.into()),
+        ])
+    });
+    Ok(ClassOutput {
+        vm,
+        source_map,
+        commands: commands.len(),
+        unoptimized_commands,
+    })
 }
@@ -0,0 +1,312 @@
+pub mod nodes;
+
+pub use nodes::*;
+
+use crate::tokenizer::lexeme_token;
+use crate::utils::{GroupItem, Grouping, Lexeme, LexemeKind, Span, Token};
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Debug)]
+// Represents an immutable token of the lossless syntax tree, without its position
+pub struct GreenToken {
+    kind: LexemeKind,
+    text: String,
+}
+
+#[derive(Debug)]
+// Represents an immutable node of the lossless syntax tree, without its position
+pub struct GreenNode {
+    kind: &'static str,
+    len: usize,
+    children: Vec<GreenElement>,
+}
+
+#[derive(Debug, Clone)]
+// Represents a child of a green node
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    // Returns the length of the source text covered by the element
+    fn len(&self) -> usize {
+        match self {
+            Self::Node(n) => n.len,
+            Self::Token(t) => t.text.len(),
+        }
+    }
+}
+
+impl GreenNode {
+    // Create a new GreenNode from its children
+    fn new(kind: &'static str, children: Vec<GreenElement>) -> Self {
+        GreenNode {
+            kind,
+            len: children.iter().map(|x| x.len()).sum(),
+            children,
+        }
+    }
+}
+
+// Walks a grouping and weaves the lexemes of the source code (including trivia) into it
+fn weave(
+    grouping: &Grouping,
+    lexemes: &mut std::slice::Iter<'_, Lexeme>,
+    source: &str,
+) -> GreenNode {
+    let mut children = vec![];
+    for item in &grouping.items {
+        match item {
+            GroupItem::Grouping(g) => {
+                children.push(GreenElement::Node(Rc::new(weave(g, lexemes, source))))
+            }
+            GroupItem::Token(_, _) => {
+                for lexeme in lexemes.by_ref() {
+                    children.push(green_token(lexeme, source));
+                    if !lexeme.kind.is_trivia() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    GreenNode::new(grouping.name, children)
+}
+
+// Creates the green token of a lexeme
+fn green_token(lexeme: &Lexeme, source: &str) -> GreenElement {
+    GreenElement::Token(Rc::new(GreenToken {
+        kind: lexeme.kind,
+        text: lexeme.span.text(source).to_string(),
+    }))
+}
+
+// Builds the lossless syntax tree of the source code from its parsed class grouping
+pub fn build(class_grouping: &Grouping, lexemes: &[Lexeme], source: &str) -> SyntaxNode {
+    let mut lexemes = lexemes.iter();
+    let mut root = weave(class_grouping, &mut lexemes, source);
+    // trailing trivia belongs to the root node
+    root.children
+        .extend(lexemes.map(|x| green_token(x, source)));
+    root.len = root.children.iter().map(|x| x.len()).sum();
+    SyntaxNode::new_root(Rc::new(root))
+}
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    offset: usize,
+    parent: Option<SyntaxNode>,
+}
+
+#[derive(Clone)]
+// Represents a node of the lossless syntax tree, knowing its position and parent
+pub struct SyntaxNode(Rc<NodeData>);
+
+#[derive(Clone)]
+// Represents a token (or trivia) of the lossless syntax tree, knowing its position and parent
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    offset: usize,
+    parent: SyntaxNode,
+}
+
+#[derive(Clone)]
+// Represents a child of a syntax node
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    // Create the root node of a tree
+    fn new_root(green: Rc<GreenNode>) -> Self {
+        SyntaxNode(Rc::new(NodeData {
+            green,
+            offset: 0,
+            parent: None,
+        }))
+    }
+
+    // Returns the kind of the node, which is the name of the grouping it was built from
+    pub fn kind(&self) -> &'static str {
+        self.0.green.kind
+    }
+
+    // Returns the span of the source code covered by the node, including trivia
+    pub fn span(&self) -> Span {
+        Span::new(self.0.offset, self.0.offset + self.0.green.len)
+    }
+
+    // Returns the span of the node without its leading and trailing trivia
+    pub fn trimmed_span(&self) -> Span {
+        let tokens = self.significant_tokens();
+        match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => first.span().cover(last.span()),
+            _ => Span::new(self.0.offset, self.0.offset),
+        }
+    }
+
+    // Returns the parent node, if any
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    // Returns the direct children of the node, both nodes and tokens
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        self.0
+            .green
+            .children
+            .iter()
+            .map(|child| {
+                let element = match child {
+                    GreenElement::Node(n) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                        green: n.clone(),
+                        offset,
+                        parent: Some(self.clone()),
+                    }))),
+                    GreenElement::Token(t) => SyntaxElement::Token(SyntaxToken {
+                        green: t.clone(),
+                        offset,
+                        parent: self.clone(),
+                    }),
+                };
+                offset += child.len();
+                element
+            })
+            .collect()
+    }
+
+    // Returns the direct child nodes
+    pub fn children(&self) -> Vec<SyntaxNode> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|x| match x {
+                SyntaxElement::Node(n) => Some(n),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Returns the direct child tokens, including trivia
+    pub fn child_tokens(&self) -> Vec<SyntaxToken> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|x| match x {
+                SyntaxElement::Token(t) => Some(t),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Returns the direct child tokens which are not trivia
+    pub fn significant_child_tokens(&self) -> Vec<SyntaxToken> {
+        self.child_tokens()
+            .into_iter()
+            .filter(|x| !x.kind().is_trivia())
+            .collect()
+    }
+
+    // Returns the direct child nodes of a kind
+    pub fn children_of_kind(&self, kind: &str) -> Vec<SyntaxNode> {
+        self.children()
+            .into_iter()
+            .filter(|x| x.kind() == kind)
+            .collect()
+    }
+
+    // Returns all nodes in the subtree in preorder, starting with this node
+    pub fn descendants(&self) -> Vec<SyntaxNode> {
+        let mut res = vec![self.clone()];
+        for child in self.children() {
+            res.extend(child.descendants());
+        }
+        res
+    }
+
+    // Returns all tokens in the subtree in source order, including trivia
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut res = vec![];
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(n) => res.extend(n.tokens()),
+                SyntaxElement::Token(t) => res.push(t),
+            }
+        }
+        res
+    }
+
+    // Returns all tokens in the subtree in source order, excluding trivia
+    pub fn significant_tokens(&self) -> Vec<SyntaxToken> {
+        self.tokens()
+            .into_iter()
+            .filter(|x| !x.kind().is_trivia())
+            .collect()
+    }
+
+    // Returns the deepest token covering the offset
+    pub fn token_at(&self, offset: usize) -> Option<SyntaxToken> {
+        self.tokens()
+            .into_iter()
+            .find(|x| x.span().start <= offset && offset < x.span().end)
+    }
+
+    // Returns the source text of the node exactly as it was written
+    pub fn text(&self) -> String {
+        self.tokens().iter().map(|x| x.text()).collect()
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in self.tokens() {
+            write!(f, "{}", token.text())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{:?}", self.kind(), self.span())
+    }
+}
+
+impl SyntaxToken {
+    // Returns the kind of the lexeme the token was built from
+    pub fn kind(&self) -> LexemeKind {
+        self.green.kind
+    }
+
+    // Returns the source text of the token
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    // Returns the span of the source code covered by the token
+    pub fn span(&self) -> Span {
+        Span::new(self.offset, self.offset + self.green.text.len())
+    }
+
+    // Returns the node containing the token
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+
+    // Returns the lexical token, if the token is not trivia
+    pub fn token(&self) -> Option<Token> {
+        let lexeme = Lexeme {
+            kind: self.kind(),
+            span: Span::new(0, self.text().len()),
+        };
+        lexeme_token(&lexeme, self.text()).ok()
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}@{:?} {:?}", self.kind(), self.span(), self.text())
+    }
+}
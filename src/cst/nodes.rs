@@ -0,0 +1,203 @@
+use super::{SyntaxNode, SyntaxToken};
+use crate::utils::LexemeKind;
+
+// Returns the text of the significant child tokens of a node
+fn child_texts(node: &SyntaxNode) -> Vec<String> {
+    node.significant_child_tokens()
+        .iter()
+        .map(|x| x.text().to_string())
+        .collect()
+}
+
+// Returns the identifier child tokens of a node
+fn child_identifiers(node: &SyntaxNode) -> Vec<SyntaxToken> {
+    node.significant_child_tokens()
+        .into_iter()
+        .filter(|x| x.kind() == LexemeKind::Identifier)
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+// Typed view of a class node
+pub struct ClassNode(SyntaxNode);
+
+#[derive(Clone, Debug)]
+// Typed view of a class variable (static/field) or local variable (var) declaration node
+pub struct VarDecNode(SyntaxNode);
+
+#[derive(Clone, Debug)]
+// Typed view of a subroutine declaration node
+pub struct SubroutineNode(SyntaxNode);
+
+#[derive(Clone, Debug)]
+// Typed view of one of the statement nodes
+pub struct StatementNode(SyntaxNode);
+
+impl ClassNode {
+    // Returns the typed view if the node is a class
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        (node.kind() == "class").then_some(ClassNode(node))
+    }
+
+    // Returns the underlying syntax node
+    pub fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+
+    // Returns the class name token
+    pub fn name(&self) -> Option<SyntaxToken> {
+        child_identifiers(&self.0).into_iter().next()
+    }
+
+    // Returns the class variable declarations
+    pub fn var_decs(&self) -> Vec<VarDecNode> {
+        self.0
+            .children_of_kind("classVarDec")
+            .into_iter()
+            .map(VarDecNode)
+            .collect()
+    }
+
+    // Returns the subroutine declarations
+    pub fn subroutines(&self) -> Vec<SubroutineNode> {
+        self.0
+            .children_of_kind("subroutineDec")
+            .into_iter()
+            .map(SubroutineNode)
+            .collect()
+    }
+}
+
+impl VarDecNode {
+    // Returns the typed view if the node is a variable declaration
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        matches!(node.kind(), "classVarDec" | "varDec").then_some(VarDecNode(node))
+    }
+
+    // Returns the underlying syntax node
+    pub fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+
+    // Returns the declaration keyword: static, field or var
+    pub fn kind(&self) -> String {
+        child_texts(&self.0).swap_remove(0)
+    }
+
+    // Returns the declared type
+    pub fn type_name(&self) -> String {
+        child_texts(&self.0).swap_remove(1)
+    }
+
+    // Returns the declared variable name tokens
+    pub fn names(&self) -> Vec<SyntaxToken> {
+        self.0.significant_child_tokens()[2..]
+            .iter()
+            .filter(|x| x.kind() == LexemeKind::Identifier)
+            .cloned()
+            .collect()
+    }
+}
+
+impl SubroutineNode {
+    // Returns the typed view if the node is a subroutine declaration
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        (node.kind() == "subroutineDec").then_some(SubroutineNode(node))
+    }
+
+    // Returns the underlying syntax node
+    pub fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+
+    // Returns the subroutine kind: constructor, function or method
+    pub fn kind(&self) -> String {
+        child_texts(&self.0).swap_remove(0)
+    }
+
+    // Returns the declared return type
+    pub fn return_type(&self) -> String {
+        child_texts(&self.0).swap_remove(1)
+    }
+
+    // Returns the subroutine name token
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0.significant_child_tokens().into_iter().nth(2)
+    }
+
+    // Returns the parameter types and name tokens
+    pub fn parameters(&self) -> Vec<(String, SyntaxToken)> {
+        let tokens = self.0.children_of_kind("parameterList")[0]
+            .significant_child_tokens()
+            .into_iter()
+            .filter(|x| x.text() != ",")
+            .collect::<Vec<_>>();
+        tokens
+            .chunks(2)
+            .map(|x| (x[0].text().to_string(), x[1].clone()))
+            .collect()
+    }
+
+    // Returns the local variable declarations
+    pub fn var_decs(&self) -> Vec<VarDecNode> {
+        self.body()
+            .children_of_kind("varDec")
+            .into_iter()
+            .map(VarDecNode)
+            .collect()
+    }
+
+    // Returns the statements of the subroutine body
+    pub fn statements(&self) -> Vec<StatementNode> {
+        statement_nodes(&self.body().children_of_kind("statements")[0])
+    }
+
+    // Returns the subroutine body node
+    pub fn body(&self) -> SyntaxNode {
+        self.0.children_of_kind("subroutineBody").swap_remove(0)
+    }
+}
+
+// Returns the typed statement views of a statements node
+fn statement_nodes(statements: &SyntaxNode) -> Vec<StatementNode> {
+    statements
+        .children()
+        .into_iter()
+        .map(StatementNode)
+        .collect()
+}
+
+impl StatementNode {
+    // Returns the typed view if the node is a statement
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        matches!(
+            node.kind(),
+            "letStatement" | "ifStatement" | "whileStatement" | "doStatement" | "returnStatement"
+        )
+        .then_some(StatementNode(node))
+    }
+
+    // Returns the underlying syntax node
+    pub fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+
+    // Returns the statement kind, which is its grouping name (e.g. letStatement)
+    pub fn kind(&self) -> &'static str {
+        self.0.kind()
+    }
+
+    // Returns the expression nodes directly within the statement
+    pub fn expressions(&self) -> Vec<SyntaxNode> {
+        self.0.children_of_kind("expression")
+    }
+
+    // Returns the nested statement blocks: the body of a while, or the branches of an if
+    pub fn blocks(&self) -> Vec<Vec<StatementNode>> {
+        self.0
+            .children_of_kind("statements")
+            .iter()
+            .map(statement_nodes)
+            .collect()
+    }
+}
@@ -0,0 +1,54 @@
+use crate::analysis::check_class;
+use crate::parser::parse_class;
+use crate::utils::{Span, Suggestion};
+
+// The most rounds of fixes applied to a file, each of which fixes at least one mistake
+static MAX_ROUNDS: usize = 100;
+
+// Applies suggested fixes to source code, returning the fixed code and the number of fixes
+// applied. Fixes overlapping an earlier fix, or inserting at the same place, are left out.
+pub fn apply_suggestions(contents: &str, suggestions: &[&Suggestion]) -> (String, usize) {
+    let mut suggestions = suggestions.to_vec();
+    suggestions.sort_by_key(|x| (x.span.start, x.span.end));
+    let mut res = String::new();
+    let mut previous: Option<Span> = None;
+    let mut applied = 0;
+    for suggestion in suggestions {
+        let end = previous.map(|x| x.end).unwrap_or_default();
+        if suggestion.span.start < end || previous == Some(suggestion.span) {
+            continue;
+        }
+        res += &contents[end..suggestion.span.start];
+        res += &suggestion.replacement;
+        previous = Some(suggestion.span);
+        applied += 1;
+    }
+    let end = previous.map(|x| x.end).unwrap_or_default();
+    res += &contents[end..];
+    (res, applied)
+}
+
+// Applies the suggested fixes of the errors of a class until none are left, returning the fixed
+// source code and the number of fixes. Since parsing stops at the first syntax error, fixing one
+// can reveal the next.
+pub fn fix_source(contents: &str) -> (String, usize) {
+    let mut contents = contents.to_string();
+    let mut count = 0;
+    for _ in 0..MAX_ROUNDS {
+        let diagnostics = match parse_class(&contents) {
+            Ok(class_grouping) => check_class(&class_grouping),
+            Err(diagnostic) => vec![diagnostic],
+        };
+        let suggestions = diagnostics
+            .iter()
+            .filter_map(|x| x.suggestions.first())
+            .collect::<Vec<_>>();
+        if suggestions.is_empty() {
+            break;
+        }
+        let (fixed, applied) = apply_suggestions(&contents, &suggestions);
+        contents = fixed;
+        count += applied;
+    }
+    (contents, count)
+}
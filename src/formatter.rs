@@ -0,0 +1,312 @@
+use crate::cst::{SyntaxNode, SyntaxToken};
+use crate::parser::parse_cst;
+use crate::utils::LexemeKind;
+use std::collections::HashMap;
+
+static INDENT: &str = "    ";
+
+// Node kinds which start on a new line, keeping a blank line before them if the source had one
+static LINE_NODES: &[&str] = &[
+    "classVarDec",
+    "varDec",
+    "letStatement",
+    "ifStatement",
+    "whileStatement",
+    "doStatement",
+    "returnStatement",
+];
+
+// Writes formatted source code line by line, deferring line breaks so trailing comments can be
+// appended to the line they were written on
+struct Printer {
+    out: String,
+    line: String,
+    pending: usize,
+    indent: usize,
+}
+
+impl Printer {
+    // Create a new Printer
+    fn new() -> Self {
+        Printer {
+            out: String::new(),
+            line: String::new(),
+            pending: 0,
+            indent: 0,
+        }
+    }
+
+    // Writes text, starting the pending new line first and separating it by a space if requested
+    fn write(&mut self, text: &str, space: bool) {
+        if self.pending > 0 {
+            self.flush();
+        } else if space && !self.line.trim().is_empty() {
+            self.line.push(' ');
+        }
+        if self.line.is_empty() {
+            self.line = INDENT.repeat(self.indent);
+        }
+        self.line.push_str(text);
+    }
+
+    // Moves the current line into the output, adding the pending blank line if any
+    fn flush(&mut self) {
+        if !self.line.trim().is_empty() {
+            self.out.push_str(self.line.trim_end());
+            self.out.push('\n');
+        }
+        // no blank lines at the start of a file or right after an opening brace
+        if self.pending > 1 && !self.out.is_empty() && !self.out.ends_with("{\n") {
+            self.out.push('\n');
+        }
+        self.line.clear();
+        self.pending = 0;
+    }
+
+    // Requests the next text to be written on a new line
+    fn newline(&mut self) {
+        self.pending = self.pending.max(1);
+    }
+
+    // Requests the next text to be written after a blank line
+    fn blank_line(&mut self) {
+        self.pending = 2;
+    }
+
+    // Returns the formatted source code
+    fn finish(mut self) -> String {
+        self.pending = 1;
+        self.flush();
+        self.out
+    }
+}
+
+// Formats the source code of a class, walking the significant tokens of its syntax tree
+struct Formatter {
+    source: String,
+    tokens: Vec<SyntaxToken>,
+    line_nodes: HashMap<usize, &'static str>,
+    alignments: HashMap<usize, (usize, usize)>,
+    printer: Printer,
+}
+
+impl Formatter {
+    // Create a new Formatter over the syntax tree of a class
+    fn new(root: &SyntaxNode) -> Self {
+        let mut line_nodes = HashMap::new();
+        let mut alignments = HashMap::new();
+        for node in root.descendants() {
+            if LINE_NODES.contains(&node.kind()) || node.kind() == "subroutineDec" {
+                line_nodes.insert(node.trimmed_span().start, node.kind());
+            }
+            let decs = node
+                .children()
+                .into_iter()
+                .filter(|x| matches!(x.kind(), "classVarDec" | "varDec"))
+                .collect::<Vec<_>>();
+            let width = |i: usize| {
+                decs.iter()
+                    .map(|x| x.significant_child_tokens()[i].text().len())
+                    .max()
+                    .unwrap_or_default()
+            };
+            let widths = (width(0), width(1));
+            for dec in &decs {
+                alignments.insert(dec.trimmed_span().start, widths);
+            }
+        }
+        Formatter {
+            source: root.text(),
+            tokens: root.tokens(),
+            line_nodes,
+            alignments,
+            printer: Printer::new(),
+        }
+    }
+
+    // Returns the number of line breaks in the whitespace trivia right before the token at i
+    fn line_breaks_before(&self, i: usize) -> usize {
+        match i.checked_sub(1).map(|i| &self.tokens[i]) {
+            Some(t) if t.kind() == LexemeKind::Whitespace => t.text().matches('\n').count(),
+            Some(_) => 0,
+            None => 1,
+        }
+    }
+
+    // Writes the comment at i, either trailing the current line or on its own lines
+    fn comment(&mut self, i: usize) {
+        let comment = self.tokens[i].clone();
+        let breaks = self.line_breaks_before(i);
+        let text = reindent(&self.source, &comment, &INDENT.repeat(self.printer.indent));
+        if breaks == 0 && !self.printer.line.trim().is_empty() {
+            self.printer.line.push(' ');
+            self.printer.line.push_str(&text);
+        } else {
+            if breaks > 1 {
+                self.printer.blank_line();
+            }
+            self.printer.newline();
+            self.printer.write(&text, false);
+        }
+        if comment.kind() == LexemeKind::LineComment || breaks > 0 {
+            self.printer.newline();
+        }
+    }
+
+    // Writes the significant token at i along with the comments before it
+    fn token(&mut self, i: usize) {
+        let mut start = i;
+        while start > 0 && self.tokens[start - 1].kind().is_trivia() {
+            start -= 1;
+        }
+        let token = self.tokens[i].clone();
+        let line_node = self.line_nodes.get(&token.span().start).copied();
+        // comments separated from the token by a blank line are not attached to it
+        let split = (start..i)
+            .rev()
+            .find(|&j| self.line_breaks_before(j + 1) > 1 && self.tokens[j].kind().is_trivia())
+            .unwrap_or(start);
+        for j in start..split {
+            if self.tokens[j].kind().is_comment() {
+                self.comment(j);
+            }
+        }
+        match line_node {
+            Some("subroutineDec") => self.printer.blank_line(),
+            Some(_) if split > start || self.line_breaks_before(start + 1) > 1 => {
+                self.printer.blank_line()
+            }
+            Some(_) => self.printer.newline(),
+            None => (),
+        }
+        for j in split..i {
+            if self.tokens[j].kind().is_comment() {
+                self.comment(j);
+            }
+        }
+        let text = token.text();
+        let parent = token.parent();
+        match text {
+            "{" => {
+                self.printer.write("{", true);
+                self.printer.indent += 1;
+                self.printer.newline();
+            }
+            "}" => {
+                self.printer.indent = self.printer.indent.saturating_sub(1);
+                self.printer.newline();
+                self.printer.write("}", false);
+                if self.next_significant(i).map(|x| x.text() == "else") != Some(true) {
+                    self.printer.newline();
+                }
+            }
+            ";" => {
+                self.printer.write(";", false);
+                self.printer.newline();
+            }
+            _ => {
+                let space = match self.prev_significant(i) {
+                    Some(prev) => needs_space(&prev, &token),
+                    None => false,
+                };
+                self.printer.write(text, space);
+            }
+        }
+        // align the declared types and names of variable declarations
+        if let Some(&(kind_width, type_width)) = self.alignments.get(&parent.trimmed_span().start) {
+            let position = parent
+                .significant_child_tokens()
+                .iter()
+                .position(|x| x.span() == token.span());
+            let width = match position {
+                Some(0) => kind_width,
+                Some(1) => type_width,
+                _ => return,
+            };
+            self.printer.line.push_str(&" ".repeat(width - text.len()));
+        }
+    }
+
+    // Returns the significant token before the token at i
+    fn prev_significant(&self, i: usize) -> Option<SyntaxToken> {
+        self.tokens[..i]
+            .iter()
+            .rev()
+            .find(|x| !x.kind().is_trivia())
+            .cloned()
+    }
+
+    // Returns the significant token after the token at i
+    fn next_significant(&self, i: usize) -> Option<SyntaxToken> {
+        self.tokens[i + 1..]
+            .iter()
+            .find(|x| !x.kind().is_trivia())
+            .cloned()
+    }
+
+    // Formats all tokens, returning the formatted source code
+    fn format(mut self) -> String {
+        for i in 0..self.tokens.len() {
+            if !self.tokens[i].kind().is_trivia() {
+                self.token(i);
+            }
+        }
+        // comments after the end of the class
+        let last = self
+            .tokens
+            .iter()
+            .rposition(|x| !x.kind().is_trivia())
+            .unwrap_or_default();
+        for j in last + 1..self.tokens.len() {
+            if self.tokens[j].kind().is_comment() {
+                self.comment(j);
+            }
+        }
+        self.printer.finish()
+    }
+}
+
+// Checks if the token is a unary operator
+fn is_unary(token: &SyntaxToken) -> bool {
+    matches!(token.text(), "-" | "~") && token.parent().kind() == "term"
+}
+
+// Checks if two consecutive tokens on the same line are separated by a space
+fn needs_space(prev: &SyntaxToken, token: &SyntaxToken) -> bool {
+    if matches!(token.text(), ";" | "," | ")" | "]" | "." | "[")
+        || matches!(prev.text(), "(" | "[" | ".")
+        || is_unary(prev)
+    {
+        return false;
+    }
+    !(token.text() == "(" && prev.kind() == LexemeKind::Identifier)
+}
+
+// Returns the text of a comment with the continuation lines of block comments re-indented
+fn reindent(source: &str, comment: &SyntaxToken, indent: &str) -> String {
+    let text = comment.text().trim_end();
+    let source_indent = {
+        let before = &source[..comment.span().start];
+        let line = &before[before.rfind('\n').map(|x| x + 1).unwrap_or(0)..];
+        line.chars().take_while(|x| x.is_whitespace()).count()
+    };
+    let mut lines = text.lines();
+    let mut res = lines.next().unwrap_or_default().trim_end().to_string();
+    for line in lines {
+        let strip = line
+            .chars()
+            .take(source_indent)
+            .take_while(|x| x.is_whitespace())
+            .map(|x| x.len_utf8())
+            .sum::<usize>();
+        res.push('\n');
+        res.push_str(indent);
+        res.push_str(line[strip..].trim_end());
+    }
+    res
+}
+
+// Formats the contents of a .jack file into the canonical layout, preserving comments
+pub fn format_source(contents: &str) -> Result<String, String> {
+    Ok(Formatter::new(&parse_cst(contents)?).format())
+}
@@ -1,10 +1,87 @@
+mod analysis;
+mod build;
 mod codegen;
 mod codewriter;
+mod cst;
+mod fix;
+mod formatter;
+mod lint;
+mod lsp;
+mod manifest;
 mod objects;
 mod parser;
+mod session;
 mod tokenizer;
 mod utils;
+mod vfs;
+mod vm;
 
-pub use codewriter::codewrite;
-pub use parser::{parse, parse_into_xml};
-pub use tokenizer::{tokenize, tokenize_into_xml};
+// The library interface: a Session compiles the classes of a program into plain data
+pub use analysis::TypeMode;
+pub use codewriter::CodegenOptions;
+pub use session::{
+    ClassInfo, Compilation, CompiledFile, Session, SessionOptions, SourceToken, SubroutineInfo,
+    SymbolTables, TokenKind, TreeElement, TreeNode, Variable, VariableKind,
+};
+pub use utils::{Diagnostic, Note, Severity, Span, Suggestion};
+pub use vfs::{DiskFiles, FileId, FileSystem, SourceFiles};
+
+// The entry points of earlier versions, kept for the code which uses them. A Session returns the
+// same results as plain data.
+#[deprecated(note = "use Session, whose results have the tokens of each file")]
+pub fn tokenize(input: &str) -> Result<Vec<utils::Token>, String> {
+    tokenizer::tokenize(input)
+}
+
+#[deprecated(note = "use Session, whose results have the tokens of each file")]
+pub fn tokenize_into_xml(input: &str) -> Result<String, String> {
+    tokenizer::tokenize_into_xml(input)
+}
+
+#[deprecated(note = "use Session, whose results have the syntax tree of each file")]
+pub fn parse(contents: &str) -> Result<utils::Grouping, String> {
+    parser::parse(contents)
+}
+
+#[deprecated(note = "use Session, whose results have the syntax tree of each file")]
+pub fn parse_into_xml(contents: &str) -> Result<String, String> {
+    parser::parse_into_xml(contents)
+}
+
+#[deprecated(note = "use Session, whose results have the VM code of each file")]
+pub fn codewrite(
+    class_grouping: &utils::Grouping,
+    program: &[&utils::Grouping],
+) -> Result<String, String> {
+    codewriter::codewrite(class_grouping, program)
+}
+
+// The internals of the compiler, which its binaries and tests use where a Session doesn't cover
+// what they do. They aren't part of the library interface and change without notice.
+#[doc(hidden)]
+pub mod internal {
+    pub use crate::analysis::{
+        check_assignments, check_class, check_returns, check_types, complete, find_references,
+        rename, Call, ClassInterface, Completion, CompletionKind, FileEdit, Location, Program,
+        Receiver, Signature, SubroutineKind, Type,
+    };
+    pub use crate::build::{build, build_in, content_hash, BuildOptions, BuildStep, Rebuild};
+    pub use crate::codewriter::{codewrite, compile_class_with, ClassOutput};
+    pub use crate::cst::{
+        ClassNode, StatementNode, SubroutineNode, SyntaxElement, SyntaxNode, SyntaxToken,
+        VarDecNode,
+    };
+    pub use crate::fix::{apply_suggestions, fix_source};
+    pub use crate::formatter::format_source;
+    pub use crate::lint::{lint, LintConfig, Rule, RULES};
+    pub use crate::lsp::serve_lsp;
+    pub use crate::manifest::{
+        jack_files, jack_files_in, jack_files_recursive, Manifest, CACHE_DIR, MANIFEST_NAME,
+    };
+    pub use crate::parser::{
+        parse, parse_class, parse_cst, parse_into_identifier_xml, parse_into_xml,
+    };
+    pub use crate::tokenizer::{lex, tokenize, tokenize_into_xml};
+    pub use crate::utils::{line_col, parse_toml, Json, Lexeme, LexemeKind, LineIndex};
+    pub use crate::vm::run_vm;
+}
@@ -0,0 +1,111 @@
+pub mod rules;
+
+pub use rules::*;
+
+use crate::parser::parse_class;
+use crate::tokenizer::lex;
+use crate::utils::{line_col, parse_toml, Diagnostic, Grouping, Json, LexemeKind};
+
+static DISABLE_LINE: &str = "jacklint-disable-line";
+static DISABLE_NEXT_LINE: &str = "jacklint-disable-next-line";
+
+#[derive(Debug, Clone, Default)]
+// Represents which lint rules are enabled, all of them by default
+pub struct LintConfig {
+    disabled: Vec<&'static str>,
+}
+
+impl LintConfig {
+    // Reads a configuration file, whose [rules] table enables or disables rules by name:
+    //
+    // [rules]
+    // unused-variable = false
+    pub fn from_toml(contents: &str) -> Result<LintConfig, String> {
+        let toml = parse_toml(contents)?;
+        let mut config = LintConfig::default();
+        if let Some(rules) = toml.get("rules") {
+            let rules = match rules {
+                Json::Object(members) => members,
+                _ => return Err("'rules' must be a table".to_string()),
+            };
+            for (name, enabled) in rules {
+                let enabled = enabled
+                    .as_bool()
+                    .ok_or(format!("Rule '{}' must be set to true or false", name))?;
+                config.set_enabled(name, enabled)?;
+            }
+        }
+        Ok(config)
+    }
+
+    // Enables or disables a rule by name
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        let rule = RULES
+            .iter()
+            .find(|x| x.name == name)
+            .ok_or(format!("Unknown lint rule '{}'", name))?;
+        self.disabled.retain(|x| *x != rule.name);
+        if !enabled {
+            self.disabled.push(rule.name);
+        }
+        Ok(())
+    }
+
+    // Checks if a rule is enabled
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.disabled.contains(&name)
+    }
+}
+
+// Returns the suppression comments of a source as (line, rules) pairs, where no rules means all
+// rules are suppressed on the line
+fn suppressions(contents: &str) -> Vec<(usize, Vec<&str>)> {
+    let mut res = vec![];
+    for lexeme in lex(contents) {
+        if !lexeme.kind.is_comment() {
+            continue;
+        }
+        let text = lexeme.span.text(contents);
+        let text = match lexeme.kind {
+            LexemeKind::LineComment => &text[2..],
+            _ => text.trim_start_matches("/*").trim_end_matches("*/"),
+        }
+        .trim();
+        let (line, _) = line_col(contents, lexeme.span.start);
+        let (line, rules) = if let Some(rules) = text.strip_prefix(DISABLE_NEXT_LINE) {
+            (line + 1, rules)
+        } else if let Some(rules) = text.strip_prefix(DISABLE_LINE) {
+            (line, rules)
+        } else {
+            continue;
+        };
+        let rules = rules.split([',', ' ']).filter(|x| !x.is_empty()).collect();
+        res.push((line, rules));
+    }
+    res
+}
+
+// Runs the enabled lint rules on a .jack file, given the other classes of the program.
+// Warnings on lines with a suppression comment for their rule are left out, e.g.
+// `// jacklint-disable-line unused-variable` or `// jacklint-disable-next-line`.
+pub fn lint(
+    contents: &str,
+    program: &[&Grouping],
+    config: &LintConfig,
+) -> Result<Vec<Diagnostic>, Diagnostic> {
+    let class_grouping = parse_class(contents)?;
+    let suppressions = suppressions(contents);
+    let mut res = RULES
+        .iter()
+        .filter(|x| config.is_enabled(x.name))
+        .flat_map(|x| (x.check)(&class_grouping, program))
+        .filter(|d| {
+            let (line, _) = line_col(contents, d.span.start);
+            !suppressions
+                .iter()
+                .any(|(l, rules)| *l == line && (rules.is_empty() || rules.contains(&d.code)))
+        })
+        .collect::<Vec<_>>();
+    res.sort_by_key(|x| x.span.start);
+    Ok(res)
+}
@@ -0,0 +1,273 @@
+use crate::analysis::{
+    identifiers, is_keyword, never_completes, return_statements, subroutines, Category, Program,
+    Type,
+};
+use crate::utils::symbol_table::{create_subroutine_symbol_table, create_symbol_table};
+use crate::utils::{Diagnostic, GroupItem, Grouping, Span, SymbolKind, Token};
+
+// Represents a lint rule, which checks a class given the other classes of the program
+pub struct Rule {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub check: fn(&Grouping, &[&Grouping]) -> Vec<Diagnostic>,
+}
+
+pub static RULES: &[Rule] = &[
+    Rule {
+        name: "unused-variable",
+        description: "locals, parameters, fields and statics which are never used",
+        check: unused_variable,
+    },
+    Rule {
+        name: "unreachable-code",
+        description: "statements which can't run because every path before them returns",
+        check: unreachable_code,
+    },
+    Rule {
+        name: "constant-condition",
+        description: "if and while conditions which are always true or false",
+        check: constant_condition,
+    },
+    Rule {
+        name: "shadowed-field",
+        description: "locals and parameters with the name of a field or static",
+        check: shadowed_field,
+    },
+    Rule {
+        name: "empty-if",
+        description: "if and else branches without statements",
+        check: empty_if,
+    },
+    Rule {
+        name: "compare-to-true",
+        description: "comparisons to true, which are the same as the condition itself",
+        check: compare_to_true,
+    },
+    Rule {
+        name: "discarded-result",
+        description: "do statements calling a subroutine which returns a value",
+        check: discarded_result,
+    },
+];
+
+// Returns all groupings of a kind in a grouping, including the grouping itself
+fn groupings<'a>(grouping: &'a Grouping, name: &str) -> Vec<&'a Grouping> {
+    let mut res = vec![];
+    if grouping.name == name {
+        res.push(grouping);
+    }
+    for g in grouping.subgroupings() {
+        res.extend(groupings(g, name));
+    }
+    res
+}
+
+// Reports variables which are declared but never used
+fn unused_variable(class_grouping: &Grouping, _: &[&Grouping]) -> Vec<Diagnostic> {
+    let ids = identifiers(class_grouping).unwrap_or_default();
+    let used = ids
+        .iter()
+        .filter(|x| !x.defined)
+        .filter_map(|x| match &x.category {
+            Category::Variable(Some(symbol)) => Some(symbol.span()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    ids.iter()
+        .filter(|x| x.defined && !used.contains(&x.span))
+        .filter_map(|x| match &x.category {
+            Category::Variable(Some(symbol)) => {
+                let kind = match symbol.kind() {
+                    SymbolKind::Static => "Static variable",
+                    SymbolKind::Field => "Field",
+                    SymbolKind::Argument => "Parameter",
+                    SymbolKind::Local => "Local variable",
+                };
+                Some(Diagnostic::warning(
+                    "unused-variable",
+                    format!("{} '{}' is never used", kind, x.name),
+                    x.span,
+                ))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// Reports the first statement of a block after a statement which never completes
+fn unreachable_code(class_grouping: &Grouping, _: &[&Grouping]) -> Vec<Diagnostic> {
+    groupings(class_grouping, "statements")
+        .into_iter()
+        .filter_map(|block| {
+            let statements = block.subgroupings();
+            let i = statements.iter().position(|x| never_completes(x))?;
+            let unreachable = statements.get(i + 1)?;
+            Some(
+                Diagnostic::warning(
+                    "unreachable-code",
+                    "Unreachable statement".to_string(),
+                    unreachable.span(),
+                )
+                .with_note(
+                    "every path through this statement returns".to_string(),
+                    Some(statements[i].spanned_tokens()[0].1),
+                ),
+            )
+        })
+        .collect()
+}
+
+// Reports conditions which are the constants true or false, except for loops which are left
+// by returning
+fn constant_condition(class_grouping: &Grouping, _: &[&Grouping]) -> Vec<Diagnostic> {
+    let mut res = vec![];
+    for statement in [
+        groupings(class_grouping, "ifStatement"),
+        groupings(class_grouping, "whileStatement"),
+    ]
+    .concat()
+    {
+        let blocks = statement.subgroupings();
+        let condition = blocks[0];
+        let message = if is_keyword(condition, "false") {
+            "Condition is always false"
+        } else if !is_keyword(condition, "true") {
+            continue;
+        } else if statement.name == "ifStatement" {
+            "Condition is always true"
+        } else if return_statements(blocks[1]).is_empty() {
+            "Loop never exits because its condition is always true"
+        } else {
+            continue;
+        };
+        res.push(Diagnostic::warning(
+            "constant-condition",
+            message.to_string(),
+            condition.span(),
+        ));
+    }
+    res
+}
+
+// Reports locals and parameters with the name of a field or static, which they hide
+fn shadowed_field(class_grouping: &Grouping, _: &[&Grouping]) -> Vec<Diagnostic> {
+    let class_symbol_table = match create_symbol_table(class_grouping) {
+        Ok(st) => st,
+        Err(_) => return vec![],
+    };
+    let mut res = vec![];
+    for subroutine in subroutines(class_grouping) {
+        let st = match create_subroutine_symbol_table(subroutine, &class_symbol_table) {
+            Ok(st) => st,
+            Err(_) => continue,
+        };
+        for symbol in st.symbols().iter().filter(|x| x.name() != "this") {
+            if let Ok(field) = class_symbol_table.get(symbol.name()) {
+                let kind = match field.kind() {
+                    SymbolKind::Static => "static",
+                    _ => "field",
+                };
+                res.push(
+                    Diagnostic::warning(
+                        "shadowed-field",
+                        format!(
+                            "'{}' shadows the {} '{}'",
+                            symbol.name(),
+                            kind,
+                            field.name()
+                        ),
+                        symbol.span(),
+                    )
+                    .with_note(format!("the {} is declared here", kind), Some(field.span())),
+                );
+            }
+        }
+    }
+    res
+}
+
+// Reports if and else branches without statements
+fn empty_if(class_grouping: &Grouping, _: &[&Grouping]) -> Vec<Diagnostic> {
+    let mut res = vec![];
+    for statement in groupings(class_grouping, "ifStatement") {
+        let keywords = statement
+            .spanned_tokens()
+            .into_iter()
+            .filter(|(x, _)| matches!(x, Token::Keyword(_)))
+            .collect::<Vec<_>>();
+        for (block, (keyword, span)) in statement.subgroupings()[1..].iter().zip(keywords) {
+            if block.subgroupings().is_empty() {
+                res.push(Diagnostic::warning(
+                    "empty-if",
+                    format!("Empty {} branch", keyword.as_text()),
+                    span,
+                ));
+            }
+        }
+    }
+    res
+}
+
+// Reports comparisons to true
+fn compare_to_true(class_grouping: &Grouping, _: &[&Grouping]) -> Vec<Diagnostic> {
+    let mut res = vec![];
+    for expression in groupings(class_grouping, "expression") {
+        let items = &expression.items;
+        for (i, item) in items.iter().enumerate() {
+            let (lhs, rhs) = match (item, i.checked_sub(1).map(|i| &items[i]), items.get(i + 1)) {
+                (
+                    GroupItem::Token(Token::Symbol('='), _),
+                    Some(GroupItem::Grouping(lhs)),
+                    Some(GroupItem::Grouping(rhs)),
+                ) => (lhs, rhs),
+                _ => continue,
+            };
+            let is_true = |term: &Grouping| matches!(term.tokens()[..], [Token::Keyword("true")]);
+            if is_true(lhs) || is_true(rhs) {
+                res.push(Diagnostic::warning(
+                    "compare-to-true",
+                    "Comparing to true is redundant, use the value itself".to_string(),
+                    lhs.span().cover(rhs.span()),
+                ));
+            }
+        }
+    }
+    res
+}
+
+// Reports do statements calling a subroutine which returns a value
+fn discarded_result(class_grouping: &Grouping, program: &[&Grouping]) -> Vec<Diagnostic> {
+    let class_symbol_table = match create_symbol_table(class_grouping) {
+        Ok(st) => st,
+        Err(_) => return vec![],
+    };
+    let program = Program::new(&[&[class_grouping], program].concat());
+    let mut res = vec![];
+    for subroutine in subroutines(class_grouping) {
+        let st = match create_subroutine_symbol_table(subroutine, &class_symbol_table) {
+            Ok(st) => st,
+            Err(_) => continue,
+        };
+        for statement in groupings(subroutine, "doStatement") {
+            let tokens = statement.spanned_tokens();
+            let call = match program.resolve_call(&statement.tokens()[1..], &st) {
+                Ok(call) => call,
+                Err(_) => continue,
+            };
+            let return_type = match call.signature {
+                Some(signature) if signature.return_type != Type::Void => &signature.return_type,
+                _ => continue,
+            };
+            let span = Span::new(tokens[1].1.start, tokens[tokens.len() - 2].1.end);
+            res.push(Diagnostic::warning(
+                "discarded-result",
+                format!(
+                    "The {} returned by '{}.{}' is discarded",
+                    return_type, call.class, call.name
+                ),
+                span,
+            ));
+        }
+    }
+    res
+}
@@ -0,0 +1,703 @@
+use crate::analysis::{
+    check_class, check_types, class_name, complete, find_references, identifiers, rename,
+    subroutine_name, subroutine_signature, subroutines, Category, CompletionKind, TypeMode,
+};
+use crate::manifest::jack_files_recursive;
+use crate::parser::parse_class;
+use crate::utils::{Diagnostic, Grouping, Json, Severity, Span, SymbolKind, Token};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+
+// Represents a source file known to the server, either opened in the editor or found on disk
+struct Document {
+    text: String,
+    open: bool,
+}
+
+// Represents the state of the language server
+struct Server {
+    documents: BTreeMap<String, Document>,
+    outgoing: Vec<Json>,
+}
+
+// The largest message body the server reads, so that a wrong Content-Length can't exhaust memory
+const MAX_CONTENT_LENGTH: u64 = 1 << 24;
+
+// Reads one JSON-RPC message framed by a Content-Length header, None at the end of input. A
+// message that can't be read is skipped and returned as a JSON-RPC error code and message.
+fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Result<Json, (i64, String)>>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = Some(value.trim().to_string());
+        }
+    }
+    let length = length.unwrap_or_default();
+    let length = match length.parse::<u64>() {
+        Ok(length) => length,
+        Err(_) => {
+            return Ok(Some(Err((
+                -32600,
+                format!("Invalid Content-Length '{}'", length),
+            ))))
+        }
+    };
+    if length > MAX_CONTENT_LENGTH {
+        io::copy(&mut reader.take(length), &mut io::sink())?;
+        return Ok(Some(Err((
+            -32600,
+            format!(
+                "Message of {} bytes exceeds the limit of {} bytes",
+                length, MAX_CONTENT_LENGTH
+            ),
+        ))));
+    }
+    let mut body = vec![];
+    reader.take(length).read_to_end(&mut body)?;
+    if (body.len() as u64) < length {
+        return Ok(None);
+    }
+    let body = String::from_utf8_lossy(&body);
+    Ok(Some(Json::parse(&body).map_err(|e| (-32700, e))))
+}
+
+// Writes one JSON-RPC message framed by a Content-Length header
+fn write_message(writer: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+// Converts a file URI into a path
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let mut bytes = vec![];
+    let mut chars = path.bytes();
+    while let Some(b) = chars.next() {
+        if b == b'%' {
+            let hex = [chars.next()?, chars.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
+
+// Converts a path into a file URI
+fn path_to_uri(path: &Path) -> String {
+    let mut res = "file://".to_string();
+    for b in path.to_string_lossy().bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+            res.push(b as char);
+        } else {
+            res += &format!("%{:02X}", b);
+        }
+    }
+    res
+}
+
+// Converts a byte offset into an LSP position, with the character counted in UTF-16 code units
+fn position(text: &str, offset: usize) -> Json {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    Json::object(vec![
+        ("line", before.matches('\n').count().into()),
+        (
+            "character",
+            before[line_start..].encode_utf16().count().into(),
+        ),
+    ])
+}
+
+// Converts an LSP position into a byte offset
+fn offset(text: &str, position: &Json) -> usize {
+    let line = position.get("line").and_then(|x| x.as_usize()).unwrap_or(0);
+    let character = position
+        .get("character")
+        .and_then(|x| x.as_usize())
+        .unwrap_or(0);
+    let line_start = text
+        .match_indices('\n')
+        .nth(line.wrapping_sub(1))
+        .map(|(i, _)| i + 1)
+        .filter(|_| line > 0)
+        .unwrap_or(0);
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+// Converts a span into an LSP range
+fn range(text: &str, span: Span) -> Json {
+    Json::object(vec![
+        ("start", position(text, span.start)),
+        ("end", position(text, span.end)),
+    ])
+}
+
+// Converts a span into an LSP location
+fn location(uri: &str, text: &str, span: Span) -> Json {
+    Json::object(vec![("uri", uri.into()), ("range", range(text, span))])
+}
+
+// Converts a diagnostic into an LSP diagnostic
+fn lsp_diagnostic(uri: &str, text: &str, diagnostic: &Diagnostic) -> Json {
+    let related = diagnostic
+        .notes
+        .iter()
+        .filter_map(|note| {
+            Some(Json::object(vec![
+                ("location", location(uri, text, note.span?)),
+                ("message", note.message.as_str().into()),
+            ]))
+        })
+        .collect::<Vec<_>>();
+    Json::object(vec![
+        ("range", range(text, diagnostic.span)),
+        (
+            "severity",
+            match diagnostic.severity {
+                Severity::Error => 1,
+                Severity::Warning => 2,
+            }
+            .into(),
+        ),
+        ("code", diagnostic.code.into()),
+        ("source", "jack".into()),
+        ("message", diagnostic.message.as_str().into()),
+        ("relatedInformation", related.into()),
+    ])
+}
+
+// Returns the LSP symbol of a declaration
+fn document_symbol(
+    text: &str,
+    name: &str,
+    detail: String,
+    kind: usize,
+    span: Span,
+    selection: Span,
+    children: Vec<Json>,
+) -> Json {
+    Json::object(vec![
+        ("name", name.into()),
+        ("detail", detail.into()),
+        ("kind", kind.into()),
+        ("range", range(text, span)),
+        ("selectionRange", range(text, selection)),
+        ("children", children.into()),
+    ])
+}
+
+impl Server {
+    // Returns the documents that parse as a class, open documents first
+    fn classes(&self) -> Vec<(&str, &str, Grouping)> {
+        let mut res = self
+            .documents
+            .iter()
+            .filter_map(|(uri, doc)| {
+                Some((
+                    uri.as_str(),
+                    doc.text.as_str(),
+                    parse_class(&doc.text).ok()?,
+                ))
+            })
+            .collect::<Vec<_>>();
+        res.sort_by_key(|(uri, _, _)| !self.documents[*uri].open);
+        res
+    }
+
+    // Returns the document declaring a class
+    fn find_class(&self, name: &str) -> Option<(&str, &str, Grouping)> {
+        self.classes()
+            .into_iter()
+            .find(|(_, _, g)| class_name(g) == Some(name))
+    }
+
+    // Queues a notification with the diagnostics of a document
+    fn publish_diagnostics(&mut self, uri: &str) {
+        let text = match self.documents.get(uri) {
+            Some(doc) if doc.open => doc.text.clone(),
+            _ => String::new(),
+        };
+        let diagnostics = match parse_class(&text) {
+            _ if text.is_empty() => vec![],
+            Ok(g) => {
+                let classes = self.classes();
+                let others = classes
+                    .iter()
+                    .filter(|(x, _, _)| *x != uri)
+                    .map(|(_, _, g)| g)
+                    .collect::<Vec<_>>();
+                let mut diagnostics = check_class(&g);
+                diagnostics.extend(check_types(&g, &others, TypeMode::Lenient));
+                diagnostics.sort_by_key(|x| x.span.start);
+                diagnostics
+            }
+            Err(d) => vec![d],
+        };
+        self.outgoing.push(Json::object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            (
+                "params",
+                Json::object(vec![
+                    ("uri", uri.into()),
+                    (
+                        "diagnostics",
+                        diagnostics
+                            .iter()
+                            .map(|d| lsp_diagnostic(uri, &text, d))
+                            .collect::<Vec<_>>()
+                            .into(),
+                    ),
+                ]),
+            ),
+        ]));
+    }
+
+    // Loads the .jack files of the workspace root
+    fn initialize(&mut self, params: &Json) -> Json {
+        let root = params
+            .path(&["rootUri"])
+            .and_then(|x| x.as_str())
+            .or_else(|| {
+                params
+                    .get("workspaceFolders")?
+                    .as_array()?
+                    .first()?
+                    .get("uri")?
+                    .as_str()
+            })
+            .and_then(uri_to_path);
+        if let Some(root) = root {
+            for path in jack_files_recursive(&root).unwrap_or_default() {
+                if let Ok(text) = fs::read_to_string(&path) {
+                    self.documents
+                        .insert(path_to_uri(&path), Document { text, open: false });
+                }
+            }
+        }
+        Json::object(vec![
+            (
+                "capabilities",
+                Json::object(vec![
+                    ("textDocumentSync", 1.into()),
+                    ("hoverProvider", true.into()),
+                    ("definitionProvider", true.into()),
+                    ("documentSymbolProvider", true.into()),
+                    ("referencesProvider", true.into()),
+                    ("renameProvider", true.into()),
+                    (
+                        "completionProvider",
+                        Json::object(vec![("triggerCharacters", vec!["."].into())]),
+                    ),
+                ]),
+            ),
+            (
+                "serverInfo",
+                Json::object(vec![("name", "jack-lsp".into())]),
+            ),
+        ])
+    }
+
+    // Returns the document symbols of a class: the class, its fields, statics and subroutines
+    fn document_symbols(&self, uri: &str) -> Json {
+        let text = match self.documents.get(uri) {
+            Some(doc) => &doc.text,
+            None => return Json::Null,
+        };
+        let class_grouping = match parse_class(text) {
+            Ok(g) => g,
+            Err(_) => return Json::Array(vec![]),
+        };
+        let mut children = vec![];
+        for class_var in class_grouping
+            .subgroupings()
+            .into_iter()
+            .filter(|x| x.name == "classVarDec")
+        {
+            let tokens = class_var.spanned_tokens();
+            let kind = match tokens[0].0 {
+                Token::Keyword("static") => 14,
+                _ => 8,
+            };
+            for (token, span) in &tokens[2..] {
+                if let Token::Identifier(name) = token {
+                    let detail = format!("{} {}", tokens[0].0.as_text(), tokens[1].0.as_text());
+                    children.push(document_symbol(
+                        text,
+                        name,
+                        detail,
+                        kind,
+                        class_var.span(),
+                        *span,
+                        vec![],
+                    ));
+                }
+            }
+        }
+        for subroutine in subroutines(&class_grouping) {
+            if let Some((name, span)) = subroutine_name(subroutine) {
+                let kind = match subroutine.tokens()[0] {
+                    Token::Keyword("constructor") => 9,
+                    Token::Keyword("method") => 6,
+                    _ => 12,
+                };
+                children.push(document_symbol(
+                    text,
+                    name,
+                    subroutine_signature(subroutine),
+                    kind,
+                    subroutine.span(),
+                    span,
+                    vec![],
+                ));
+            }
+        }
+        let name = class_name(&class_grouping).unwrap_or_default();
+        Json::Array(vec![document_symbol(
+            text,
+            name,
+            format!("class {}", name),
+            5,
+            class_grouping.span(),
+            class_grouping.spanned_tokens()[1].1,
+            children,
+        )])
+    }
+
+    // Returns the hover text or definition location of the identifier at a position
+    fn identifier_request(&self, params: &Json, definition: bool) -> Json {
+        let uri = params
+            .path(&["textDocument", "uri"])
+            .and_then(|x| x.as_str())
+            .unwrap_or_default();
+        let text = match self.documents.get(uri) {
+            Some(doc) => &doc.text,
+            None => return Json::Null,
+        };
+        let offset = offset(text, params.get("position").unwrap_or(&Json::Null));
+        let class_grouping = match parse_class(text) {
+            Ok(g) => g,
+            Err(_) => return Json::Null,
+        };
+        let ids = identifiers(&class_grouping).unwrap_or_default();
+        let id = match ids.iter().find(|x| x.span.contains(offset)) {
+            Some(id) => id,
+            None => return Json::Null,
+        };
+        let (hover, target) = match &id.category {
+            Category::Variable(Some(symbol)) => {
+                let declaration = match symbol.kind() {
+                    SymbolKind::Static => "static",
+                    SymbolKind::Field => "field",
+                    SymbolKind::Argument => "argument",
+                    SymbolKind::Local => "var",
+                };
+                let hover = format!(
+                    "```jack\n{} {} {}\n```\nsegment: `{}`",
+                    declaration,
+                    symbol.class(),
+                    symbol.name(),
+                    symbol.literal()
+                );
+                let target =
+                    (symbol.span() != Span::default()).then(|| location(uri, text, symbol.span()));
+                (hover, target)
+            }
+            Category::Variable(None) => return Json::Null,
+            Category::Class => {
+                let target = self
+                    .find_class(id.name)
+                    .map(|(uri, text, g)| location(uri, text, g.spanned_tokens()[1].1));
+                (format!("```jack\nclass {}\n```", id.name), target)
+            }
+            Category::Subroutine => {
+                let class = id.class.as_deref().unwrap_or_default();
+                let found = self.find_class(class).and_then(|(uri, text, g)| {
+                    let subroutine = subroutines(&g)
+                        .into_iter()
+                        .find(|x| subroutine_name(x).map(|x| x.0) == Some(id.name))?;
+                    Some((
+                        subroutine_signature(subroutine),
+                        location(uri, text, subroutine_name(subroutine)?.1),
+                    ))
+                });
+                match found {
+                    Some((signature, target)) => (
+                        format!("```jack\n{}\n```\nclass {}", signature, class),
+                        Some(target),
+                    ),
+                    None => return Json::Null,
+                }
+            }
+        };
+        if definition {
+            return target.unwrap_or(Json::Null);
+        }
+        Json::object(vec![
+            (
+                "contents",
+                Json::object(vec![("kind", "markdown".into()), ("value", hover.into())]),
+            ),
+            ("range", range(text, id.span)),
+        ])
+    }
+
+    // Returns the completion candidates at a position, offering the members of the other
+    // documents of the workspace too
+    fn completion(&self, params: &Json) -> Json {
+        let uri = params
+            .path(&["textDocument", "uri"])
+            .and_then(|x| x.as_str())
+            .unwrap_or_default();
+        let text = match self.documents.get(uri) {
+            Some(doc) => &doc.text,
+            None => return Json::Null,
+        };
+        let offset = offset(text, params.get("position").unwrap_or(&Json::Null));
+        let others = self
+            .documents
+            .iter()
+            .filter(|(x, _)| *x != uri)
+            .map(|(_, doc)| doc.text.as_str())
+            .collect::<Vec<_>>();
+        complete(text, offset, &others)
+            .into_iter()
+            .map(|x| {
+                let kind: usize = match x.kind {
+                    CompletionKind::Keyword => 14,
+                    CompletionKind::Variable => 6,
+                    CompletionKind::Subroutine => 3,
+                    CompletionKind::Class => 7,
+                };
+                Json::object(vec![
+                    ("label", x.label.into()),
+                    ("kind", kind.into()),
+                    ("detail", x.detail.into()),
+                ])
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    // Returns the locations of all references to the identifier at a position
+    fn references(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let uri = params
+            .path(&["textDocument", "uri"])
+            .and_then(|x| x.as_str())
+            .unwrap_or_default();
+        let text = match self.documents.get(uri) {
+            Some(doc) => &doc.text,
+            None => return Ok(Json::Null),
+        };
+        let offset = offset(text, params.get("position").unwrap_or(&Json::Null));
+        let files = self.files();
+        let locations = find_references(&files, uri, offset).map_err(|e| (-32803, e))?;
+        Ok(locations
+            .iter()
+            .map(|x| location(&x.path, &self.documents[&x.path].text, x.span))
+            .collect::<Vec<_>>()
+            .into())
+    }
+
+    // Returns the workspace edit renaming the identifier at a position, which replaces the
+    // contents of the changed documents and renames the file of a renamed class
+    fn rename(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let uri = params
+            .path(&["textDocument", "uri"])
+            .and_then(|x| x.as_str())
+            .unwrap_or_default();
+        let text = match self.documents.get(uri) {
+            Some(doc) => &doc.text,
+            None => return Ok(Json::Null),
+        };
+        let offset = offset(text, params.get("position").unwrap_or(&Json::Null));
+        let new_name = params
+            .get("newName")
+            .and_then(|x| x.as_str())
+            .unwrap_or_default();
+        let files = self.files();
+        let edits = rename(&files, uri, offset, new_name).map_err(|e| (-32803, e))?;
+        let mut changes = vec![];
+        for edit in edits {
+            let old_text = &self.documents[&edit.path].text;
+            changes.push(Json::object(vec![
+                (
+                    "textDocument",
+                    Json::object(vec![
+                        ("uri", edit.path.as_str().into()),
+                        ("version", Json::Null),
+                    ]),
+                ),
+                (
+                    "edits",
+                    vec![Json::object(vec![
+                        ("range", range(old_text, Span::new(0, old_text.len()))),
+                        ("newText", edit.contents.into()),
+                    ])]
+                    .into(),
+                ),
+            ]));
+            if let Some(new_path) = edit.new_path {
+                changes.push(Json::object(vec![
+                    ("kind", "rename".into()),
+                    ("oldUri", edit.path.into()),
+                    ("newUri", new_path.into()),
+                ]));
+            }
+        }
+        Ok(Json::object(vec![("documentChanges", changes.into())]))
+    }
+
+    // Returns the documents as (uri, contents) pairs
+    fn files(&self) -> Vec<(&str, &str)> {
+        self.documents
+            .iter()
+            .map(|(uri, doc)| (uri.as_str(), doc.text.as_str()))
+            .collect()
+    }
+
+    // Handles a request, returning its result or an error code and message
+    fn request(&mut self, method: &str, params: &Json) -> Result<Json, (i64, String)> {
+        match method {
+            "initialize" => Ok(self.initialize(params)),
+            "shutdown" => Ok(Json::Null),
+            "textDocument/documentSymbol" => Ok(self.document_symbols(
+                params
+                    .path(&["textDocument", "uri"])
+                    .and_then(|x| x.as_str())
+                    .unwrap_or_default(),
+            )),
+            "textDocument/hover" => Ok(self.identifier_request(params, false)),
+            "textDocument/definition" => Ok(self.identifier_request(params, true)),
+            "textDocument/completion" => Ok(self.completion(params)),
+            "textDocument/references" => self.references(params),
+            "textDocument/rename" => self.rename(params),
+            _ => Err((-32601, format!("Method not found: {}", method))),
+        }
+    }
+
+    // Handles a notification
+    fn notification(&mut self, method: &str, params: &Json) {
+        let uri = params
+            .path(&["textDocument", "uri"])
+            .and_then(|x| x.as_str())
+            .unwrap_or_default()
+            .to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params
+                    .path(&["textDocument", "text"])
+                    .and_then(|x| x.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                self.documents
+                    .insert(uri.clone(), Document { text, open: true });
+                self.publish_diagnostics(&uri);
+            }
+            "textDocument/didChange" => {
+                let text = params
+                    .get("contentChanges")
+                    .and_then(|x| x.as_array())
+                    .and_then(|x| x.last())
+                    .and_then(|x| x.get("text"))
+                    .and_then(|x| x.as_str());
+                if let Some(text) = text {
+                    self.documents.insert(
+                        uri.clone(),
+                        Document {
+                            text: text.to_string(),
+                            open: true,
+                        },
+                    );
+                    self.publish_diagnostics(&uri);
+                }
+            }
+            "textDocument/didClose" => {
+                match uri_to_path(&uri).and_then(|x| fs::read_to_string(x).ok()) {
+                    Some(text) => {
+                        self.documents
+                            .insert(uri.clone(), Document { text, open: false });
+                    }
+                    None => {
+                        self.documents.remove(&uri);
+                    }
+                }
+                self.publish_diagnostics(&uri);
+            }
+            _ => (),
+        }
+    }
+}
+
+// Returns the response to the request with the given id
+fn response(id: Json, result: Result<Json, (i64, String)>) -> Json {
+    let result = match result {
+        Ok(result) => ("result", result),
+        Err((code, message)) => (
+            "error",
+            Json::object(vec![
+                ("code", Json::Number(code as f64)),
+                ("message", message.into()),
+            ]),
+        ),
+    };
+    Json::object(vec![("jsonrpc", "2.0".into()), ("id", id), result])
+}
+
+// Runs a Language Server Protocol server, reading JSON-RPC messages until the exit notification
+pub fn serve_lsp(reader: &mut impl BufRead, writer: &mut impl Write) -> io::Result<()> {
+    let mut server = Server {
+        documents: BTreeMap::new(),
+        outgoing: vec![],
+    };
+    while let Some(message) = read_message(reader)? {
+        let message = match message {
+            Ok(message) => message,
+            Err(error) => {
+                write_message(writer, &response(Json::Null, Err(error)))?;
+                continue;
+            }
+        };
+        let method = message
+            .get("method")
+            .and_then(|x| x.as_str())
+            .unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+        if method == "exit" {
+            break;
+        }
+        match message.get("id") {
+            Some(id) => {
+                let result = server.request(method, &params);
+                write_message(writer, &response(id.clone(), result))?;
+            }
+            None => server.notification(method, &params),
+        }
+        for message in server.outgoing.drain(..) {
+            write_message(writer, &message)?;
+        }
+    }
+    Ok(())
+}
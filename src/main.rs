@@ -1,14 +1,659 @@
 use std::env;
 use std::fs;
-use std::iter::Iterator;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, SystemTime};
 
-use lib::{codewrite, parse};
+use lib::internal::{
+    build, fix_source, jack_files, parse_class, parse_into_xml, run_vm, tokenize_into_xml,
+    BuildOptions, Json, Manifest, CACHE_DIR,
+};
+use lib::{CodegenOptions, Compilation, Diagnostic, Session, SessionOptions, Severity, Span};
 
-fn main() {
-    let path = env::args()
-        .nth(1)
-        .expect("Path to source code to compile not provided");
-    let contents = fs::read_to_string(path).unwrap();
-    let class_grouping = parse(&contents).unwrap();
-    codewrite(&class_grouping);
+static USAGE: &str = "Usage: main [COMMAND] [OPTIONS] [FILE_OR_DIRECTORY ...]
+Compiles Jack programs. Without paths, the project of the jack.toml in the current directory or
+one of its parents is used. A path of - reads a single class from stdin and writes to stdout.
+
+Commands:
+  tokenize   Write the tokens of each file to NameT.xml
+  parse      Write the syntax tree of each file to Name.xml
+  check      Report errors and warnings without writing any files
+  build      Compile each file to Name.vm (the default)
+  run        Compile the program and run it, with a built-in OS
+
+Options:
+  -o, --out-dir DIR   Write the output files to DIR instead of next to their sources
+  -j, --jobs N        Compile N files at the same time (build only)
+  --verbose           Report which files were compiled and why (build only)
+  --watch             Build again whenever a file changes, until stopped (build only)
+  --source-map        Write a source map of each Name.vm to Name.vm.map, giving the Jack line,
+                      column and subroutine of every VM command (build only)
+  --source-comments   Write the first line of each Jack statement as a comment before its VM
+                      commands (build only)
+  --reference         Generate the same VM code as the official nand2tetris JackCompiler, label
+                      names included, to compare the two with diff (build only)
+  -O0, -O1            Leave the VM code as it is generated (the default), or remove redundant
+                      commands with peephole optimizations and report how many (build only)
+  --intern-strings    Make each distinct string literal of a class once, when it is first used,
+                      instead of on every evaluation. The literal is then the same String object
+                      everywhere in the class: changes made to it are seen by its later uses, and
+                      it must not be disposed (build only)
+  --fix               Apply the suggested fixes of syntax errors to the files first (check and
+                      build only)
+  --message-format F  Print diagnostics as human readable text or as JSON, one object per line,
+                      with the fields of a diagnostic: file, severity, code, message, line,
+                      column, endLine, endColumn, notes and fixes (default: human)
+
+Diagnostics are printed to stdout by check, and to stderr by the other commands.
+
+The exit code is 1 if the program has errors, and 2 for invalid arguments or unreadable files.";
+
+// How often --watch checks if the files changed
+static POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+static COMMANDS: &[&str] = &["tokenize", "parse", "check", "build", "run"];
+
+// Represents the parsed command line
+struct Options {
+    command: String,
+    out_dir: Option<PathBuf>,
+    jobs: Option<usize>,
+    verbose: bool,
+    watch: bool,
+    fix: bool,
+    source_map: bool,
+    source_comments: bool,
+    reference: bool,
+    opt_level: u8,
+    intern_strings: bool,
+    message_format: MessageFormat,
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Represents how diagnostics are printed
+enum MessageFormat {
+    Human,
+    // One JSON object per line, for tools reading the output
+    Json,
+}
+
+// Parses the command line arguments, where the command is optional for compatibility with the
+// time main could only build
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut args = args.iter().peekable();
+    let command = match args.peek() {
+        Some(x) if COMMANDS.contains(&x.as_str()) => args.next().unwrap().clone(),
+        _ => "build".to_string(),
+    };
+    let mut options = Options {
+        command,
+        out_dir: None,
+        jobs: None,
+        verbose: false,
+        watch: false,
+        fix: false,
+        source_map: false,
+        source_comments: false,
+        reference: false,
+        opt_level: 0,
+        intern_strings: false,
+        message_format: MessageFormat::Human,
+        paths: vec![],
+    };
+    while let Some(arg) = args.next() {
+        // options with a value can also be written as --option=value
+        let (arg, inline) = match arg.split_once('=') {
+            Some((option, value)) if option.starts_with("--") => (option, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next().cloned())
+                .ok_or(format!("Missing value for {}", arg))
+        };
+        match arg {
+            "-o" | "--out-dir" => options.out_dir = Some(PathBuf::from(value()?)),
+            "-j" | "--jobs" => {
+                let n = value()?;
+                options.jobs = match n.parse::<usize>() {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => return Err(format!("Invalid number of jobs '{}'", n)),
+                };
+            }
+            "--message-format" => {
+                options.message_format = match value()?.as_str() {
+                    "human" => MessageFormat::Human,
+                    "json" => MessageFormat::Json,
+                    format => return Err(format!("Unknown message format '{}'", format)),
+                }
+            }
+            "--verbose" => options.verbose = true,
+            "--watch" => options.watch = true,
+            "--fix" => options.fix = true,
+            "--source-map" => options.source_map = true,
+            "--source-comments" => options.source_comments = true,
+            "--reference" => options.reference = true,
+            "-O0" => options.opt_level = 0,
+            "-O1" => options.opt_level = 1,
+            "--intern-strings" => options.intern_strings = true,
+            "-" => options.paths.push(PathBuf::from(arg)),
+            _ if arg.starts_with('-') => return Err(format!("Unknown option '{}'", arg)),
+            _ => options.paths.push(PathBuf::from(arg)),
+        }
+    }
+    let build_only = options.jobs.is_some()
+        || options.verbose
+        || options.watch
+        || options.source_map
+        || options.source_comments
+        || options.reference
+        || options.opt_level > 0
+        || options.intern_strings;
+    if build_only && options.command != "build" {
+        return Err(format!(
+            "{} doesn't support the options of build: --jobs, --verbose, --watch, --source-map, \
+             --source-comments, --reference, -O1 and --intern-strings",
+            options.command
+        ));
+    }
+    if options.source_map && options.paths.iter().any(|x| x == Path::new("-")) {
+        return Err("--source-map needs files to write the source map next to".to_string());
+    }
+    if options.watch && options.fix {
+        return Err("--fix cannot be combined with --watch".to_string());
+    }
+    if options.watch && options.paths.iter().any(|x| x == Path::new("-")) {
+        return Err("--watch can only watch files, not stdin".to_string());
+    }
+    if options.fix && !["check", "build"].contains(&options.command.as_str()) {
+        return Err(format!("--fix is not supported by {}", options.command));
+    }
+    if options.out_dir.is_some() && ["check", "run"].contains(&options.command.as_str()) {
+        return Err(format!("{} doesn't write any files", options.command));
+    }
+    Ok(options)
+}
+
+// Represents what a command works on
+enum Input {
+    // A single class read from stdin
+    Stdin(String),
+    Files {
+        files: Vec<PathBuf>,
+        build_options: BuildOptions,
+        // The precompiled .vm files of the libraries of a project
+        library_vm_files: Vec<PathBuf>,
+    },
+}
+
+// Finds the files given on the command line, or the files of the project of the current
+// directory without paths
+fn input(options: &Options) -> Result<Input, String> {
+    if options.paths.iter().any(|x| x == Path::new("-")) {
+        if options.paths.len() > 1 {
+            return Err("- cannot be combined with other paths".to_string());
+        }
+        let mut contents = String::new();
+        io::stdin()
+            .read_to_string(&mut contents)
+            .map_err(|e| format!("stdin: {}", e))?;
+        return Ok(Input::Stdin(contents));
+    }
+    let (files, mut build_options, library_vm_files) = if options.paths.is_empty() {
+        let current_dir = env::current_dir().map_err(|e| e.to_string())?;
+        let path = Manifest::discover(&current_dir)
+            .ok_or("No paths given, and no jack.toml found in the current directory")?;
+        let manifest = Manifest::load(&path)?;
+        (
+            manifest.jack_files()?,
+            manifest.build_options(),
+            manifest.library_vm_files(),
+        )
+    } else {
+        let first = &options.paths[0];
+        let cache_dir = match first.is_dir() {
+            true => first.join(CACHE_DIR),
+            false => first.with_file_name(CACHE_DIR),
+        };
+        let mut files = vec![];
+        for path in &options.paths {
+            if !path.exists() {
+                return Err(format!("{}: No such file or directory", path.display()));
+            }
+            files.extend(jack_files(path).map_err(|e| format!("{}: {}", path.display(), e))?);
+        }
+        (files, BuildOptions::new(cache_dir), vec![])
+    };
+    build_options.out_dir = options.out_dir.clone().or(build_options.out_dir);
+    build_options.jobs = options.jobs.unwrap_or(build_options.jobs);
+    build_options.codegen = codegen_options(options);
+    Ok(Input::Files {
+        files,
+        build_options,
+        library_vm_files,
+    })
+}
+
+// Returns the code generation options of the command line
+fn codegen_options(options: &Options) -> CodegenOptions {
+    CodegenOptions {
+        source_comments: options.source_comments,
+        source_map: options.source_map,
+        reference: options.reference,
+        opt_level: options.opt_level,
+        intern_strings: options.intern_strings,
+    }
+}
+
+// Reads the .jack files of a command, as (file name, contents) pairs
+fn read_files(files: &[PathBuf]) -> Result<Vec<(String, String)>, String> {
+    files
+        .iter()
+        .map(|x| {
+            fs::read_to_string(x)
+                .map(|contents| (x.display().to_string(), contents))
+                .map_err(|e| format!("{}: {}", x.display(), e))
+        })
+        .collect()
+}
+
+// Writes the output of a command to stdout
+fn print(output: &str) -> Result<(), String> {
+    io::stdout()
+        .write_all(output.as_bytes())
+        .map_err(|e| format!("stdout: {}", e))
+}
+
+// Applies the suggested fixes of the errors of each file, rewriting the files which changed
+fn fix_files(input: &Input) -> Result<(), String> {
+    let files = match input {
+        Input::Stdin(_) => return Err("--fix can only fix files, not stdin".to_string()),
+        Input::Files { files, .. } => files,
+    };
+    for (file, (name, contents)) in files.iter().zip(read_files(files)?) {
+        let (fixed, count) = fix_source(&contents);
+        if count > 0 {
+            fs::write(file, fixed).map_err(|e| format!("{}: {}", name, e))?;
+            let plural = if count == 1 { "" } else { "es" };
+            eprintln!("Fixed {}: {} fix{} applied", name, count, plural);
+        }
+    }
+    Ok(())
+}
+
+// Prints the diagnostics of a file in the chosen format, returning whether any is an error
+fn report(
+    out: &mut dyn Write,
+    format: MessageFormat,
+    file: &str,
+    contents: &str,
+    diagnostics: &[Diagnostic],
+) -> bool {
+    for diagnostic in diagnostics {
+        let _ = match format {
+            MessageFormat::Human => writeln!(out, "{}", diagnostic.render(file, contents)),
+            MessageFormat::Json => writeln!(out, "{}", diagnostic.to_json(file, contents)),
+        };
+    }
+    diagnostics.iter().any(|x| x.severity == Severity::Error)
+}
+
+// Returns a build error of the form "path: message" as a JSON diagnostic. It has the members of
+// Diagnostic::to_json, without a position since the error isn't located in the file.
+fn build_error_json(error: &str) -> Json {
+    let (file, message) = error.split_once(": ").unwrap_or(("", error));
+    Json::object(vec![
+        ("file", file.into()),
+        ("severity", "error".into()),
+        ("code", "build-error".into()),
+        ("message", message.into()),
+        ("line", Json::Null),
+        ("column", Json::Null),
+        ("endLine", Json::Null),
+        ("endColumn", Json::Null),
+        ("notes", Json::Array(vec![])),
+        ("fixes", Json::Array(vec![])),
+    ])
+}
+
+// Returns the diagnostic of an error a command failed with, located by the parser when it's a
+// syntax error
+fn error_diagnostic(contents: &str, code: &'static str, message: String) -> Diagnostic {
+    parse_class(contents)
+        .err()
+        .unwrap_or(Diagnostic::error(code, message, Span::default()))
+}
+
+// Compiles the sources as one program with a Session
+fn compile(sources: &[(String, String)], generate_vm: bool) -> Compilation {
+    let options = SessionOptions {
+        generate_vm,
+        ..Default::default()
+    };
+    sources
+        .iter()
+        .fold(
+            Session::new().with_options(options),
+            |session, (file, contents)| session.with_source(file, contents),
+        )
+        .compile()
+}
+
+// Checks every file against the others as one program, returning the syntax errors, semantic
+// errors and warnings of each file
+fn diagnostics(sources: &[(String, String)]) -> Vec<Vec<Diagnostic>> {
+    compile(sources, false)
+        .files
+        .into_iter()
+        .map(|x| x.diagnostics)
+        .collect()
+}
+
+// Reports the errors of a program, which stop it from being compiled, returning whether there
+// were any
+fn report_errors(sources: &[(String, String)], format: MessageFormat) -> bool {
+    let mut errors = false;
+    for ((file, contents), diagnostics) in sources.iter().zip(diagnostics(sources)) {
+        let diagnostics = diagnostics
+            .into_iter()
+            .filter(|x| x.severity == Severity::Error)
+            .collect::<Vec<_>>();
+        errors |= report(&mut io::stderr(), format, file, contents, &diagnostics);
+    }
+    errors
+}
+
+// Runs tokenize or parse, writing the XML of each file next to it or to the output directory
+// under the name of the file with the given suffix
+fn write_xml(
+    input: Input,
+    format: MessageFormat,
+    convert: fn(&str) -> Result<String, String>,
+    suffix: &str,
+) -> Result<ExitCode, String> {
+    let (files, out_dir) = match input {
+        Input::Stdin(contents) => {
+            return match convert(&contents) {
+                Ok(xml) => print(&xml).map(|_| ExitCode::SUCCESS),
+                Err(e) => {
+                    let diagnostic = error_diagnostic(&contents, "syntax-error", e);
+                    report(&mut io::stderr(), format, "stdin", &contents, &[diagnostic]);
+                    Ok(ExitCode::FAILURE)
+                }
+            };
+        }
+        Input::Files {
+            files,
+            build_options,
+            ..
+        } => (files, build_options.out_dir),
+    };
+    if let Some(dir) = &out_dir {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let mut code = ExitCode::SUCCESS;
+    for (file, (name, contents)) in files.iter().zip(read_files(&files)?) {
+        let xml = match convert(&contents) {
+            Ok(xml) => xml,
+            Err(e) => {
+                let diagnostic = error_diagnostic(&contents, "syntax-error", e);
+                report(&mut io::stderr(), format, &name, &contents, &[diagnostic]);
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+        let name = format!(
+            "{}{}.xml",
+            file.file_stem().unwrap_or_default().to_string_lossy(),
+            suffix
+        );
+        let output = match &out_dir {
+            Some(dir) => dir.join(name),
+            None => file.with_file_name(name),
+        };
+        fs::write(&output, xml).map_err(|e| format!("{}: {}", output.display(), e))?;
+    }
+    Ok(code)
+}
+
+// Prints the syntax errors, semantic errors and warnings of every file
+fn check(input: Input, format: MessageFormat) -> Result<ExitCode, String> {
+    let sources = match input {
+        Input::Stdin(contents) => vec![("stdin".to_string(), contents)],
+        Input::Files { files, .. } => read_files(&files)?,
+    };
+    let mut errors = false;
+    for ((file, contents), diagnostics) in sources.iter().zip(diagnostics(&sources)) {
+        errors |= report(&mut io::stdout(), format, file, contents, &diagnostics);
+    }
+    Ok(if errors {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+// Prints how many VM commands the optimizations removed
+fn print_savings(commands: usize, unoptimized_commands: usize) {
+    let saved = unoptimized_commands - commands;
+    eprintln!(
+        "Optimized {} VM commands to {}, {} fewer ({:.1}%)",
+        unoptimized_commands,
+        commands,
+        saved,
+        100.0 * saved as f64 / unoptimized_commands.max(1) as f64
+    );
+}
+
+// Compiles the files to .vm files through the build cache, copying the precompiled library files
+// of a project to its output directory
+fn build_files(input: Input, options: &Options) -> Result<ExitCode, String> {
+    let (format, verbose) = (options.message_format, options.verbose);
+    let (files, build_options, library_vm_files) = match input {
+        Input::Stdin(contents) => {
+            let session_options = SessionOptions {
+                codegen: codegen_options(options),
+                ..Default::default()
+            };
+            let compilation = Session::new()
+                .with_source("stdin", contents)
+                .with_options(session_options)
+                .compile();
+            let file = &compilation.files[0];
+            if let Some(vm) = &file.vm {
+                if options.opt_level > 0 {
+                    print_savings(file.commands, file.unoptimized_commands);
+                }
+                return print(vm).map(|_| ExitCode::SUCCESS);
+            }
+            let errors = file
+                .diagnostics
+                .iter()
+                .filter(|x| x.severity == Severity::Error)
+                .cloned()
+                .collect::<Vec<_>>();
+            report(&mut io::stderr(), format, "stdin", &file.source, &errors);
+            return Ok(ExitCode::FAILURE);
+        }
+        Input::Files {
+            files,
+            build_options,
+            library_vm_files,
+            ..
+        } => (files, build_options, library_vm_files),
+    };
+    let steps = match build(&files, &build_options) {
+        Ok(steps) => steps,
+        Err(e) => {
+            // the build stops at the first error of each file, so the errors are reported by
+            // checking the program
+            if !report_errors(&read_files(&files)?, format) {
+                match format {
+                    MessageFormat::Human => eprintln!("error: {}", e),
+                    MessageFormat::Json => {
+                        for error in e.lines() {
+                            eprintln!("{}", build_error_json(error));
+                        }
+                    }
+                }
+            }
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+    if verbose {
+        for step in &steps {
+            println!("{}", step);
+        }
+    }
+    if options.opt_level > 0 {
+        print_savings(
+            steps.iter().map(|x| x.commands).sum(),
+            steps.iter().map(|x| x.unoptimized_commands).sum(),
+        );
+    }
+    if let Some(out_dir) = &build_options.out_dir {
+        for file in library_vm_files {
+            let output = out_dir.join(file.file_name().unwrap_or_default());
+            fs::copy(&file, &output).map_err(|e| format!("{}: {}", output.display(), e))?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+// Compiles the program in memory and runs it, with the keyboard reading from stdin and the
+// output written to stdout
+fn run(input: Input, format: MessageFormat) -> Result<ExitCode, String> {
+    let (sources, library_vm_files) = match input {
+        Input::Stdin(contents) => (vec![("stdin".to_string(), contents)], vec![]),
+        Input::Files {
+            files,
+            library_vm_files,
+            ..
+        } => (read_files(&files)?, read_files(&library_vm_files)?),
+    };
+    let compilation = compile(&sources, true);
+    let mut errors = false;
+    for file in &compilation.files {
+        let diagnostics = file
+            .diagnostics
+            .iter()
+            .filter(|x| x.severity == Severity::Error)
+            .cloned()
+            .collect::<Vec<_>>();
+        errors |= report(
+            &mut io::stderr(),
+            format,
+            &file.name,
+            &file.source,
+            &diagnostics,
+        );
+    }
+    if errors {
+        return Ok(ExitCode::FAILURE);
+    }
+    let mut vm_files = compilation
+        .files
+        .into_iter()
+        .filter_map(|x| Some((x.name, x.vm?)))
+        .collect::<Vec<_>>();
+    vm_files.extend(library_vm_files);
+    let files = vm_files
+        .iter()
+        .map(|(file, vm)| (file.as_str(), vm.as_str()))
+        .collect::<Vec<_>>();
+    match run_vm(&files, &mut io::stdin().lock(), &mut io::stdout().lock()) {
+        Ok(()) => Ok(ExitCode::SUCCESS),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
+
+// Returns the modification time and size of each file of a command, which change when a file is
+// edited, added or removed
+fn snapshot(input: &Input) -> Vec<(PathBuf, Option<(SystemTime, u64)>)> {
+    let files = match input {
+        Input::Stdin(_) => return vec![],
+        Input::Files { files, .. } => files,
+    };
+    files
+        .iter()
+        .map(|x| {
+            let metadata = fs::metadata(x).and_then(|m| Ok((m.modified()?, m.len())));
+            (x.clone(), metadata.ok())
+        })
+        .collect()
+}
+
+// Polls the files of a build, building them again whenever they change. Only the classes which
+// changed, or which use a class whose interface changed, are compiled again thanks to the build
+// cache. The diagnostics of the previous build are cleared from the terminal.
+fn watch(options: &Options) -> Result<ExitCode, String> {
+    let mut previous = None;
+    loop {
+        // the files are found again each time, so that added and removed files are noticed
+        let (input, state) = match input(options) {
+            Ok(input) => {
+                let state = Ok(snapshot(&input));
+                (Some(input), state)
+            }
+            Err(e) => (None, Err(e)),
+        };
+        if previous.as_ref() != Some(&state) {
+            if io::stderr().is_terminal() {
+                eprint!("\x1b[2J\x1b[H");
+            }
+            match input {
+                Some(input) => {
+                    let res = build_files(input, options);
+                    match res {
+                        Ok(code) if code == ExitCode::SUCCESS => eprintln!("Build succeeded"),
+                        Ok(_) => eprintln!("Build failed"),
+                        Err(e) => eprintln!("error: {}", e),
+                    }
+                }
+                None => eprintln!("error: {}", state.as_ref().unwrap_err()),
+            }
+            eprintln!("Watching for changes, press Ctrl-C to stop");
+            previous = Some(state);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.iter().any(|x| x == "-h" || x == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let res = parse_args(&args).and_then(|options| {
+        if options.watch {
+            return watch(&options);
+        }
+        let input = input(&options)?;
+        if options.fix {
+            fix_files(&input)?;
+        }
+        let format = options.message_format;
+        match options.command.as_str() {
+            "tokenize" => write_xml(input, format, tokenize_into_xml, "T"),
+            "parse" => write_xml(input, format, parse_into_xml, ""),
+            "check" => check(input, format),
+            "run" => run(input, format),
+            _ => build_files(input, &options),
+        }
+    });
+    match res {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
 }
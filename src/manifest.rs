@@ -0,0 +1,191 @@
+use crate::build::BuildOptions;
+use crate::utils::{parse_toml, Json};
+use crate::vfs::{DiskFiles, FileSystem};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// The file name of project manifests
+pub static MANIFEST_NAME: &str = "jack.toml";
+
+// The directory holding the build cache, relative to the project or source directory
+pub static CACHE_DIR: &str = ".jack-cache";
+
+static KEYS: &[&str] = &["name", "sources", "libs", "out-dir", "target", "extensions"];
+
+#[derive(Debug, Clone)]
+// Represents a project described by a jack.toml file, e.g.
+//
+// name = "tetris"
+// sources = ["src"]
+// libs = ["../shared", "os"]
+// out-dir = "build"
+// target = "vm"
+// extensions = []
+//
+// Paths are relative to the directory of the manifest. Projects can only be compiled to VM code
+// and no language extensions are supported yet, so the target must be vm and the extensions
+// empty.
+pub struct Manifest {
+    pub root: PathBuf,
+    pub name: Option<String>,
+    // Directories or files with the classes of the project, the root directory by default
+    pub sources: Vec<PathBuf>,
+    // Directories or files with classes used by the project, e.g. shared classes or an OS
+    pub libs: Vec<PathBuf>,
+    // Where the output files are written, next to their sources if not set
+    pub out_dir: Option<PathBuf>,
+}
+
+// Returns the strings of an array member of a manifest
+fn strings(toml: &Json, key: &str) -> Result<Vec<String>, String> {
+    match toml.get(key) {
+        None => Ok(vec![]),
+        Some(value) => value
+            .as_array()
+            .and_then(|x| {
+                x.iter()
+                    .map(|x| x.as_str().map(|x| x.to_string()))
+                    .collect()
+            })
+            .ok_or(format!("'{}' must be an array of strings", key)),
+    }
+}
+
+impl Manifest {
+    // Reads the contents of a manifest in the given root directory
+    pub fn parse(contents: &str, root: &Path) -> Result<Manifest, String> {
+        let toml = parse_toml(contents)?;
+        if let Json::Object(members) = &toml {
+            if let Some((key, _)) = members.iter().find(|(k, _)| !KEYS.contains(&k.as_str())) {
+                return Err(format!("Unknown key '{}'", key));
+            }
+        }
+        let string = |key: &str| match toml.get(key) {
+            None => Ok(None),
+            Some(value) => value
+                .as_str()
+                .map(|x| Some(x.to_string()))
+                .ok_or(format!("'{}' must be a string", key)),
+        };
+        match string("target")?.as_deref() {
+            None | Some("vm") => (),
+            Some(target @ ("asm" | "hack")) => {
+                return Err(format!("Target '{}' is not supported, only vm", target))
+            }
+            Some(target) => return Err(format!("Unknown target '{}', expected vm", target)),
+        }
+        if let Some(extension) = strings(&toml, "extensions")?.first() {
+            return Err(format!(
+                "Language extension '{}' is not supported",
+                extension
+            ));
+        }
+        let mut sources = strings(&toml, "sources")?
+            .iter()
+            .map(|x| root.join(x))
+            .collect::<Vec<_>>();
+        if sources.is_empty() {
+            sources.push(root.to_path_buf());
+        }
+        Ok(Manifest {
+            root: root.to_path_buf(),
+            name: string("name")?,
+            sources,
+            libs: strings(&toml, "libs")?
+                .iter()
+                .map(|x| root.join(x))
+                .collect(),
+            out_dir: string("out-dir")?.map(|x| root.join(x)),
+        })
+    }
+
+    // Reads a manifest file
+    pub fn load(path: &Path) -> Result<Manifest, String> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let root = path.parent().unwrap_or(Path::new("."));
+        Manifest::parse(&contents, root).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // Finds the manifest of the project a directory belongs to, by looking in the directory and
+    // then in each of its parents
+    pub fn discover(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|x| x.join(MANIFEST_NAME))
+            .find(|x| x.is_file())
+    }
+
+    // Returns the .jack files of the project, the sources followed by the libraries. Library
+    // classes with the name of a project class are left out, since the project class replaces
+    // them.
+    pub fn jack_files(&self) -> Result<Vec<PathBuf>, String> {
+        let mut res: Vec<PathBuf> = vec![];
+        for (i, path) in self.sources.iter().chain(&self.libs).enumerate() {
+            let files = jack_files(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            for file in files {
+                let replaced = i >= self.sources.len()
+                    && res.iter().any(|x| x.file_stem() == file.file_stem());
+                if !replaced {
+                    res.push(file);
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    // Returns the precompiled .vm files of the libraries without a .jack source, which are copied
+    // to the output directory
+    pub fn library_vm_files(&self) -> Vec<PathBuf> {
+        self.libs
+            .iter()
+            .filter(|x| x.is_dir())
+            .flat_map(|x| fs::read_dir(x).into_iter().flatten().flatten())
+            .map(|x| x.path())
+            .filter(|x| x.extension().is_some_and(|x| x == "vm"))
+            .filter(|x| !x.with_extension("jack").exists())
+            .collect()
+    }
+
+    // Returns the options of a build of the project
+    pub fn build_options(&self) -> BuildOptions {
+        let mut options = BuildOptions::new(self.root.join(CACHE_DIR));
+        options.out_dir = self.out_dir.clone();
+        options
+    }
+}
+
+// Returns the .jack files at a path, which is either a file or a directory of files
+pub fn jack_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    jack_files_in(&DiskFiles, path)
+}
+
+// Returns the .jack files in a directory and its subdirectories, each directory's own files first
+// and in the order of their paths. Symbolic links to directories are not followed, so a link to
+// a parent directory can't make it loop.
+pub fn jack_files_recursive(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut res = jack_files(dir)?;
+    let mut subdirs = fs::read_dir(dir)?
+        .filter_map(|x| x.ok())
+        .filter(|x| x.file_type().is_ok_and(|x| x.is_dir()))
+        .map(|x| x.path())
+        .collect::<Vec<_>>();
+    subdirs.sort();
+    for subdir in subdirs {
+        res.extend(jack_files_recursive(&subdir)?);
+    }
+    Ok(res)
+}
+
+// Returns the .jack files at a path of a file system
+pub fn jack_files_in(files: &dyn FileSystem, path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !files.is_dir(path) {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    Ok(files
+        .read_dir(path)?
+        .into_iter()
+        .filter(|x| x.extension().is_some_and(|x| x == "jack"))
+        .collect())
+}
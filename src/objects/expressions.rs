@@ -5,17 +5,19 @@ pub fn expression(s: &mut TokenStream) -> ParseResult {
     let mut res = Grouping::new("expression");
     res.add_grouping(term(s)?);
     loop {
-        let op = s.next(|op| match op {
-            Token::Symbol('+')
-            | Token::Symbol('-')
-            | Token::Symbol('*')
-            | Token::Symbol('/')
-            | Token::Symbol('&')
-            | Token::Symbol('|')
-            | Token::Symbol('<')
-            | Token::Symbol('>')
-            | Token::Symbol('=') => true,
-            _ => false,
+        let op = s.next(|op| {
+            matches!(
+                op,
+                Token::Symbol('+')
+                    | Token::Symbol('-')
+                    | Token::Symbol('*')
+                    | Token::Symbol('/')
+                    | Token::Symbol('&')
+                    | Token::Symbol('|')
+                    | Token::Symbol('<')
+                    | Token::Symbol('>')
+                    | Token::Symbol('=')
+            )
         });
         match op {
             Ok(t) => {
@@ -74,7 +76,10 @@ pub fn term(s: &mut TokenStream) -> ParseResult {
             res.add_token(s.next(|_| true)?);
             res.add_grouping(term(s)?);
         }
-        _ => Err("Invalid term token")?,
+        _ => {
+            s.expect("an expression");
+            Err("Invalid term token")?
+        }
     }
     Ok(res)
 }
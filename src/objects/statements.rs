@@ -17,7 +17,10 @@ pub fn statement(s: &mut TokenStream) -> ParseResult {
         Token::Keyword("while") => while_statement(s),
         Token::Keyword("do") => do_statement(s),
         Token::Keyword("return") => return_statement(s),
-        _ => Err("Invalid statement identifier"),
+        _ => {
+            s.expect("a statement");
+            Err("Invalid statement identifier")
+        }
     }
 }
 
@@ -91,9 +94,10 @@ fn do_statement(s: &mut TokenStream) -> ParseResult {
 fn return_statement(s: &mut TokenStream) -> ParseResult {
     let mut res = Grouping::new("returnStatement");
     res.add_token(s.keyword("return")?);
-    let exp = expression(s);
-    if let Ok(e) = exp {
-        res.add_grouping(e);
+    let start = s.pos();
+    match expression(s) {
+        Ok(e) => res.add_grouping(e),
+        Err(_) => s.rewind(start),
     }
     res.add_token(s.symbol(';')?);
     Ok(res)
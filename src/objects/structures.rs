@@ -15,7 +15,7 @@ pub fn class(s: &mut TokenStream) -> ParseResult {
 
 // Returns the type token
 // Keyword: int/char/bool OR Identifier
-fn type_dec(s: &mut TokenStream) -> TokenResult {
+fn type_dec(s: &mut TokenStream) -> TokenResult<'_> {
     let dtype = s.peek()?;
     match dtype {
         Token::Keyword("int") | Token::Keyword("char") | Token::Keyword("boolean") => {
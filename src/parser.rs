@@ -1,15 +1,190 @@
-use crate::objects::structures;
-use crate::tokenizer::tokenize;
-use crate::utils::{Grouping, TokenStream};
+use crate::analysis::{identifiers, Category};
+use crate::cst::{self, SyntaxNode};
+use crate::objects;
+use crate::tokenizer::{is_valid_identifier, lex, lexeme_token, tokenize_with_spans};
+use crate::utils::{
+    line_col, Diagnostic, Grouping, LexemeKind, Span, SymbolKind, Token, TokenStream,
+};
+use std::collections::HashMap;
+
+// Parses the contents of a .jack file into an abstract syntax tree Grouping, locating the
+// syntax error if there is one. Common mistakes come with a suggested fix.
+pub fn parse_class(contents: &str) -> Result<Grouping, Diagnostic> {
+    parse_tokens(contents).map_err(|e| {
+        suggestions(contents, &e)
+            .into_iter()
+            .filter(|(_, span, replacement)| fixes_error(contents, *span, replacement))
+            .fold(e.diagnostic.clone(), |d, (message, span, replacement)| {
+                d.with_suggestion(message.to_string(), span, replacement.to_string())
+            })
+    })
+}
+
+// Represents where and why parsing failed
+struct SyntaxError {
+    diagnostic: Diagnostic,
+    // The spans of the tokens, and the position of the token where parsing failed
+    spans: Vec<Span>,
+    pos: usize,
+    expected: Vec<String>,
+}
+
+impl SyntaxError {
+    // Create a SyntaxError which was found before the contents were split into tokens
+    fn lexical(message: String, span: Span) -> Self {
+        SyntaxError {
+            diagnostic: Diagnostic::error("syntax-error", message, span),
+            spans: vec![],
+            pos: 0,
+            expected: vec![],
+        }
+    }
+}
 
 // Parses the contents of a .jack file into an abstract syntax tree Grouping
-pub fn parse(contents: &str) -> Result<Grouping, String> {
-    let tokens = tokenize(contents)?;
+fn parse_tokens(contents: &str) -> Result<Grouping, Box<SyntaxError>> {
+    if let Some(l) = lex(contents).iter().find(|x| x.kind == LexemeKind::Error) {
+        let message = lexeme_token(l, contents).err().unwrap_or_default();
+        return Err(Box::new(SyntaxError::lexical(message, l.span)));
+    }
+    let (tokens, spans) =
+        tokenize_with_spans(contents).map_err(|e| SyntaxError::lexical(e, Span::default()))?;
     let mut stream = TokenStream::new(tokens);
-    Ok(structures::class(&mut stream)?)
+    let class_grouping = objects::class(&mut stream);
+    let (pos, expected) = match class_grouping {
+        Ok(mut g) if stream.is_finished() => {
+            g.assign_spans(&mut spans.into_iter());
+            return Ok(g);
+        }
+        Ok(_) => (stream.pos(), vec!["end of file".to_string()]),
+        Err(_) => {
+            let (pos, expected) = stream.furthest_failure();
+            (pos, expected.to_vec())
+        }
+    };
+    let span = spans
+        .get(pos)
+        .copied()
+        .unwrap_or(Span::new(contents.len(), contents.len()));
+    let found = match spans.get(pos) {
+        Some(span) => format!("'{}'", span.text(contents)),
+        None => "end of file".to_string(),
+    };
+    let diagnostic = Diagnostic::error(
+        "syntax-error",
+        format!("Expected {}, found {}", expected.join(" or "), found),
+        span,
+    );
+    Err(Box::new(SyntaxError {
+        diagnostic,
+        spans,
+        pos,
+        expected,
+    }))
+}
+
+// Returns fixes for common mistakes which may have caused a syntax error: a missing ';' or ')',
+// '==' written for '=' and a subroutine call statement without 'do'
+fn suggestions(contents: &str, error: &SyntaxError) -> Vec<(&'static str, Span, &'static str)> {
+    let (spans, pos) = (&error.spans, error.pos);
+    let text = |i: usize| spans.get(i).map(|x| x.text(contents)).unwrap_or_default();
+    let expected = |x: &str| error.expected.iter().any(|e| e == x);
+    let mut res = vec![];
+    if let Some(previous) = pos.checked_sub(1).and_then(|i| spans.get(i)) {
+        let after_previous = Span::new(previous.end, previous.end);
+        if expected("';'") {
+            res.push(("Insert the missing ';'", after_previous, ";"));
+        }
+        if expected("')'") {
+            res.push(("Insert the missing ')'", after_previous, ")"));
+        }
+        if text(pos - 1) == "=" && text(pos) == "=" {
+            res.push((
+                "Jack compares values with '=' instead of '=='",
+                Span::new(previous.start, spans[pos].end),
+                "=",
+            ));
+        }
+    }
+    let is_call = matches!(text(pos + 1), "(" | ".");
+    if expected("a statement") && is_valid_identifier(text(pos)) && is_call {
+        let before = Span::new(spans[pos].start, spans[pos].start);
+        res.push(("Add 'do' to call the subroutine", before, "do "));
+    }
+    res
+}
+
+// Checks if a fix gets the parser past a syntax error, because the fixed contents parse or the
+// parser fails after the replacement
+fn fixes_error(contents: &str, span: Span, replacement: &str) -> bool {
+    let fixed = format!(
+        "{}{}{}",
+        &contents[..span.start],
+        replacement,
+        &contents[span.end..]
+    );
+    match parse_tokens(&fixed) {
+        Ok(_) => true,
+        Err(e) => e.diagnostic.span.start >= span.start + replacement.len(),
+    }
+}
+
+// Parses the contents of a .jack file into an abstract syntax tree Grouping
+pub fn parse(contents: &str) -> Result<Grouping, String> {
+    parse_class(contents).map_err(|d| {
+        let (line, col) = line_col(contents, d.span.start);
+        format!("{}:{}: {}", line, col, d.message)
+    })
 }
 
 // Parses the conents of a .jack file into an XML abstract syntax tree
 pub fn parse_into_xml(contents: &str) -> Result<String, String> {
     Ok(parse(contents)?.as_xml())
 }
+
+// Parses the contents of a .jack file into an XML abstract syntax tree whose identifiers record
+// their category, their symbol table index (for variables) and whether they are defined or used
+pub fn parse_into_identifier_xml(contents: &str) -> Result<String, String> {
+    let class_grouping = parse(contents)?;
+    let mut attributes = HashMap::new();
+    for id in identifiers(&class_grouping)? {
+        let (category, index) = match &id.category {
+            Category::Variable(Some(symbol)) => (
+                match symbol.kind() {
+                    SymbolKind::Static => "static",
+                    SymbolKind::Field => "field",
+                    SymbolKind::Argument => "argument",
+                    SymbolKind::Local => "var",
+                },
+                format!(" index=\"{}\"", symbol.index()),
+            ),
+            Category::Variable(None) => {
+                let (line, col) = line_col(contents, id.span.start);
+                return Err(format!(
+                    "{}:{}: Undefined variable '{}'",
+                    line, col, id.name
+                ));
+            }
+            Category::Class => ("class", String::new()),
+            Category::Subroutine => ("subroutine", String::new()),
+        };
+        let usage = if id.defined { "defined" } else { "used" };
+        attributes.insert(
+            id.span.start,
+            format!("category=\"{}\"{} usage=\"{}\"", category, index, usage),
+        );
+    }
+    Ok(
+        class_grouping.as_xml_with(&|token, span| match (token, attributes.get(&span.start)) {
+            (Token::Identifier(name), Some(attributes)) => {
+                format!("<identifier {}> {} </identifier>", attributes, name)
+            }
+            _ => token.as_xml(),
+        }),
+    )
+}
+
+// Parses the contents of a .jack file into a lossless concrete syntax tree
+pub fn parse_cst(contents: &str) -> Result<SyntaxNode, String> {
+    Ok(cst::build(&parse(contents)?, &lex(contents), contents))
+}
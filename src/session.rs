@@ -0,0 +1,444 @@
+use crate::analysis::{check_class, check_types, subroutine_name, subroutines};
+use crate::analysis::{ClassInterface, Program, Type, TypeMode};
+use crate::codewriter::{compile_class_with, CodegenOptions};
+use crate::cst::{self, SyntaxElement, SyntaxNode};
+use crate::lint::{lint, LintConfig};
+use crate::manifest::jack_files_in;
+use crate::parser::parse_class;
+use crate::tokenizer::lex;
+use crate::utils::{
+    create_subroutine_symbol_table, create_symbol_table, Diagnostic, Grouping, LexemeKind,
+    Severity, Span, SymbolKind, SymbolTable,
+};
+use crate::vfs::{DiskFiles, FileSystem};
+use std::path::Path;
+
+// The library interface for tools which compile Jack programs. A Session collects the classes
+// of a program, read from files or given as strings, and compiles them together:
+//
+// let compilation = Session::new()
+//     .with_path("src/Main.jack")?
+//     .with_source("Util.jack", "class Util { ... }")
+//     .with_options(SessionOptions { generate_vm: false, ..Default::default() })
+//     .compile();
+// for file in &compilation.files {
+//     for diagnostic in &file.diagnostics {
+//         println!("{}", diagnostic.render(&file.name, &file.source));
+//     }
+// }
+//
+// The results are plain data owned by the caller, so that the internal representations of the
+// compiler remain free to change.
+
+#[derive(Debug, Clone)]
+// Represents the settings of a Session
+pub struct SessionOptions {
+    // How strictly the types of expressions are checked
+    pub type_mode: TypeMode,
+    // Whether the warnings of the lint rules are added to the diagnostics
+    pub lint: bool,
+    // The names of the lint rules left out, e.g. unused-variable. Names of rules which don't
+    // exist are ignored.
+    pub disabled_lints: Vec<String>,
+    // Whether the VM code of files without errors is generated
+    pub generate_vm: bool,
+    pub codegen: CodegenOptions,
+}
+
+impl Default for SessionOptions {
+    fn default() -> Self {
+        SessionOptions {
+            type_mode: TypeMode::Lenient,
+            lint: false,
+            disabled_lints: vec![],
+            generate_vm: true,
+            codegen: CodegenOptions::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Represents the lexical class of a token
+pub enum TokenKind {
+    Keyword,
+    Symbol,
+    IntConst,
+    StringConst,
+    Identifier,
+}
+
+impl TokenKind {
+    // Returns the kind of a lexeme, None for whitespace, comments and invalid characters
+    fn from_lexeme(kind: LexemeKind) -> Option<TokenKind> {
+        match kind {
+            LexemeKind::Keyword => Some(TokenKind::Keyword),
+            LexemeKind::Symbol => Some(TokenKind::Symbol),
+            LexemeKind::IntConst => Some(TokenKind::IntConst),
+            LexemeKind::StringConst => Some(TokenKind::StringConst),
+            LexemeKind::Identifier => Some(TokenKind::Identifier),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents a token of a source file, leaving out whitespace and comments
+pub struct SourceToken {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents a node of the syntax tree of a file, named after its grammar rule, e.g. class or
+// whileStatement. The span leaves out the whitespace and comments around the node.
+pub struct TreeNode {
+    pub kind: String,
+    pub span: Span,
+    pub children: Vec<TreeElement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents a child of a syntax tree node, in source order
+pub enum TreeElement {
+    Node(TreeNode),
+    Token(SourceToken),
+}
+
+impl TreeNode {
+    // Copies a node of the lossless syntax tree, leaving out its trivia
+    fn from_syntax(node: &SyntaxNode) -> TreeNode {
+        let children = node
+            .children_with_tokens()
+            .iter()
+            .filter_map(|x| match x {
+                SyntaxElement::Node(node) => Some(TreeElement::Node(TreeNode::from_syntax(node))),
+                SyntaxElement::Token(token) => Some(TreeElement::Token(SourceToken {
+                    kind: TokenKind::from_lexeme(token.kind())?,
+                    text: token.text().to_string(),
+                    span: token.span(),
+                })),
+            })
+            .collect();
+        TreeNode {
+            kind: node.kind().to_string(),
+            span: node.trimmed_span(),
+            children,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents the signature of a subroutine. Types are named as in declarations, e.g. int or
+// Array.
+pub struct SubroutineInfo {
+    pub name: String,
+    // The keyword declaring the subroutine: constructor, function or method
+    pub kind: String,
+    pub return_type: String,
+    pub parameters: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents what other classes can see of a class: its variables as (name, type) pairs and its
+// subroutine signatures, in declaration order
+pub struct ClassInfo {
+    pub name: String,
+    pub fields: Vec<(String, String)>,
+    pub statics: Vec<(String, String)>,
+    pub subroutines: Vec<SubroutineInfo>,
+}
+
+impl ClassInfo {
+    // Copies the interface of a class
+    fn from_interface(interface: &ClassInterface) -> ClassInfo {
+        let variables = |x: &[(String, Type)]| {
+            x.iter()
+                .map(|(name, t)| (name.clone(), t.to_string()))
+                .collect()
+        };
+        ClassInfo {
+            name: interface.name.clone(),
+            fields: variables(&interface.fields),
+            statics: variables(&interface.statics),
+            subroutines: interface
+                .subroutines
+                .iter()
+                .map(|(name, signature)| SubroutineInfo {
+                    name: name.clone(),
+                    kind: signature.kind.keyword().to_string(),
+                    return_type: signature.return_type.to_string(),
+                    parameters: signature.parameters.iter().map(|x| x.to_string()).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Represents where a variable is stored
+pub enum VariableKind {
+    Static,
+    Field,
+    Argument,
+    Local,
+}
+
+impl VariableKind {
+    // Returns the VM memory segment holding variables of this kind
+    pub fn segment(&self) -> &'static str {
+        match self {
+            VariableKind::Static => "static",
+            VariableKind::Field => "this",
+            VariableKind::Argument => "argument",
+            VariableKind::Local => "local",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents a declared variable, at an index of its VM segment
+pub struct Variable {
+    pub name: String,
+    pub kind: VariableKind,
+    // The type as named in the declaration, e.g. int or Array
+    pub var_type: String,
+    pub index: usize,
+    // The span of the name in the declaration, empty for the implicit `this` argument
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents the symbol tables of a class: its static and field variables, and the arguments
+// and local variables of each subroutine in declaration order
+pub struct SymbolTables {
+    pub class: Vec<Variable>,
+    pub subroutines: Vec<(String, Vec<Variable>)>,
+}
+
+#[derive(Debug, Clone)]
+// Represents the results of compiling one source file
+pub struct CompiledFile {
+    pub name: String,
+    pub source: String,
+    pub tokens: Vec<SourceToken>,
+    // The syntax tree, unless the file has a syntax error
+    pub tree: Option<TreeNode>,
+    pub interface: Option<ClassInfo>,
+    pub symbols: Option<SymbolTables>,
+    // The VM code, when it was generated and the file has no errors
+    pub vm: Option<String>,
+    // The JSON source map of the VM code, when the code generation options ask for one
+    pub source_map: Option<String>,
+    // The number of VM commands, and the number before the peephole optimizations, or 0 without
+    // VM code
+    pub commands: usize,
+    pub unoptimized_commands: usize,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl CompiledFile {
+    // Checks if any diagnostic of the file is an error
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|x| x.severity == Severity::Error)
+    }
+}
+
+#[derive(Debug, Clone)]
+// Represents the results of compiling every source file of a Session, in the order they were
+// added
+pub struct Compilation {
+    pub files: Vec<CompiledFile>,
+}
+
+impl Compilation {
+    // Returns the results of a source file by name
+    pub fn file(&self, name: &str) -> Option<&CompiledFile> {
+        self.files.iter().find(|x| x.name == name)
+    }
+
+    // Checks if any file has an error
+    pub fn has_errors(&self) -> bool {
+        self.files.iter().any(|x| x.has_errors())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+// Represents the source files and settings of a compilation
+pub struct Session {
+    sources: Vec<(String, String)>,
+    options: SessionOptions,
+}
+
+// Returns the variables of a symbol table scope
+fn variables(st: &SymbolTable) -> Vec<Variable> {
+    st.symbols()
+        .iter()
+        .map(|x| Variable {
+            name: x.name().to_string(),
+            kind: match x.kind() {
+                SymbolKind::Static => VariableKind::Static,
+                SymbolKind::Field => VariableKind::Field,
+                SymbolKind::Argument => VariableKind::Argument,
+                SymbolKind::Local => VariableKind::Local,
+            },
+            var_type: Type::from_datatype(x.dtype()).to_string(),
+            index: x.index(),
+            span: x.span(),
+        })
+        .collect()
+}
+
+// Collects the symbol tables of a parsed class
+fn symbol_tables(class_grouping: &Grouping) -> Option<SymbolTables> {
+    let class_st = create_symbol_table(class_grouping).ok()?;
+    let subroutines = subroutines(class_grouping)
+        .into_iter()
+        .filter_map(|x| {
+            let (name, _) = subroutine_name(x)?;
+            let st = create_subroutine_symbol_table(x, &class_st).ok()?;
+            Some((name.to_string(), variables(&st)))
+        })
+        .collect();
+    Some(SymbolTables {
+        class: variables(&class_st),
+        subroutines,
+    })
+}
+
+impl Session {
+    // Create a Session without sources, with the default options
+    pub fn new() -> Self {
+        Session::default()
+    }
+
+    // Add a class given as a string, under a file name used in the results
+    pub fn with_source(mut self, name: impl Into<String>, contents: impl Into<String>) -> Self {
+        self.sources.push((name.into(), contents.into()));
+        self
+    }
+
+    // Add the .jack files at a path, which is either a file or a directory of files
+    pub fn with_path(self, path: impl AsRef<Path>) -> Result<Self, String> {
+        self.with_path_in(&DiskFiles, path)
+    }
+
+    // Add the .jack files at a path of a file system, e.g. of files kept in memory
+    pub fn with_path_in(
+        mut self,
+        files: &dyn FileSystem,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, String> {
+        let path = path.as_ref();
+        let error = |path: &Path, e: std::io::Error| format!("{}: {}", path.display(), e);
+        for file in jack_files_in(files, path).map_err(|e| error(path, e))? {
+            let contents = files.read(&file).map_err(|e| error(&file, e))?;
+            self.sources.push((file.display().to_string(), contents));
+        }
+        Ok(self)
+    }
+
+    // Set the options of the compilation
+    pub fn with_options(mut self, options: SessionOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    // Compiles the sources as one program, so that each class is checked and compiled against
+    // the others. Files with syntax errors only have tokens and diagnostics.
+    pub fn compile(&self) -> Compilation {
+        let classes = self
+            .sources
+            .iter()
+            .map(|(_, contents)| parse_class(contents))
+            .collect::<Vec<_>>();
+        let groupings = classes
+            .iter()
+            .filter_map(|x| x.as_ref().ok())
+            .collect::<Vec<_>>();
+        let program = Program::new(&groupings);
+        let mut lint_config = LintConfig::default();
+        for name in &self.options.disabled_lints {
+            let _ = lint_config.set_enabled(name, false);
+        }
+        let files = self
+            .sources
+            .iter()
+            .zip(&classes)
+            .map(|((name, contents), class)| {
+                let lexemes = lex(contents);
+                let tokens = lexemes
+                    .iter()
+                    .filter_map(|x| {
+                        Some(SourceToken {
+                            kind: TokenKind::from_lexeme(x.kind)?,
+                            text: x.span.text(contents).to_string(),
+                            span: x.span,
+                        })
+                    })
+                    .collect();
+                let mut file = CompiledFile {
+                    name: name.clone(),
+                    source: contents.clone(),
+                    tokens,
+                    tree: None,
+                    interface: None,
+                    symbols: None,
+                    vm: None,
+                    source_map: None,
+                    commands: 0,
+                    unoptimized_commands: 0,
+                    diagnostics: vec![],
+                };
+                let class_grouping = match class {
+                    Ok(g) => g,
+                    Err(diagnostic) => {
+                        file.diagnostics.push(diagnostic.clone());
+                        return file;
+                    }
+                };
+                let tree = cst::build(class_grouping, &lexemes, contents);
+                file.tree = Some(TreeNode::from_syntax(&tree));
+                let interface = ClassInterface::from_grouping(class_grouping);
+                file.interface = Some(ClassInfo::from_interface(&interface));
+                file.symbols = symbol_tables(class_grouping);
+                file.diagnostics = check_class(class_grouping);
+                file.diagnostics.extend(check_types(
+                    class_grouping,
+                    &groupings,
+                    self.options.type_mode,
+                ));
+                if self.options.lint {
+                    file.diagnostics
+                        .extend(lint(contents, &groupings, &lint_config).unwrap_or_default());
+                }
+                file.diagnostics.sort_by_key(|x| x.span.start);
+                if self.options.generate_vm && !file.has_errors() {
+                    let source = (name.as_str(), contents.as_str());
+                    match compile_class_with(
+                        class_grouping,
+                        &program,
+                        source,
+                        &self.options.codegen,
+                    ) {
+                        Ok(output) => {
+                            file.vm = Some(output.vm);
+                            file.source_map = output.source_map.map(|x| x.to_string());
+                            file.commands = output.commands;
+                            file.unoptimized_commands = output.unoptimized_commands;
+                        }
+                        Err(e) => file.diagnostics.push(Diagnostic::error(
+                            "compile-error",
+                            e,
+                            class_grouping.span(),
+                        )),
+                    }
+                }
+                file
+            })
+            .collect();
+        Compilation { files }
+    }
+}
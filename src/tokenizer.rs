@@ -1,4 +1,4 @@
-use crate::utils::{Token, KEYWORDS, SYMBOLS};
+use crate::utils::{Lexeme, LexemeKind, Span, Token, KEYWORDS, SYMBOLS};
 
 // Encode the tokens into an XML string
 pub fn into_xml(tokens: Vec<Token>) -> String {
@@ -10,43 +10,10 @@ pub fn into_xml(tokens: Vec<Token>) -> String {
     )
 }
 
-// Removes comments and trims the leading and ending whitespace
-pub fn compact_contents(contents: &str) -> String {
-    let mut res = String::new();
-    let mut commented = false;
-    for line in contents.lines() {
-        for (i, c) in line.chars().enumerate() {
-            let next_c = line.chars().nth(i + 1).unwrap_or_default();
-            let prev_c = {
-                if i == 0 {
-                    '0'
-                } else {
-                    line.chars().nth(i - 1).unwrap()
-                }
-            };
-            if c == '/' && next_c == '/' {
-                break;
-            }
-            if c == '/' && next_c == '*' {
-                commented = true;
-                continue;
-            }
-            if prev_c == '*' && c == '/' {
-                commented = false;
-                continue;
-            }
-            if !commented {
-                res.push(c);
-            }
-        }
-    }
-    res.trim().to_string()
-}
-
 // Checks if arg is a valid lexical identifier token
-fn is_valid_identifier(id: &str) -> bool {
+pub fn is_valid_identifier(id: &str) -> bool {
     !id.is_empty()
-        && !id.chars().nth(0).unwrap().is_numeric()
+        && !id.chars().next().unwrap().is_numeric()
         && id.chars().all(|x| x.is_alphanumeric() || x == '_')
 }
 
@@ -69,45 +36,88 @@ fn tokenize_word(word: &str) -> Result<Token, String> {
     }
 }
 
-// Tokenizes the compacted source code (source code with no comments)
-fn tokenize_compacted(input: &str) -> Result<Vec<Token>, String> {
-    let mut res = vec![];
-    let mut temp = String::new();
-    let mut is_literal = false;
-    for c in input.trim().chars() {
-        if is_literal {
-            if c == '"' {
-                is_literal = false;
-                res.push(Token::StringConst(temp));
-                temp = String::new();
-            } else {
-                temp.push(c);
-            }
-            continue;
+// Returns the kind and byte length of the lexeme at the start of the input
+fn next_lexeme(input: &str) -> (LexemeKind, usize) {
+    let c = input.chars().next().unwrap();
+    if c.is_whitespace() {
+        let len = input.find(|x: char| !x.is_whitespace());
+        (LexemeKind::Whitespace, len.unwrap_or(input.len()))
+    } else if input.starts_with("//") {
+        let len = input.find(['\r', '\n']);
+        (LexemeKind::LineComment, len.unwrap_or(input.len()))
+    } else if let Some(comment) = input.strip_prefix("/*") {
+        match comment.find("*/") {
+            Some(i) => (LexemeKind::BlockComment, i + 4),
+            None => (LexemeKind::Error, input.len()),
+        }
+    } else if c == '"' {
+        match input[1..].find(['"', '\r', '\n']) {
+            Some(i) if input[i + 1..].starts_with('"') => (LexemeKind::StringConst, i + 2),
+            Some(i) => (LexemeKind::Error, i + 1),
+            None => (LexemeKind::Error, input.len()),
         }
+    } else if SYMBOLS.contains(&c) {
+        (LexemeKind::Symbol, c.len_utf8())
+    } else {
+        let len = input
+            .find(|x: char| x.is_whitespace() || SYMBOLS.contains(&x) || x == '"')
+            .unwrap_or(input.len());
+        let kind = match tokenize_word(&input[..len]) {
+            Ok(Token::Keyword(_)) => LexemeKind::Keyword,
+            Ok(Token::IntConst(_)) => LexemeKind::IntConst,
+            Ok(_) => LexemeKind::Identifier,
+            Err(_) => LexemeKind::Error,
+        };
+        (kind, len)
+    }
+}
+
+// Splits the raw input source code into lexemes without losing any character
+pub fn lex(input: &str) -> Vec<Lexeme> {
+    let mut res = vec![];
+    let mut pos = 0;
+    while pos < input.len() {
+        let (kind, len) = next_lexeme(&input[pos..]);
+        res.push(Lexeme {
+            kind,
+            span: Span::new(pos, pos + len),
+        });
+        pos += len;
+    }
+    res
+}
 
-        let symbol = SYMBOLS.iter().find(|&i| i == &c);
-        if c.is_whitespace() || symbol.is_some() || c == '"' {
-            if !temp.is_empty() {
-                res.push(tokenize_word(&temp)?);
-                temp.clear();
-            }
-            if let Some(&s) = symbol {
-                res.push(Token::Symbol(s));
-            }
-            if c == '"' {
-                is_literal = true;
-            }
-            continue;
+// Converts a non-trivia lexeme of the source code into its token
+pub fn lexeme_token(lexeme: &Lexeme, input: &str) -> Result<Token, String> {
+    let text = lexeme.span.text(input);
+    match lexeme.kind {
+        LexemeKind::Symbol => Ok(Token::Symbol(text.chars().next().unwrap())),
+        LexemeKind::StringConst => Ok(Token::StringConst(text[1..text.len() - 1].to_string())),
+        LexemeKind::Error if text.starts_with('"') => {
+            Err(format!("Unterminated string constant {}", text))
+        }
+        LexemeKind::Error if text.starts_with("/*") => Err("Unterminated comment".to_string()),
+        LexemeKind::Keyword | LexemeKind::IntConst | LexemeKind::Identifier | LexemeKind::Error => {
+            tokenize_word(text)
         }
-        temp.push(c);
+        _ => Err(format!("'{}' is not a token", text)),
     }
-    Ok(res)
+}
+
+// Tokenizes the raw input source code, along with the span of each token
+pub fn tokenize_with_spans(input: &str) -> Result<(Vec<Token>, Vec<Span>), String> {
+    Ok(lex(input)
+        .iter()
+        .filter(|x| !x.kind.is_trivia())
+        .map(|x| Ok((lexeme_token(x, input)?, x.span)))
+        .collect::<Result<Vec<_>, String>>()?
+        .into_iter()
+        .unzip())
 }
 
 // Tokenizes the raw input source code
 pub fn tokenize(input: &str) -> Result<Vec<Token>, String> {
-    tokenize_compacted(&compact_contents(input))
+    Ok(tokenize_with_spans(input)?.0)
 }
 
 // Tokenizes the raw input source code into an XML file
@@ -0,0 +1,154 @@
+use super::{line_col, Json, Span};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+// Represents how serious a diagnostic is
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+// Represents additional information attached to a diagnostic, optionally at another location
+pub struct Note {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+// Represents a change to the source code which fixes the problem of a diagnostic: the text of
+// the span is replaced, or the replacement inserted if the span is empty
+pub struct Suggestion {
+    pub message: String,
+    pub span: Span,
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone)]
+// Represents an error or warning found in the source code of a class
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    pub notes: Vec<Note>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic {
+    // Create a new error Diagnostic
+    pub fn error(code: &'static str, message: String, span: Span) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message,
+            span,
+            notes: vec![],
+            suggestions: vec![],
+        }
+    }
+
+    // Create a new warning Diagnostic
+    pub fn warning(code: &'static str, message: String, span: Span) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(code, message, span)
+        }
+    }
+
+    // Attach a note to the diagnostic
+    pub fn with_note(mut self, message: String, span: Option<Span>) -> Self {
+        self.notes.push(Note { message, span });
+        self
+    }
+
+    // Attach a suggested fix to the diagnostic
+    pub fn with_suggestion(mut self, message: String, span: Span, replacement: String) -> Self {
+        self.suggestions.push(Suggestion {
+            message,
+            span,
+            replacement,
+        });
+        self
+    }
+
+    // Returns the human readable representation of the diagnostic in a file
+    pub fn render(&self, file: &str, source: &str) -> String {
+        let (line, col) = line_col(source, self.span.start);
+        let mut res = format!(
+            "{}:{}:{}: {}[{}]: {}",
+            file, line, col, self.severity, self.code, self.message
+        );
+        for note in &self.notes {
+            match note.span {
+                Some(span) => {
+                    let (line, col) = line_col(source, span.start);
+                    res += &format!("\n  {}:{}:{}: note: {}", file, line, col, note.message);
+                }
+                None => res += &format!("\n  note: {}", note.message),
+            }
+        }
+        for suggestion in &self.suggestions {
+            let (line, col) = line_col(source, suggestion.span.start);
+            res += &format!(
+                "\n  {}:{}:{}: help: {}",
+                file, line, col, suggestion.message
+            );
+        }
+        res
+    }
+
+    // Returns the JSON representation of the diagnostic in a file, with 1-based lines and columns
+    pub fn to_json(&self, file: &str, source: &str) -> Json {
+        let position = |span: Span| {
+            let (line, column) = line_col(source, span.start);
+            let (end_line, end_column) = line_col(source, span.end);
+            vec![
+                ("line", line.into()),
+                ("column", column.into()),
+                ("endLine", end_line.into()),
+                ("endColumn", end_column.into()),
+            ]
+        };
+        let notes = self
+            .notes
+            .iter()
+            .map(|note| {
+                let mut members = vec![("message", note.message.as_str().into())];
+                if let Some(span) = note.span {
+                    members.extend(position(span));
+                }
+                Json::object(members)
+            })
+            .collect::<Vec<_>>();
+        let fixes = self
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                let mut members = vec![("message", suggestion.message.as_str().into())];
+                members.extend(position(suggestion.span));
+                members.push(("replacement", suggestion.replacement.as_str().into()));
+                Json::object(members)
+            })
+            .collect::<Vec<_>>();
+        let mut members = vec![
+            ("file", file.into()),
+            ("severity", self.severity.to_string().into()),
+            ("code", self.code.into()),
+            ("message", self.message.as_str().into()),
+        ];
+        members.extend(position(self.span));
+        members.push(("notes", notes.into()));
+        members.push(("fixes", fixes.into()));
+        Json::object(members)
+    }
+}
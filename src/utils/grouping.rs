@@ -1,4 +1,4 @@
-use super::{ParseResult, Token, TokenStream};
+use super::{ParseResult, Span, Token, TokenStream};
 use std::fmt;
 
 #[derive(Clone)]
@@ -33,7 +33,8 @@ impl Grouping {
 
     // Add a token to the grouping
     pub fn add_token(&mut self, t: &Token) {
-        self.items.push(GroupItem::Token(t.clone()));
+        self.items
+            .push(GroupItem::Token(t.clone(), Span::default()));
     }
 
     // Add repeat tokens comma separated
@@ -43,11 +44,14 @@ impl Grouping {
         f: impl Fn(&mut TokenStream, &mut Self) -> Result<(), &'static str>,
         at_least_one: bool,
     ) -> Result<(), &'static str> {
-        // first repeat token
+        // first repeat token, which is given up entirely when it's optional
+        let (start, items) = (s.pos(), self.items.len());
         if let Err(e) = f(s, self) {
             if at_least_one {
                 return Err(e);
             } else {
+                s.rewind(start);
+                self.items.truncate(items);
                 return Ok(());
             }
         };
@@ -70,29 +74,39 @@ impl Grouping {
         self.items.push(GroupItem::Grouping(g));
     }
 
-    // Add repeat subgroupings
+    // Add repeat subgroupings, until one fails to match. The tokens of the failed subgrouping
+    // are left in the stream, so that the error is reported by what comes next.
     pub fn add_repeat_grouping(
         &mut self,
         s: &mut TokenStream,
         f: impl Fn(&mut TokenStream) -> ParseResult,
     ) {
         loop {
+            let start = s.pos();
             let g_res = f(s);
             match g_res {
                 Ok(g) => self.add_grouping(g),
-                Err(_) => break,
+                Err(_) => {
+                    s.rewind(start);
+                    break;
+                }
             }
         }
     }
 
     // Returns the XML string representation of the syntax tree
     pub fn as_xml(&self) -> String {
+        self.as_xml_with(&|t, _| t.as_xml())
+    }
+
+    // Returns the XML string representation of the syntax tree, writing tokens with a function
+    pub fn as_xml_with(&self, token_xml: &dyn Fn(&Token, Span) -> String) -> String {
         format!(
             "<{}>{}</{}>",
             self.name,
             self.items
                 .iter()
-                .fold(String::new(), |acc, i| acc + &i.as_xml()),
+                .fold(String::new(), |acc, i| acc + &i.as_xml_with(token_xml)),
             self.name
         )
     }
@@ -102,7 +116,7 @@ impl Grouping {
         self.items
             .iter()
             .map(|x| {
-                if let GroupItem::Token(t) = x {
+                if let GroupItem::Token(t, _) = x {
                     Some(t)
                 } else {
                     None
@@ -113,6 +127,40 @@ impl Grouping {
             .collect::<Vec<&Token>>()
     }
 
+    // Assigns source spans to the tokens of the grouping in the order they were parsed
+    pub fn assign_spans(&mut self, spans: &mut impl Iterator<Item = Span>) {
+        for item in &mut self.items {
+            match item {
+                GroupItem::Grouping(g) => g.assign_spans(spans),
+                GroupItem::Token(_, span) => *span = spans.next().unwrap_or_default(),
+            }
+        }
+    }
+
+    // Returns only the tokens in a grouping along with their spans
+    pub fn spanned_tokens(&self) -> Vec<(&Token, Span)> {
+        self.items
+            .iter()
+            .filter_map(|x| match x {
+                GroupItem::Token(t, span) => Some((t, *span)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Returns the span of the source code covered by the grouping
+    pub fn span(&self) -> Span {
+        self.items
+            .iter()
+            .map(|x| match x {
+                GroupItem::Grouping(g) => g.span(),
+                GroupItem::Token(_, span) => *span,
+            })
+            .filter(|x| x != &Span::default())
+            .reduce(|a, b| a.cover(b))
+            .unwrap_or_default()
+    }
+
     // Returns only the sub-groupings in a grouping
     pub fn subgroupings(&self) -> Vec<&Grouping> {
         self.items
@@ -134,15 +182,15 @@ impl Grouping {
 // Represents items in a grouping, can either be tokens or further substructures
 pub enum GroupItem {
     Grouping(Grouping),
-    Token(Token),
+    Token(Token, Span),
 }
 
 impl GroupItem {
-    // Returns the XML string representation of the item
-    fn as_xml(&self) -> String {
+    // Returns the XML string representation of the item, writing tokens with a function
+    fn as_xml_with(&self, token_xml: &dyn Fn(&Token, Span) -> String) -> String {
         match self {
-            Self::Grouping(g) => g.as_xml(),
-            Self::Token(t) => t.as_xml(),
+            Self::Grouping(g) => g.as_xml_with(token_xml),
+            Self::Token(t, span) => token_xml(t, *span),
         }
     }
 }
@@ -1,12 +1,22 @@
+pub mod diagnostic;
 pub mod grouping;
+pub mod json;
+pub mod pool;
+pub mod span;
 pub mod symbol_table;
 pub mod token;
 pub mod token_stream;
+pub mod toml;
 
+pub use diagnostic::*;
 pub use grouping::*;
+pub use json::*;
+pub use pool::*;
+pub use span::*;
 pub use symbol_table::*;
 pub use token::*;
 pub use token_stream::*;
+pub use toml::*;
 
 pub type TokenResult<'a> = Result<&'a Token, &'static str>;
 pub type ParseResult = Result<Grouping, &'static str>;
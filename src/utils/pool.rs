@@ -0,0 +1,50 @@
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+// Returns the number of worker threads to use by default, one per available CPU
+pub fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, |x| x.get())
+}
+
+// Applies a function to every item on a pool of worker threads, which take the items from a
+// channel and send back the results. The results are in the order of the items regardless of
+// which worker finished first. With a single job, everything runs on the current thread.
+pub fn parallel_map<T: Send, R: Send>(
+    items: Vec<T>,
+    jobs: usize,
+    f: impl Fn(T) -> R + Sync,
+) -> Vec<R> {
+    let count = items.len();
+    let jobs = jobs.min(count);
+    if jobs <= 1 {
+        return items.into_iter().map(f).collect();
+    }
+    let (job_sender, job_receiver) = mpsc::channel();
+    for job in items.into_iter().enumerate() {
+        job_sender.send(job).unwrap();
+    }
+    drop(job_sender);
+    let job_receiver = Mutex::new(job_receiver);
+    let (result_sender, result_receiver) = mpsc::channel();
+    thread::scope(|s| {
+        for _ in 0..jobs {
+            let result_sender = result_sender.clone();
+            let job_receiver = &job_receiver;
+            let f = &f;
+            s.spawn(move || {
+                loop {
+                    // the lock is only held while taking the next item, not while running it
+                    let job = job_receiver.lock().unwrap().recv();
+                    let Ok((i, item)) = job else { break };
+                    result_sender.send((i, f(item))).unwrap();
+                }
+            });
+        }
+    });
+    drop(result_sender);
+    let mut res = (0..count).map(|_| None).collect::<Vec<_>>();
+    for (i, result) in result_receiver {
+        res[i] = Some(result);
+    }
+    res.into_iter().flatten().collect()
+}
@@ -1,15 +1,15 @@
 use super::*;
 use std::fmt;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum SymbolKind {
-    StaticVar,
-    FieldVar,
-    ArgumentVar,
-    LocalVar,
+    Static,
+    Field,
+    Argument,
+    Local,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Datatype {
     Boolean,
     Char,
@@ -20,7 +20,7 @@ pub enum Datatype {
 impl Datatype {
     pub fn keyword_to_datatype(keyword: &str) -> Datatype {
         match keyword {
-            "bool" => Datatype::Boolean,
+            "boolean" => Datatype::Boolean,
             "char" => Datatype::Char,
             "int" => Datatype::Int,
             _ => Datatype::Custom(keyword.to_string()),
@@ -28,18 +28,55 @@ impl Datatype {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Symbol<'a> {
     name: &'a str,
     dtype: Datatype,
     kind: SymbolKind,
     index: usize,
+    span: Span,
 }
 
 impl<'a> Symbol<'a> {
+    // Create a new symbol, whose index is assigned by the symbol table it is put in
+    pub fn new(name: &'a str, dtype: Datatype, kind: SymbolKind, span: Span) -> Symbol<'a> {
+        Symbol {
+            name,
+            dtype,
+            kind,
+            index: 0,
+            span,
+        }
+    }
+
+    // Returns the name of the symbol
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    // Returns the kind of the symbol
+    pub fn kind(&self) -> &SymbolKind {
+        &self.kind
+    }
+
+    // Returns the datatype of the symbol
+    pub fn dtype(&self) -> &Datatype {
+        &self.dtype
+    }
+
+    // Returns the running index of the symbol among the symbols of its kind
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    // Returns the span of the name in the declaration of the symbol
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
     pub fn class(&self) -> String {
         match &self.dtype {
-            Datatype::Boolean => "bool".to_string(),
+            Datatype::Boolean => "boolean".to_string(),
             Datatype::Char => "char".to_string(),
             Datatype::Int => "int".to_string(),
             Datatype::Custom(s) => s.to_string(),
@@ -48,10 +85,10 @@ impl<'a> Symbol<'a> {
 
     pub fn literal(&self) -> String {
         (match self.kind {
-            SymbolKind::StaticVar => "static",
-            SymbolKind::FieldVar => "field",
-            SymbolKind::ArgumentVar => "argument",
-            SymbolKind::LocalVar => "local",
+            SymbolKind::Static => "static",
+            SymbolKind::Field => "this",
+            SymbolKind::Argument => "argument",
+            SymbolKind::Local => "local",
         })
         .to_string()
             + " "
@@ -59,69 +96,97 @@ impl<'a> Symbol<'a> {
     }
 }
 
+#[derive(Clone)]
 pub struct SymbolTable<'a> {
-    parent: Option<&'a SymbolTable<'a>>,
+    class_name: &'a str,
+    parent: Option<Box<SymbolTable<'a>>>,
     symbols: Vec<Symbol<'a>>,
 }
 
 impl<'a> SymbolTable<'a> {
-    fn new(mut symbols: Vec<Symbol<'a>>) -> SymbolTable<'a> {
+    // Create a new SymbolTable, numbering the symbols of each kind in declaration order
+    pub fn new(class_name: &'a str, mut symbols: Vec<Symbol<'a>>) -> SymbolTable<'a> {
         let mut static_count = 0;
         let mut local_count = 0;
         let mut field_count = 0;
         let mut arg_count = 0;
         for symbol in &mut symbols {
             match symbol.kind {
-                SymbolKind::StaticVar => {
+                SymbolKind::Static => {
                     symbol.index = static_count;
                     static_count += 1;
                 }
-                SymbolKind::LocalVar => {
+                SymbolKind::Local => {
                     symbol.index = local_count;
                     local_count += 1;
                 }
-                SymbolKind::FieldVar => {
+                SymbolKind::Field => {
                     symbol.index = field_count;
                     field_count += 1;
                 }
-                SymbolKind::ArgumentVar => {
+                SymbolKind::Argument => {
                     symbol.index = arg_count;
                     arg_count += 1;
                 }
             }
         }
-        SymbolTable { parent: None, symbols }
+        SymbolTable {
+            class_name,
+            parent: None,
+            symbols,
+        }
+    }
+
+    // Sets the symbol table of the enclosing scope
+    pub fn with_parent(mut self, parent: SymbolTable<'a>) -> SymbolTable<'a> {
+        self.parent = Some(Box::new(parent));
+        self
+    }
+
+    // Returns the symbol table of the enclosing scope, if any
+    pub fn parent(&self) -> Option<&SymbolTable<'a>> {
+        self.parent.as_deref()
+    }
+
+    // Returns the symbols declared in this scope, not including the parent scope
+    pub fn symbols(&self) -> &[Symbol<'a>] {
+        &self.symbols
     }
 
-    pub fn get(&self, symbol_name: &str) -> Result<&Symbol<'_>, String> {
+    // Returns the name of the class the symbol table belongs to
+    pub fn class_name(&self) -> &'a str {
+        self.class_name
+    }
+
+    pub fn get(&self, symbol_name: &str) -> Result<&Symbol<'a>, String> {
         for symbol in &self.symbols {
             if symbol.name == symbol_name {
                 return Ok(symbol);
             }
         }
-        if let Some(st) = self.parent {
-            return st.get(symbol_name); 
+        if let Some(st) = &self.parent {
+            st.get(symbol_name)
         } else {
-            return Err(format!("Undefined symbol {}", symbol_name));
+            Err(format!("Undefined symbol {}", symbol_name))
         }
     }
 }
 
 impl fmt::Display for SymbolTable<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "Symbol Table");
+        writeln!(f, "Symbol Table")?;
         for symbol in &self.symbols {
             let dtype_str = match &symbol.dtype {
-                Datatype::Boolean => "bool",
+                Datatype::Boolean => "boolean",
                 Datatype::Char => "char",
                 Datatype::Int => "int",
-                Datatype::Custom(s) => &s,
+                Datatype::Custom(s) => s,
             };
             let kind_str = match symbol.kind {
-                SymbolKind::StaticVar => "static",
-                SymbolKind::FieldVar => "field",
-                SymbolKind::ArgumentVar => "arg",
-                SymbolKind::LocalVar => "local",
+                SymbolKind::Static => "static",
+                SymbolKind::Field => "field",
+                SymbolKind::Argument => "arg",
+                SymbolKind::Local => "local",
             };
             writeln!(
                 f,
@@ -141,40 +206,39 @@ fn token_to_datatype(token: &Token) -> Result<Datatype, String> {
     }
 }
 
-fn class_var_symbols(class_var_grouping: &Grouping) -> Result<Vec<Symbol>, String> {
+fn class_var_symbols(class_var_grouping: &Grouping) -> Result<Vec<Symbol<'_>>, String> {
     if class_var_grouping.name != "classVarDec" {
         return Err("Grouping is not a class variable declaration".to_string());
     }
     let kind = match class_var_grouping.tokens()[0] {
-        Token::Keyword("field") => SymbolKind::FieldVar,
-        Token::Keyword("static") => SymbolKind::StaticVar,
+        Token::Keyword("field") => SymbolKind::Field,
+        Token::Keyword("static") => SymbolKind::Static,
         _ => {
             return Err("Class variable must be either static or field".to_string());
         }
     };
-    let dtype = token_to_datatype(&class_var_grouping.tokens()[1])?;
-    let tokens = class_var_grouping.tokens();
-    tokens[2..tokens.len()]
-        .into_iter()
-        .filter(|x| matches!(x, Token::Identifier(_)))
-        .map(|x| {
-            Ok(Symbol {
-                name: {
-                    if let Token::Identifier(n) = x {
-                        &n
-                    } else {
-                        Err("Invalid class variable declaration".to_string())?
-                    }
-                },
+    declared_symbols(class_var_grouping, kind)
+}
+
+// Returns the symbols named in a class or local variable declaration
+fn declared_symbols(declaration: &Grouping, kind: SymbolKind) -> Result<Vec<Symbol<'_>>, String> {
+    let dtype = token_to_datatype(declaration.tokens()[1])?;
+    Ok(declaration.spanned_tokens()[2..]
+        .iter()
+        .filter_map(|(x, span)| match x {
+            Token::Identifier(n) => Some(Symbol {
+                name: n,
                 dtype: dtype.clone(),
                 kind: kind.clone(),
                 index: 0,
-            })
+                span: *span,
+            }),
+            _ => None,
         })
-        .collect()
+        .collect())
 }
 
-pub fn create_symbol_table(class_grouping: &Grouping) -> Result<SymbolTable, String> {
+pub fn create_symbol_table(class_grouping: &Grouping) -> Result<SymbolTable<'_>, String> {
     if class_grouping.name != "class" {
         return Err("Grouping is not a class".to_string());
     }
@@ -182,7 +246,7 @@ pub fn create_symbol_table(class_grouping: &Grouping) -> Result<SymbolTable, Str
         if let Token::Identifier(name) = class_grouping.tokens()[1] {
             name
         } else {
-            return Err("Class doesn't have a name".to_string())?;
+            return Err("Class doesn't have a name".to_string());
         }
     };
     let symbols = class_grouping
@@ -194,21 +258,21 @@ pub fn create_symbol_table(class_grouping: &Grouping) -> Result<SymbolTable, Str
         .into_iter()
         .flatten()
         .collect::<Vec<_>>();
-    Ok(SymbolTable::new(symbols))
+    Ok(SymbolTable::new(class_name, symbols))
 }
 
-fn parameter_symbols(parameter_list: &Grouping) -> Result<Vec<Symbol>, String> {
+fn parameter_symbols(parameter_list: &Grouping) -> Result<Vec<Symbol<'_>>, String> {
     if parameter_list.name != "parameterList" {
         return Err("Grouping is not a parameter list".to_string());
     }
     parameter_list
-        .tokens()
-        .iter()
-        .filter(|x| !matches!(x, Token::Symbol(',')))
+        .spanned_tokens()
+        .into_iter()
+        .filter(|(x, _)| !matches!(x, Token::Symbol(',')))
         .collect::<Vec<_>>()
         .chunks(2)
-        .map(|x| (x[0], x[1]))
-        .map(|(type_token, name_token)| {
+        .map(|x| (x[0].0, x[1]))
+        .map(|(type_token, (name_token, span))| {
             Ok(Symbol {
                 name: {
                     if let Token::Identifier(name) = name_token {
@@ -217,44 +281,27 @@ fn parameter_symbols(parameter_list: &Grouping) -> Result<Vec<Symbol>, String> {
                         Err("Parameter list is invalid".to_string())?
                     }
                 },
-                dtype: token_to_datatype(&type_token)?,
-                kind: SymbolKind::ArgumentVar,
+                dtype: token_to_datatype(type_token)?,
+                kind: SymbolKind::Argument,
                 index: 0,
+                span,
             })
         })
         .collect::<Result<Vec<_>, String>>()
 }
 
-fn var_dec_symbols(var_dec: &Grouping) -> Result<Vec<Symbol>, String> {
+fn var_dec_symbols(var_dec: &Grouping) -> Result<Vec<Symbol<'_>>, String> {
     if var_dec.name != "varDec" {
         return Err("Grouping is not a local variable declaration".to_string());
     }
-    let dtype = token_to_datatype(&var_dec.tokens()[1])?;
-    var_dec
-        .tokens()
-        .iter()
-        .filter(|x| matches!(x, Token::Identifier(_)))
-        .map(|x| {
-            Ok(Symbol {
-                name: {
-                    if let Token::Identifier(n) = x {
-                        &n
-                    } else {
-                        Err("Invalid local variable declaration".to_string())?
-                    }
-                },
-                dtype: dtype.clone(),
-                kind: SymbolKind::LocalVar,
-                index: 0,
-            })
-        })
-        .collect()
+    declared_symbols(var_dec, SymbolKind::Local)
 }
 
-fn create_subroutine_symbol_table(
-    subroutine_grouping: &Grouping,
-    class_name: String,
-) -> Result<SymbolTable, String> {
+// Creates the symbol table of a subroutine, falling back to the class symbol table
+pub fn create_subroutine_symbol_table<'a>(
+    subroutine_grouping: &'a Grouping,
+    class_symbol_table: &SymbolTable<'a>,
+) -> Result<SymbolTable<'a>, String> {
     if subroutine_grouping.name != "subroutineDec" {
         return Err("Not a subroutine grouping".to_string());
     }
@@ -262,9 +309,10 @@ fn create_subroutine_symbol_table(
     if let Token::Keyword("method") = subroutine_grouping.tokens()[0] {
         let this_symbol = Symbol {
             name: "this",
-            dtype: Datatype::Custom(class_name),
-            kind: SymbolKind::ArgumentVar,
+            dtype: Datatype::Custom(class_symbol_table.class_name.to_string()),
+            kind: SymbolKind::Argument,
             index: 0,
+            span: Span::default(),
         };
         args.insert(0, this_symbol);
     }
@@ -277,5 +325,8 @@ fn create_subroutine_symbol_table(
         .into_iter()
         .flatten()
         .collect();
-    Ok(SymbolTable::new([args, locals].concat()))
+    Ok(
+        SymbolTable::new(class_symbol_table.class_name, [args, locals].concat())
+            .with_parent(class_symbol_table.clone()),
+    )
 }
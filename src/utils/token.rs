@@ -1,4 +1,6 @@
-pub static KEYWORDS: &'static [&'static str] = &[
+use super::Span;
+
+pub static KEYWORDS: &[&str] = &[
     "class",
     "constructor",
     "function",
@@ -21,10 +23,46 @@ pub static KEYWORDS: &'static [&'static str] = &[
     "while",
     "return",
 ];
-pub static SYMBOLS: &'static [char] = &[
+pub static SYMBOLS: &[char] = &[
     '{', '}', '(', ')', '[', ']', '.', ',', ';', '+', '-', '*', '/', '&', '|', '<', '>', '=', '~',
 ];
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Represents the kind of a lexeme, including the trivia that is not tokenized
+pub enum LexemeKind {
+    Whitespace,
+    LineComment,
+    BlockComment,
+    Keyword,
+    Symbol,
+    IntConst,
+    StringConst,
+    Identifier,
+    Error,
+}
+
+impl LexemeKind {
+    // Checks if the lexeme is ignored by the parser
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self,
+            Self::Whitespace | Self::LineComment | Self::BlockComment
+        )
+    }
+
+    // Checks if the lexeme is a comment
+    pub fn is_comment(&self) -> bool {
+        matches!(self, Self::LineComment | Self::BlockComment)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+// Represents a piece of the source code, so that all lexemes together reproduce it exactly
+pub struct Lexeme {
+    pub kind: LexemeKind,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone)]
 // Represents a lexical token
 pub enum Token {
@@ -36,6 +74,17 @@ pub enum Token {
 }
 
 impl Token {
+    // Return the token as it is written in source code
+    pub fn as_text(&self) -> String {
+        match self {
+            Token::Keyword(v) => v.to_string(),
+            Token::Symbol(v) => v.to_string(),
+            Token::IntConst(v) => v.to_string(),
+            Token::StringConst(v) => format!("\"{}\"", v),
+            Token::Identifier(v) => v.to_string(),
+        }
+    }
+
     // Return the XML representation string of the token
     pub fn as_xml(&self) -> String {
         match self {
@@ -5,6 +5,8 @@ use std::fmt;
 pub struct TokenStream {
     tokens: Vec<Token>,
     pos: usize,
+    furthest: usize,
+    expected: Vec<String>,
 }
 
 impl fmt::Debug for TokenStream {
@@ -16,16 +18,64 @@ impl fmt::Debug for TokenStream {
 impl TokenStream {
     // Create a new TokenStream
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, pos: 0 }
+        Self {
+            tokens,
+            pos: 0,
+            furthest: 0,
+            expected: vec![],
+        }
+    }
+
+    // Return the position of the next token in the stream
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    // Move the stream back to an earlier position, after an optional part failed to match
+    pub fn rewind(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    // Checks if all tokens have been consumed
+    pub fn is_finished(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    // Record what was expected at the current position, after matching failed there
+    pub fn expect(&mut self, expected: &str) {
+        if self.pos > self.furthest {
+            self.furthest = self.pos;
+            self.expected.clear();
+        }
+        if self.pos == self.furthest && !self.expected.iter().any(|x| x == expected) {
+            self.expected.push(expected.to_string());
+        }
+    }
+
+    // Return the furthest position where matching failed, along with what was expected there
+    pub fn furthest_failure(&self) -> (usize, &[String]) {
+        (self.furthest, &self.expected)
+    }
+
+    // Validate next token in stream, recording the expectation if it does not match
+    fn next_expecting(
+        &mut self,
+        expected: &str,
+        validation_f: impl Fn(&Token) -> bool,
+    ) -> TokenResult<'_> {
+        if !self.tokens.get(self.pos).is_some_and(&validation_f) {
+            self.expect(expected);
+        }
+        self.next(validation_f)
     }
 
     // Return the next token without advancing the stream
-    pub fn peek(&self) -> TokenResult {
-        return self.tokens.get(self.pos).ok_or("No more tokens left");
+    pub fn peek(&self) -> TokenResult<'_> {
+        self.tokens.get(self.pos).ok_or("No more tokens left")
     }
 
     // Validate next token in stream based on validation function and return it
-    pub fn next(&mut self, validation_f: impl Fn(&Token) -> bool) -> TokenResult {
+    pub fn next(&mut self, validation_f: impl Fn(&Token) -> bool) -> TokenResult<'_> {
         let token = self.tokens.get(self.pos).ok_or("No more tokens left")?;
         if validation_f(token) {
             // println!("suc {:?}", token);
@@ -38,15 +88,23 @@ impl TokenStream {
     }
 
     // Return next token assuming its a specific keyword
-    pub fn keyword(&mut self, keyword: &'static str) -> TokenResult {
+    pub fn keyword(&mut self, keyword: &'static str) -> TokenResult<'_> {
         // println!("keyword val {}", keyword);
-        self.next(|t| matches!(t, Token::Keyword(k) if k == &keyword))
+        self.next_expecting(
+            &format!("'{}'", keyword),
+            |t| matches!(t, Token::Keyword(k) if k == &keyword),
+        )
     }
 
     // Return next token assuming its a specific keyword
-    pub fn keywords(&mut self, keywords: &'static [&'static str]) -> TokenResult {
+    pub fn keywords(&mut self, keywords: &'static [&'static str]) -> TokenResult<'_> {
         // println!("keywords val {:?}", keywords);
-        self.next(|t| {
+        let expected = keywords
+            .iter()
+            .map(|x| format!("'{}'", x))
+            .collect::<Vec<_>>()
+            .join(" or ");
+        self.next_expecting(&expected, |t| {
             keywords
                 .iter()
                 .any(|keyword| matches!(t, Token::Keyword(k) if k == keyword))
@@ -54,25 +112,28 @@ impl TokenStream {
     }
 
     // Return next token assuming its a specific symbol
-    pub fn symbol(&mut self, symbol: char) -> TokenResult {
+    pub fn symbol(&mut self, symbol: char) -> TokenResult<'_> {
         // println!("symbol val {}", symbol);
-        self.next(|t| matches!(t, Token::Symbol(s) if s == &symbol))
+        self.next_expecting(
+            &format!("'{}'", symbol),
+            |t| matches!(t, Token::Symbol(s) if s == &symbol),
+        )
     }
 
     // Return next token assuming its a string constant
-    pub fn string(&mut self) -> TokenResult {
+    pub fn string(&mut self) -> TokenResult<'_> {
         // println!("string const val");
-        self.next(|t| matches!(t, Token::StringConst(_)))
+        self.next_expecting("a string constant", |t| matches!(t, Token::StringConst(_)))
     }
 
     // Return next token assuming its an integer
-    pub fn int(&mut self) -> TokenResult {
+    pub fn int(&mut self) -> TokenResult<'_> {
         // println!("int const val");
-        self.next(|t| matches!(t, Token::IntConst(_)))
+        self.next_expecting("an integer constant", |t| matches!(t, Token::IntConst(_)))
     }
 
     // Return next token assuming its an identifier
-    pub fn identifier(&mut self) -> TokenResult {
-        self.next(|t| matches!(t, Token::Identifier(_)))
+    pub fn identifier(&mut self) -> TokenResult<'_> {
+        self.next_expecting("an identifier", |t| matches!(t, Token::Identifier(_)))
     }
 }
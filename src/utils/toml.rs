@@ -0,0 +1,244 @@
+use super::Json;
+
+// Parses the subset of TOML used by configuration files into a JSON object:
+// tables, dotted keys, strings, integers, booleans, arrays and inline tables
+pub fn parse_toml(input: &str) -> Result<Json, String> {
+    let mut parser = TomlParser {
+        chars: input.chars().collect(),
+        pos: 0,
+    };
+    let mut root = Json::Object(vec![]);
+    let mut table: Vec<String> = vec![];
+    loop {
+        parser.skip_trivia(true);
+        match parser.peek() {
+            None => return Ok(root),
+            Some('[') => {
+                parser.pos += 1;
+                parser.skip_trivia(false);
+                table = parser.key()?;
+                parser.skip_trivia(false);
+                parser.expect(']')?;
+                insert(&mut root, &table, None)?;
+            }
+            Some(_) => {
+                let key = [table.clone(), parser.key()?].concat();
+                parser.skip_trivia(false);
+                parser.expect('=')?;
+                parser.skip_trivia(false);
+                let value = parser.value()?;
+                insert(&mut root, &key, Some(value)).map_err(|e| parser.error(&e))?;
+            }
+        }
+        parser.skip_trivia(false);
+        match parser.peek() {
+            None | Some('\n') => (),
+            Some(c) => return Err(parser.error(&format!("Unexpected '{}'", c))),
+        }
+    }
+}
+
+// Inserts a value at a path of keys, creating the tables on the way. Without a value, only the
+// tables are created.
+fn insert(root: &mut Json, path: &[String], value: Option<Json>) -> Result<(), String> {
+    let mut json = root;
+    for (i, key) in path.iter().enumerate() {
+        let members = match json {
+            Json::Object(members) => members,
+            _ => return Err(format!("'{}' is not a table", path[..i].join("."))),
+        };
+        let last = i + 1 == path.len();
+        let position = match members.iter().position(|(k, _)| k == key) {
+            Some(_) if last && value.is_some() => {
+                return Err(format!("'{}' is defined twice", path.join(".")))
+            }
+            Some(position) => position,
+            None => {
+                members.push((key.clone(), Json::Object(vec![])));
+                members.len() - 1
+            }
+        };
+        if last {
+            if let Some(value) = value {
+                members[position].1 = value;
+                return Ok(());
+            }
+        }
+        json = &mut members[position].1;
+    }
+    Ok(())
+}
+
+// Parses TOML text character by character
+struct TomlParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl TomlParser {
+    // Returns the next character
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    // Returns an error message with the current line number
+    fn error(&self, message: &str) -> String {
+        let line = self.chars[..self.pos.min(self.chars.len())]
+            .iter()
+            .filter(|x| **x == '\n')
+            .count()
+            + 1;
+        format!("line {}: {}", line, message)
+    }
+
+    // Consumes the expected character
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() != Some(c) {
+            return Err(self.error(&format!("Expected '{}'", c)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    // Skips spaces and comments, and line breaks too if requested
+    fn skip_trivia(&mut self, newlines: bool) {
+        while let Some(c) = self.peek() {
+            match c {
+                '#' => {
+                    while self.peek().is_some_and(|x| x != '\n') {
+                        self.pos += 1;
+                    }
+                }
+                '\n' if !newlines => break,
+                c if c.is_whitespace() => self.pos += 1,
+                _ => break,
+            }
+        }
+    }
+
+    // Parses a bare, quoted or dotted key
+    fn key(&mut self) -> Result<Vec<String>, String> {
+        let mut res = vec![];
+        loop {
+            self.skip_trivia(false);
+            let part = match self.peek() {
+                Some('"') | Some('\'') => self.string()?,
+                _ => {
+                    let start = self.pos;
+                    while self
+                        .peek()
+                        .is_some_and(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-')
+                    {
+                        self.pos += 1;
+                    }
+                    if start == self.pos {
+                        return Err(self.error("Expected a key"));
+                    }
+                    self.chars[start..self.pos].iter().collect()
+                }
+            };
+            res.push(part);
+            self.skip_trivia(false);
+            if self.peek() != Some('.') {
+                return Ok(res);
+            }
+            self.pos += 1;
+        }
+    }
+
+    // Parses a basic or literal string
+    fn string(&mut self) -> Result<String, String> {
+        let quote = self.peek().unwrap_or('"');
+        self.pos += 1;
+        let mut res = String::new();
+        loop {
+            let c = match self.peek() {
+                Some('\n') | None => return Err(self.error("Unterminated string")),
+                Some(c) => c,
+            };
+            self.pos += 1;
+            match c {
+                c if c == quote => return Ok(res),
+                '\\' if quote == '"' => {
+                    let escaped = self.peek().ok_or(self.error("Unterminated string"))?;
+                    self.pos += 1;
+                    res.push(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        '"' => '"',
+                        '\\' => '\\',
+                        c => return Err(self.error(&format!("Unknown escape '\\{}'", c))),
+                    });
+                }
+                c => res.push(c),
+            }
+        }
+    }
+
+    // Parses a value
+    fn value(&mut self) -> Result<Json, String> {
+        match self.peek() {
+            Some('"') | Some('\'') => Ok(Json::String(self.string()?)),
+            Some('[') => {
+                self.pos += 1;
+                let mut res = vec![];
+                loop {
+                    self.skip_trivia(true);
+                    if self.peek() == Some(']') {
+                        self.pos += 1;
+                        return Ok(Json::Array(res));
+                    }
+                    res.push(self.value()?);
+                    self.skip_trivia(true);
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some(']') => (),
+                        _ => return Err(self.error("Expected ',' or ']'")),
+                    }
+                }
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut res = Json::Object(vec![]);
+                loop {
+                    self.skip_trivia(false);
+                    if self.peek() == Some('}') {
+                        self.pos += 1;
+                        return Ok(res);
+                    }
+                    let key = self.key()?;
+                    self.expect('=')?;
+                    self.skip_trivia(false);
+                    let value = self.value()?;
+                    insert(&mut res, &key, Some(value)).map_err(|e| self.error(&e))?;
+                    self.skip_trivia(false);
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some('}') => (),
+                        _ => return Err(self.error("Expected ',' or '}'")),
+                    }
+                }
+            }
+            _ => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|x| x.is_ascii_alphanumeric() || matches!(x, '_' | '-' | '+'))
+                {
+                    self.pos += 1;
+                }
+                let text = self.chars[start..self.pos].iter().collect::<String>();
+                match text.as_str() {
+                    "true" => Ok(Json::Bool(true)),
+                    "false" => Ok(Json::Bool(false)),
+                    _ => text
+                        .replace('_', "")
+                        .parse::<i64>()
+                        .map(|x| Json::Number(x as f64))
+                        .map_err(|_| self.error(&format!("Invalid value '{}'", text))),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+// Represents where the compiler reads its sources from and writes its outputs to. The files are
+// shared between the worker threads of a build, so writing only needs a shared reference.
+pub trait FileSystem: Sync {
+    fn read(&self, path: &Path) -> io::Result<String>;
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()>;
+
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    fn is_dir(&self, path: &Path) -> bool;
+
+    // Returns the paths of the files and directories directly in a directory, sorted
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+
+    // Returns the path a file is known by however it was named, used to identify it
+    fn canonicalize(&self, path: &Path) -> PathBuf {
+        path.to_path_buf()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+// Represents the real file system
+pub struct DiskFiles;
+
+impl FileSystem for DiskFiles {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut paths = fs::read_dir(path)?
+            .map(|x| x.map(|x| x.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn canonicalize(&self, path: &Path) -> PathBuf {
+        fs::canonicalize(path).unwrap_or(path.to_path_buf())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+// Identifies a file of a SourceFiles, in the order the files were added
+pub struct FileId(pub usize);
+
+#[derive(Debug, Default)]
+// Represents files kept in memory, e.g. for tests or to compile programs in a browser. Each
+// path has a file id, which stays the same when the file is written again. A removed file keeps
+// its id without contents, so the ids of the other files don't change. Directories exist when
+// they were created or hold a file.
+pub struct SourceFiles {
+    files: RwLock<Vec<(PathBuf, Option<String>)>>,
+    dirs: RwLock<Vec<PathBuf>>,
+}
+
+impl SourceFiles {
+    // Create an empty file system
+    pub fn new() -> Self {
+        SourceFiles::default()
+    }
+
+    // Reads a directory of the real file system and everything in it into memory, under the
+    // same paths
+    pub fn load(dir: impl AsRef<Path>) -> io::Result<SourceFiles> {
+        let files = SourceFiles::new();
+        let mut pending = vec![dir.as_ref().to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            files.create_dir_all(&dir)?;
+            let (dirs, paths): (Vec<_>, Vec<_>) = DiskFiles
+                .read_dir(&dir)?
+                .into_iter()
+                .partition(|x| x.is_dir());
+            for path in paths {
+                files.insert(&path, fs::read_to_string(&path)?);
+            }
+            // the files get their ids in the order of their paths
+            pending.extend(dirs.into_iter().rev());
+        }
+        Ok(files)
+    }
+
+    // Adds a file, or replaces the contents of the file at the path, returning its id
+    pub fn insert(&self, path: impl AsRef<Path>, contents: impl Into<String>) -> FileId {
+        let path = path.as_ref();
+        let mut files = self.files.write().unwrap();
+        match files.iter().position(|(x, _)| x == path) {
+            Some(i) => {
+                files[i].1 = Some(contents.into());
+                FileId(i)
+            }
+            None => {
+                files.push((path.to_path_buf(), Some(contents.into())));
+                FileId(files.len() - 1)
+            }
+        }
+    }
+
+    // Returns the id of the file at a path
+    pub fn id(&self, path: impl AsRef<Path>) -> Option<FileId> {
+        let path = path.as_ref();
+        let files = self.files.read().unwrap();
+        files
+            .iter()
+            .position(|(x, contents)| x == path && contents.is_some())
+            .map(FileId)
+    }
+
+    // Returns the path of a file
+    pub fn path(&self, id: FileId) -> Option<PathBuf> {
+        let files = self.files.read().unwrap();
+        files
+            .get(id.0)
+            .filter(|(_, contents)| contents.is_some())
+            .map(|(x, _)| x.clone())
+    }
+
+    // Returns the contents of a file
+    pub fn contents(&self, id: FileId) -> Option<String> {
+        let files = self.files.read().unwrap();
+        files.get(id.0).and_then(|(_, x)| x.clone())
+    }
+
+    // Returns the contents of the file at a path
+    pub fn get(&self, path: impl AsRef<Path>) -> Option<String> {
+        self.contents(self.id(path)?)
+    }
+
+    // Returns the paths of the files, in the order of their ids
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let files = self.files.read().unwrap();
+        files
+            .iter()
+            .filter(|(_, contents)| contents.is_some())
+            .map(|(x, _)| x.clone())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Returns the error of a missing file or directory
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("No such file or directory: {}", path.display()),
+    )
+}
+
+impl FileSystem for SourceFiles {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        self.get(path).ok_or_else(|| not_found(path))
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        let parent = path.parent().unwrap_or(Path::new(""));
+        if !parent.as_os_str().is_empty() && !self.is_dir(parent) {
+            return Err(not_found(parent));
+        }
+        self.insert(path, contents);
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let mut files = self.files.write().unwrap();
+        match files
+            .iter_mut()
+            .find(|(x, contents)| x == path && contents.is_some())
+        {
+            Some((_, contents)) => {
+                *contents = None;
+                Ok(())
+            }
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut dirs = self.dirs.write().unwrap();
+        if !dirs.iter().any(|x| x == path) {
+            dirs.push(path.to_path_buf());
+        }
+        Ok(())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        let dirs = self.dirs.read().unwrap();
+        let files = self.files.read().unwrap();
+        dirs.iter()
+            .chain(files.iter().filter(|(_, x)| x.is_some()).map(|(x, _)| x))
+            .any(|x| x != path && x.starts_with(path))
+            || dirs.iter().any(|x| x == path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        if !self.is_dir(path) {
+            return Err(not_found(path));
+        }
+        let dirs = self.dirs.read().unwrap();
+        let files = self.files.read().unwrap();
+        let mut paths = dirs
+            .iter()
+            .chain(files.iter().filter(|(_, x)| x.is_some()).map(|(x, _)| x))
+            .filter_map(|x| {
+                let child = x.strip_prefix(path).ok()?.components().next()?;
+                Some(path.join(child))
+            })
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.dedup();
+        Ok(paths)
+    }
+}
@@ -0,0 +1,626 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+// The addresses of the Hack RAM the VM is laid out in
+const SP: usize = 0;
+const LCL: usize = 1;
+const ARG: usize = 2;
+const THIS: usize = 3;
+const THAT: usize = 4;
+const TEMP: usize = 5;
+const STATIC: usize = 16;
+const STACK: usize = 256;
+const HEAP: usize = 2048;
+const SCREEN: usize = 16384;
+const RAM_SIZE: usize = 24577;
+
+// The character codes of the Jack character set which differ from ASCII
+const NEW_LINE: i16 = 128;
+const BACKSPACE: i16 = 129;
+
+#[derive(Debug, Clone, Copy)]
+// Represents a memory segment of the VM
+enum Segment {
+    Constant,
+    Local,
+    Argument,
+    This,
+    That,
+    Pointer,
+    Temp,
+    // The address of a static variable, which belongs to the file of the command
+    Static,
+}
+
+#[derive(Debug, Clone)]
+// Represents a VM command, with labels resolved to command indices
+enum Command {
+    Push(Segment, usize),
+    Pop(Segment, usize),
+    Arithmetic(&'static str),
+    Label,
+    Goto(usize),
+    IfGoto(usize),
+    Function(usize),
+    Call(String, usize),
+    Return,
+}
+
+// The start of each function of a program, with the name of the function
+type Functions = Vec<(usize, String)>;
+
+static ARITHMETIC: &[&str] = &["add", "sub", "neg", "eq", "gt", "lt", "and", "or", "not"];
+
+// Parses the VM files of a program into a list of commands along with the start of each function
+fn load(files: &[(&str, &str)]) -> Result<(Vec<Command>, Functions), String> {
+    let mut commands = vec![];
+    let mut functions = vec![];
+    let mut static_base = STATIC;
+    for (file, contents) in files {
+        let lines = contents
+            .lines()
+            .enumerate()
+            .map(|(i, x)| (i + 1, x.split("//").next().unwrap_or_default()))
+            .map(|(i, x)| (i, x.split_whitespace().collect::<Vec<_>>()))
+            .filter(|(_, x)| !x.is_empty())
+            .collect::<Vec<_>>();
+        let error = |line: usize, message: String| format!("{}:{}: {}", file, line, message);
+
+        // labels are local to their function, so they are looked up with the function name
+        let mut labels = HashMap::new();
+        let mut function = "";
+        for (i, (line, words)) in lines.iter().enumerate() {
+            match words[..] {
+                ["function", name, ..] => function = name,
+                ["label", label] => {
+                    let key = format!("{}${}", function, label);
+                    if labels.insert(key, commands.len() + i).is_some() {
+                        return Err(error(*line, format!("Label '{}' is defined twice", label)));
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let mut statics = 0;
+        let mut function = "";
+        for (line, words) in &lines {
+            let number = |word: &str| {
+                word.parse::<usize>()
+                    .map_err(|_| error(*line, format!("Expected a number, found '{}'", word)))
+            };
+            let label = |function: &str, label: &str| {
+                labels
+                    .get(&format!("{}${}", function, label))
+                    .copied()
+                    .ok_or(error(*line, format!("Undefined label '{}'", label)))
+            };
+            let segment = |name: &str, index: usize| {
+                let (segment, limit) = match name {
+                    "constant" => (Segment::Constant, 32768),
+                    "local" => (Segment::Local, usize::MAX),
+                    "argument" => (Segment::Argument, usize::MAX),
+                    "this" => (Segment::This, usize::MAX),
+                    "that" => (Segment::That, usize::MAX),
+                    "pointer" => (Segment::Pointer, 2),
+                    "temp" => (Segment::Temp, 8),
+                    "static" => (Segment::Static, 240),
+                    _ => return Err(error(*line, format!("Unknown segment '{}'", name))),
+                };
+                if index >= limit {
+                    return Err(error(*line, format!("Index {} is out of range", index)));
+                }
+                Ok(segment)
+            };
+            let command = match words[..] {
+                ["push", s, i] => {
+                    let (i, s) = (number(i)?, segment(s, number(i)?)?);
+                    match s {
+                        Segment::Static => {
+                            statics = statics.max(i + 1);
+                            Command::Push(s, static_base + i)
+                        }
+                        _ => Command::Push(s, i),
+                    }
+                }
+                ["pop", s, i] => {
+                    let (i, s) = (number(i)?, segment(s, number(i)?)?);
+                    match s {
+                        Segment::Constant => {
+                            return Err(error(*line, "Cannot pop to constant".to_string()))
+                        }
+                        Segment::Static => {
+                            statics = statics.max(i + 1);
+                            Command::Pop(s, static_base + i)
+                        }
+                        _ => Command::Pop(s, i),
+                    }
+                }
+                [op] if ARITHMETIC.contains(&op) => {
+                    Command::Arithmetic(ARITHMETIC.iter().find(|x| **x == op).unwrap())
+                }
+                ["label", _] => Command::Label,
+                ["goto", l] => Command::Goto(label(function, l)?),
+                ["if-goto", l] => Command::IfGoto(label(function, l)?),
+                ["function", name, n] => {
+                    function = name;
+                    functions.push((commands.len(), name.to_string()));
+                    Command::Function(number(n)?)
+                }
+                ["call", name, n] => Command::Call(name.to_string(), number(n)?),
+                ["return"] => Command::Return,
+                _ => {
+                    return Err(error(
+                        *line,
+                        format!("Unknown command '{}'", words.join(" ")),
+                    ))
+                }
+            };
+            commands.push(command);
+        }
+        static_base += statics;
+        if static_base > STACK {
+            return Err(format!("{}: Too many static variables", file));
+        }
+    }
+    Ok((commands, functions))
+}
+
+// Runs the VM code of a program on an emulated Hack RAM, starting with Sys.init if the program
+// defines it and Main.main otherwise. The OS classes are built in, unless the program defines
+// them itself: Output prints to the output, Keyboard reads from the input and the Screen is left
+// out. The program ends when its first function returns or it calls Sys.halt.
+pub fn run_vm(
+    files: &[(&str, &str)],
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<(), String> {
+    let (commands, functions) = load(files)?;
+    let entry = ["Sys.init", "Main.main"]
+        .into_iter()
+        .find(|x| functions.iter().any(|(_, name)| name == x))
+        .ok_or("The program has no Sys.init or Main.main function".to_string())?;
+    let mut vm = Vm {
+        ram: vec![0; RAM_SIZE],
+        addresses: functions
+            .iter()
+            .map(|(start, name)| (name.clone(), *start))
+            .collect(),
+        commands,
+        functions,
+        frames: vec![],
+        free: vec![(HEAP, SCREEN - HEAP)],
+        allocated: HashMap::new(),
+        input,
+        output,
+        halted: false,
+    };
+    vm.ram[SP] = STACK as i16;
+    let res = vm.run(entry);
+    vm.output.flush().map_err(|e| e.to_string())?;
+    res
+}
+
+// Represents the state of a running VM program
+struct Vm<'a> {
+    ram: Vec<i16>,
+    commands: Vec<Command>,
+    functions: Functions,
+    addresses: HashMap<String, usize>,
+    // The return addresses of the functions being run
+    frames: Vec<usize>,
+    // The free blocks of the heap as (address, size) pairs, and the size of each allocated block
+    free: Vec<(usize, usize)>,
+    allocated: HashMap<usize, usize>,
+    input: &'a mut dyn BufRead,
+    output: &'a mut dyn Write,
+    halted: bool,
+}
+
+impl Vm<'_> {
+    // Returns the name of the function a command belongs to
+    fn function_at(&self, pc: usize) -> &str {
+        self.functions
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= pc)
+            .map(|(_, name)| name.as_str())
+            .unwrap_or_default()
+    }
+
+    // Returns an address of the RAM, checking it is in range
+    fn address(&self, address: i64) -> Result<usize, String> {
+        if address < 0 || address as usize >= RAM_SIZE {
+            return Err(format!("Address {} is out of range", address));
+        }
+        Ok(address as usize)
+    }
+
+    fn push(&mut self, value: i16) -> Result<(), String> {
+        let sp = self.ram[SP] as usize;
+        if sp >= HEAP {
+            return Err("Stack overflow".to_string());
+        }
+        self.ram[sp] = value;
+        self.ram[SP] += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<i16, String> {
+        let sp = self.ram[SP] as usize;
+        if sp <= STACK {
+            return Err("Stack underflow".to_string());
+        }
+        self.ram[SP] -= 1;
+        Ok(self.ram[sp - 1])
+    }
+
+    // Returns the RAM address of an entry of a segment
+    fn segment_address(&self, segment: Segment, index: usize) -> Result<usize, String> {
+        let base = |pointer: usize| self.address(self.ram[pointer] as u16 as i64 + index as i64);
+        match segment {
+            Segment::Local => base(LCL),
+            Segment::Argument => base(ARG),
+            Segment::This => base(THIS),
+            Segment::That => base(THAT),
+            Segment::Pointer => Ok(THIS + index),
+            Segment::Temp => Ok(TEMP + index),
+            Segment::Static => Ok(index),
+            Segment::Constant => Err("Constant has no address".to_string()),
+        }
+    }
+
+    // Runs the program from its entry function until it returns
+    fn run(&mut self, entry: &str) -> Result<(), String> {
+        let mut pc = self.addresses[entry];
+        self.frames.push(usize::MAX);
+        // the frame of the entry function, whose saved pointers are never restored
+        for _ in 0..5 {
+            self.push(0)?;
+        }
+        self.ram[ARG] = self.ram[SP] - 5;
+        self.ram[LCL] = self.ram[SP];
+        while !self.halted && pc != usize::MAX {
+            pc = self
+                .step(pc)
+                .map_err(|e| format!("{}: {}", self.function_at(pc), e))?;
+        }
+        Ok(())
+    }
+
+    // Runs a command, returning the index of the next command
+    fn step(&mut self, pc: usize) -> Result<usize, String> {
+        match self.commands[pc].clone() {
+            Command::Push(Segment::Constant, value) => self.push(value as i16)?,
+            Command::Push(segment, index) => {
+                let address = self.segment_address(segment, index)?;
+                self.push(self.ram[address])?;
+            }
+            Command::Pop(segment, index) => {
+                let address = self.segment_address(segment, index)?;
+                self.ram[address] = self.pop()?;
+            }
+            Command::Arithmetic(op) => {
+                let y = self.pop()?;
+                let value = match op {
+                    "neg" => y.wrapping_neg(),
+                    "not" => !y,
+                    _ => {
+                        let x = self.pop()?;
+                        match op {
+                            "add" => x.wrapping_add(y),
+                            "sub" => x.wrapping_sub(y),
+                            "and" => x & y,
+                            "or" => x | y,
+                            "eq" => -((x == y) as i16),
+                            "gt" => -((x > y) as i16),
+                            _ => -((x < y) as i16),
+                        }
+                    }
+                };
+                self.push(value)?;
+            }
+            Command::Label => (),
+            Command::Goto(target) => return Ok(target),
+            Command::IfGoto(target) => {
+                if self.pop()? != 0 {
+                    return Ok(target);
+                }
+            }
+            Command::Function(locals) => {
+                for _ in 0..locals {
+                    self.push(0)?;
+                }
+            }
+            Command::Call(name, args) => {
+                return self.call(&name, args, pc + 1);
+            }
+            Command::Return => {
+                let frame = self.ram[LCL] as usize;
+                let value = self.pop()?;
+                let arg = self.ram[ARG] as usize;
+                self.ram[arg] = value;
+                self.ram[SP] = arg as i16 + 1;
+                for (i, pointer) in [THAT, THIS, ARG, LCL].into_iter().enumerate() {
+                    self.ram[pointer] = self.ram[frame - 1 - i];
+                }
+                return Ok(self.frames.pop().unwrap_or(usize::MAX));
+            }
+        }
+        Ok(pc + 1)
+    }
+
+    // Calls a function of the program, or a built-in OS function if the program doesn't define
+    // it, returning the index of the next command
+    fn call(&mut self, name: &str, args: usize, return_pc: usize) -> Result<usize, String> {
+        if let Some(&start) = self.addresses.get(name) {
+            if self.ram[SP] as usize + 5 >= HEAP {
+                return Err("Stack overflow".to_string());
+            }
+            self.push(0)?;
+            for pointer in [LCL, ARG, THIS, THAT] {
+                self.push(self.ram[pointer])?;
+            }
+            self.ram[ARG] = self.ram[SP] - 5 - args as i16;
+            self.ram[LCL] = self.ram[SP];
+            self.frames.push(return_pc);
+            return Ok(start);
+        }
+        let mut values = vec![0; args];
+        for value in values.iter_mut().rev() {
+            *value = self.pop()?;
+        }
+        let res = self.builtin(name, &values)?;
+        self.push(res)?;
+        Ok(return_pc)
+    }
+
+    // Returns the character of a character code of the Jack character set
+    fn char_text(c: i16) -> String {
+        match c {
+            NEW_LINE => "\n".to_string(),
+            BACKSPACE => "\u{8}".to_string(),
+            c => char::from_u32(c as u32).unwrap_or('?').to_string(),
+        }
+    }
+
+    fn write(&mut self, text: &str) -> Result<(), String> {
+        self.output
+            .write_all(text.as_bytes())
+            .map_err(|e| e.to_string())
+    }
+
+    // Reads a line of the input, without the line break
+    fn read_line(&mut self) -> Result<String, String> {
+        self.output.flush().map_err(|e| e.to_string())?;
+        let mut line = String::new();
+        match self.input.read_line(&mut line) {
+            Ok(0) => Err("Unexpected end of input".to_string()),
+            Ok(_) => Ok(line.trim_end_matches(['\n', '\r']).to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    // Allocates a block of the heap with the first free block large enough
+    fn alloc(&mut self, size: i16) -> Result<i16, String> {
+        if size <= 0 {
+            return Err(format!("Cannot allocate {} words", size));
+        }
+        let size = size as usize;
+        let i = self
+            .free
+            .iter()
+            .position(|(_, free)| *free >= size)
+            .ok_or("Heap overflow".to_string())?;
+        let (address, free) = self.free[i];
+        if free == size {
+            self.free.remove(i);
+        } else {
+            self.free[i] = (address + size, free - size);
+        }
+        self.allocated.insert(address, size);
+        Ok(address as i16)
+    }
+
+    fn dealloc(&mut self, address: i16) -> Result<(), String> {
+        let address = address as u16 as usize;
+        let size = self
+            .allocated
+            .remove(&address)
+            .ok_or(format!("Address {} was not allocated", address))?;
+        self.free.push((address, size));
+        Ok(())
+    }
+
+    // Returns the characters of a String object, which is laid out as its capacity, its length
+    // and then its characters
+    fn string(&self, address: i16) -> Result<Vec<i16>, String> {
+        let start = self.address(address as u16 as i64)?;
+        let length = self.ram[self.address(start as i64 + 1)?];
+        let end = self.address(start as i64 + 2 + length as i64)?;
+        Ok(self.ram[start + 2..end].to_vec())
+    }
+
+    // Creates a String object with the given characters
+    fn new_string(&mut self, chars: &[i16]) -> Result<i16, String> {
+        let capacity = chars.len().max(1);
+        let address = self.alloc(capacity as i16 + 2)? as usize;
+        self.ram[address] = capacity as i16;
+        self.ram[address + 1] = chars.len() as i16;
+        self.ram[address + 2..address + 2 + chars.len()].copy_from_slice(chars);
+        Ok(address as i16)
+    }
+
+    // Returns the address of a character of a String object, checking it is in range
+    fn char_address(&self, string: i16, j: i16, capacity: bool) -> Result<usize, String> {
+        let start = self.address(string as u16 as i64)?;
+        let limit = self.ram[start + if capacity { 0 } else { 1 }];
+        if j < 0 || j >= limit {
+            return Err(format!("String index {} is out of range", j));
+        }
+        self.address(start as i64 + 2 + j as i64)
+    }
+
+    // Runs a built-in OS function with its arguments, returning its return value
+    fn builtin(&mut self, name: &str, args: &[i16]) -> Result<i16, String> {
+        let arg = |i: usize| args.get(i).copied().unwrap_or_default();
+        let (x, y) = (arg(0), arg(1));
+        Ok(match name {
+            "Math.init" | "Memory.init" | "Output.init" | "Screen.init" | "Keyboard.init"
+            | "Output.moveCursor" | "Sys.wait" => 0,
+            _ if name.starts_with("Screen.") => 0,
+            "Math.abs" => x.wrapping_abs(),
+            "Math.multiply" => x.wrapping_mul(y),
+            "Math.divide" => {
+                if y == 0 {
+                    return Err("Division by zero".to_string());
+                }
+                x.wrapping_div(y)
+            }
+            "Math.min" => x.min(y),
+            "Math.max" => x.max(y),
+            "Math.sqrt" => {
+                if x < 0 {
+                    return Err("Cannot compute the square root of a negative number".to_string());
+                }
+                (x as f64).sqrt() as i16
+            }
+            "Memory.peek" => self.ram[self.address(x as u16 as i64)?],
+            "Memory.poke" => {
+                let address = self.address(x as u16 as i64)?;
+                self.ram[address] = y;
+                0
+            }
+            "Memory.alloc" | "Array.new" => self.alloc(x)?,
+            "Memory.deAlloc" | "Array.dispose" | "String.dispose" => {
+                self.dealloc(x)?;
+                0
+            }
+            "String.new" => {
+                if x < 0 {
+                    return Err(format!("Cannot create a String of length {}", x));
+                }
+                let address = self.alloc(x.max(1) + 2)?;
+                self.ram[address as usize] = x;
+                self.ram[address as usize + 1] = 0;
+                address
+            }
+            "String.length" => self.string(x)?.len() as i16,
+            "String.charAt" => self.ram[self.char_address(x, y, false)?],
+            "String.setCharAt" => {
+                let address = self.char_address(x, y, false)?;
+                self.ram[address] = arg(2);
+                0
+            }
+            "String.appendChar" => {
+                let length = self.string(x)?.len() as i16;
+                let address = self
+                    .char_address(x, length, true)
+                    .map_err(|_| "String is full".to_string())?;
+                self.ram[address] = y;
+                self.ram[x as u16 as usize + 1] += 1;
+                x
+            }
+            "String.eraseLastChar" => {
+                if self.string(x)?.is_empty() {
+                    return Err("String is empty".to_string());
+                }
+                self.ram[x as u16 as usize + 1] -= 1;
+                0
+            }
+            "String.intValue" => {
+                let text = self
+                    .string(x)?
+                    .iter()
+                    .map(|c| Vm::char_text(*c))
+                    .collect::<String>();
+                parse_int(&text)
+            }
+            "String.setInt" => {
+                let chars = y.to_string().bytes().map(|x| x as i16).collect::<Vec<_>>();
+                if chars.len() as i16 > self.ram[x as u16 as usize] {
+                    return Err("String is full".to_string());
+                }
+                let start = x as u16 as usize;
+                self.ram[start + 1] = chars.len() as i16;
+                self.ram[start + 2..start + 2 + chars.len()].copy_from_slice(&chars);
+                0
+            }
+            "String.backSpace" => BACKSPACE,
+            "String.doubleQuote" => '"' as i16,
+            "String.newLine" => NEW_LINE,
+            "Output.printChar" => {
+                self.write(&Vm::char_text(x))?;
+                0
+            }
+            "Output.printString" => {
+                let text = self
+                    .string(x)?
+                    .iter()
+                    .map(|c| Vm::char_text(*c))
+                    .collect::<String>();
+                self.write(&text)?;
+                0
+            }
+            "Output.printInt" => {
+                self.write(&x.to_string())?;
+                0
+            }
+            "Output.println" => {
+                self.write("\n")?;
+                0
+            }
+            "Output.backSpace" => {
+                self.write(&Vm::char_text(BACKSPACE))?;
+                0
+            }
+            // there is no keyboard to hold a key down
+            "Keyboard.keyPressed" => 0,
+            "Keyboard.readChar" => {
+                let mut byte = [0];
+                match self.input.read(&mut byte) {
+                    Ok(0) => return Err("Unexpected end of input".to_string()),
+                    Ok(_) if byte[0] == b'\n' => NEW_LINE,
+                    Ok(_) => byte[0] as i16,
+                    Err(e) => return Err(e.to_string()),
+                }
+            }
+            "Keyboard.readLine" | "Keyboard.readInt" => {
+                let message = self
+                    .string(x)?
+                    .iter()
+                    .map(|c| Vm::char_text(*c))
+                    .collect::<String>();
+                self.write(&message)?;
+                let line = self.read_line()?;
+                if name == "Keyboard.readInt" {
+                    parse_int(&line)
+                } else {
+                    let chars = line.chars().map(|c| c as i16).collect::<Vec<_>>();
+                    self.new_string(&chars)?
+                }
+            }
+            "Sys.halt" => {
+                self.halted = true;
+                0
+            }
+            "Sys.error" => return Err(format!("Sys.error({})", x)),
+            "Sys.init" => return Err("Sys.init can only start the program".to_string()),
+            _ => return Err(format!("Undefined function '{}'", name)),
+        })
+    }
+}
+
+// Returns the value of the leading integer of a text, like String.intValue
+fn parse_int(text: &str) -> i16 {
+    let text = text.trim_start();
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(rest) => (-1i16, rest),
+        None => (1, text),
+    };
+    digits
+        .chars()
+        .map_while(|c| c.to_digit(10))
+        .fold(0i16, |n, d| n.wrapping_mul(10).wrapping_add(d as i16))
+        .wrapping_mul(sign)
+}
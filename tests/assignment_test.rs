@@ -0,0 +1,61 @@
+extern crate lib;
+
+use lib::internal::{check_assignments, parse};
+use lib::Severity;
+
+static SQUARE_GAME: &str = include_str!("./samples/Square/SquareGame.jack");
+
+static READS: &str = "class Main {
+    function int main(boolean b) {
+        var int x, y, z, w;
+        var Array a;
+        if (b) {
+            let x = 1;
+            let y = 1;
+        } else {
+            let x = 2;
+            let z = x;
+        }
+        while (b) {
+            let w = 0;
+        }
+        let a[x] = y + z + w;
+        if (b) {
+            return 0;
+        } else {
+            let w = 1;
+        }
+        return w;
+    }
+}
+";
+
+#[test]
+fn check_assignments_test() {
+    // the sample relies on the VM zeroing key
+    let names = check_assignments(&parse(SQUARE_GAME).unwrap())
+        .iter()
+        .map(|x| x.span.text(SQUARE_GAME))
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["key"]);
+
+    let diagnostics = check_assignments(&parse(READS).unwrap());
+    let messages = diagnostics
+        .iter()
+        .map(|x| x.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        [
+            "Local variable 'a' may be read before it is assigned",
+            "Local variable 'y' may be read before it is assigned",
+            "Local variable 'z' may be read before it is assigned",
+            "Local variable 'w' may be read before it is assigned",
+        ]
+    );
+    let y = &diagnostics[1];
+    assert_eq!(y.severity, Severity::Warning);
+    assert_eq!(y.span.text(READS), "y");
+    assert_eq!(y.span.start, READS.find("y + z").unwrap());
+    assert_eq!(y.notes[0].span.unwrap().start, READS.find("y, z").unwrap());
+}
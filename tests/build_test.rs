@@ -0,0 +1,284 @@
+extern crate lib;
+
+mod common;
+
+use common::samples;
+use lib::internal::{build_in, codewrite, jack_files_in, parse, BuildOptions, Json, Rebuild};
+use lib::{FileSystem, SourceFiles};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+// Returns the sample programs in memory, and the paths of the classes of Square
+fn square_program() -> (SourceFiles, Vec<PathBuf>) {
+    let files = samples();
+    let sources = jack_files_in(&files, Path::new("tests/samples/Square")).unwrap();
+    (files, sources)
+}
+
+#[test]
+fn incremental_build_test() {
+    let (files, sources) = square_program();
+    let out = PathBuf::from("out");
+    let mut options = BuildOptions::new(PathBuf::from(".jack-cache"));
+    options.out_dir = Some(out.clone());
+    let reasons = || {
+        build_in(&files, &sources, &options)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.reason)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        reasons(),
+        [Rebuild::NotCached, Rebuild::NotCached, Rebuild::NotCached]
+    );
+    let square = parse(&files.get(&sources[1]).unwrap()).unwrap();
+    let game = parse(&files.get(&sources[2]).unwrap()).unwrap();
+    assert_eq!(
+        files.get(out.join("SquareGame.vm")).unwrap(),
+        codewrite(&game, &[&square]).unwrap()
+    );
+    assert_eq!(reasons(), [Rebuild::Fresh, Rebuild::Fresh, Rebuild::Fresh]);
+
+    // a change to a subroutine body leaves the classes using it alone
+    let body = files
+        .get(&sources[1])
+        .unwrap()
+        .replace("let size = Asize;", "let size = Asize + 0;");
+    files.insert(&sources[1], body.clone());
+    assert_eq!(
+        reasons(),
+        [Rebuild::Fresh, Rebuild::SourceChanged, Rebuild::Fresh]
+    );
+
+    // a change to a signature rebuilds them
+    files.insert(
+        &sources[1],
+        body.replace("method void moveUp()", "method void moveUp(int n)"),
+    );
+    files.insert(out.join("Main.vm"), "");
+    assert_eq!(
+        reasons(),
+        [
+            Rebuild::Fresh,
+            Rebuild::SourceChanged,
+            Rebuild::InterfaceChanged("Square".to_string())
+        ]
+    );
+    // outputs are written again from the cache
+    assert!(files
+        .get(out.join("Main.vm"))
+        .unwrap()
+        .starts_with("function Main.main"));
+    // nothing is written outside of the in-memory files
+    assert!(!Path::new(".jack-cache").exists() && !out.exists());
+}
+
+#[test]
+fn parallel_build_test() {
+    let files = SourceFiles::new();
+    let mut sources = vec![];
+    for i in 0..24 {
+        let path = PathBuf::from(format!("C{:02}.jack", i));
+        let source = format!(
+            "class C{i:02} {{
+    function int f(int x) {{
+        if (x > {i}) {{ return C{:02}.f(x - 1); }}
+        return x * {i};
+    }}
+}}
+",
+            (i + 1) % 24
+        );
+        files.insert(&path, source);
+        sources.push(path);
+    }
+    let outputs = |jobs: usize| {
+        let mut options = BuildOptions::new(PathBuf::from(format!("cache{}", jobs)));
+        options.out_dir = Some(PathBuf::from(format!("out{}", jobs)));
+        options.jobs = jobs;
+        build_in(&files, &sources, &options)
+            .unwrap()
+            .iter()
+            .map(|x| files.get(&x.output).unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(outputs(1), outputs(8));
+
+    // errors are reported in the order of the files
+    for i in [3, 17, 9] {
+        files.insert(&sources[i], "class Broken {");
+    }
+    let mut options = BuildOptions::new(PathBuf::from("cache8"));
+    options.jobs = 8;
+    let errors = build_in(&files, &sources, &options).unwrap_err();
+    let files = errors
+        .lines()
+        .map(|x| x.split(':').next().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(files, [3, 9, 17].map(|i| sources[i].display().to_string()));
+}
+
+#[test]
+// Test that the outputs and cache entries of deleted sources are removed
+fn removed_source_build_test() {
+    let (files, sources) = square_program();
+    let cache = PathBuf::from(".jack-cache");
+    let mut options = BuildOptions::new(cache.clone());
+    options.out_dir = Some(PathBuf::from("out"));
+    options.codegen.source_map = true;
+    build_in(&files, &sources, &options).unwrap();
+    assert_eq!(files.read_dir(&cache).unwrap().len(), 3);
+    let entry = files.read(&files.read_dir(&cache).unwrap()[0]).unwrap();
+    let options_json = Json::parse(&entry)
+        .unwrap()
+        .get("options")
+        .cloned()
+        .unwrap();
+    assert_eq!(options_json.get("sourceMap"), Some(&Json::Bool(true)));
+    assert_eq!(
+        options_json.get("optLevel").and_then(|x| x.as_usize()),
+        Some(0)
+    );
+
+    // a source which is left out of the build but still exists keeps its outputs
+    build_in(&files, &sources[1..], &options).unwrap();
+    assert!(files.get("out/Main.vm").is_some());
+    assert_eq!(files.read_dir(&cache).unwrap().len(), 3);
+
+    files.remove_file(&sources[0]).unwrap();
+    let steps = build_in(&files, &sources[1..], &options).unwrap();
+    assert!(steps.iter().all(|x| x.reason == Rebuild::Fresh));
+    assert!(files.get("out/Main.vm").is_none() && files.get("out/Main.vm.map").is_none());
+    assert!(files.get("out/Square.vm").is_some() && files.get("out/Square.vm.map").is_some());
+    assert_eq!(files.read_dir(&cache).unwrap().len(), 2);
+
+    options.codegen.opt_level = 1;
+    let steps = build_in(&files, &sources[1..], &options).unwrap();
+    assert!(steps.iter().all(|x| x.reason == Rebuild::OptionsChanged));
+}
+
+// Represents in-memory files which record how many threads were writing at the same time
+struct ConcurrentWrites {
+    files: SourceFiles,
+    active: AtomicUsize,
+    max_active: AtomicUsize,
+}
+
+impl FileSystem for ConcurrentWrites {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        self.files.read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_active.fetch_max(active, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(20));
+        self.active.fetch_sub(1, Ordering::SeqCst);
+        self.files.write(path, contents)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.files.remove_file(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.files.create_dir_all(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.files.is_dir(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        self.files.read_dir(path)
+    }
+}
+
+#[test]
+// Test that the files of a build are compiled at the same time rather than one after another
+fn concurrent_build_test() {
+    let (files, sources) = square_program();
+    let files = ConcurrentWrites {
+        files,
+        active: AtomicUsize::new(0),
+        max_active: AtomicUsize::new(0),
+    };
+    let mut options = BuildOptions::new(PathBuf::from(".jack-cache"));
+    options.jobs = 3;
+    build_in(&files, &sources, &options).unwrap();
+    assert!(files.max_active.load(Ordering::SeqCst) > 1);
+}
+
+#[test]
+// Test builds of a project which only exists in memory, edited between builds
+fn in_memory_build_test() {
+    let root = PathBuf::from("in-memory-project");
+    let files = SourceFiles::new();
+    files.insert(
+        root.join("Main.jack"),
+        "class Main {
+    function void main() {
+        do Output.printInt(Util.double(21));
+        return;
+    }
+}
+",
+    );
+    files.insert(
+        root.join("Util.jack"),
+        "class Util {
+    function int double(int x) {
+        return x + x;
+    }
+}
+",
+    );
+    let mut options = BuildOptions::new(root.join(".jack-cache"));
+    options.out_dir = Some(root.join("build"));
+    let build = || {
+        let sources = jack_files_in(&files, &root).unwrap();
+        build_in(&files, &sources, &options)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.reason)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(build(), [Rebuild::NotCached, Rebuild::NotCached]);
+    assert!(files
+        .get(root.join("build/Main.vm"))
+        .unwrap()
+        .contains("call Util.double 1"));
+    assert_eq!(build(), [Rebuild::Fresh, Rebuild::Fresh]);
+
+    let util = files.get(root.join("Util.jack")).unwrap();
+    files.insert(root.join("Util.jack"), util.replace("x + x", "x * 2"));
+    assert_eq!(build(), [Rebuild::Fresh, Rebuild::SourceChanged]);
+    assert!(files
+        .get(root.join("build/Util.vm"))
+        .unwrap()
+        .contains("call Math.multiply 2"));
+
+    files.insert(
+        root.join("Util.jack"),
+        util.replace("double(int x)", "double(int x, int y)"),
+    );
+    assert_eq!(
+        build(),
+        [
+            Rebuild::InterfaceChanged("Util".to_string()),
+            Rebuild::SourceChanged
+        ]
+    );
+    files.insert(root.join("Extra.jack"), "class Extra { }");
+    assert_eq!(
+        build(),
+        [Rebuild::NotCached, Rebuild::Fresh, Rebuild::Fresh]
+    );
+    assert!(!root.exists());
+}
@@ -0,0 +1,381 @@
+extern crate lib;
+
+use lib::internal::Json;
+use lib::{Diagnostic, Span};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+static MAIN: &str = "class Main {
+    function void main() {
+        var String name;
+        let name = Keyboard.readLine(\"Name? \");
+        do Output.printString(\"Hello, \");
+        do Output.printString(name);
+        do Output.println();
+        do Output.printInt(Main.fact(Keyboard.readInt(\"n? \")));
+        return;
+    }
+
+    function int fact(int n) {
+        if (n < 2) {
+            return 1;
+        }
+        return n * Main.fact(n - 1);
+    }
+}
+";
+
+// Runs main with the given arguments and stdin
+fn main(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_main"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+// Creates an empty temporary directory for a test
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("jack_cli_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Return true if the two strings are equal ignoring whitespace, case and newlines
+fn text_eq(str_1: &str, str_2: &str) -> bool {
+    let f = |s: &str| s.replace(['\n', '\r', ' '], "").to_lowercase();
+    f(str_1) == f(str_2)
+}
+
+#[test]
+// Test that tokenize and parse write the XML of each file to the output directory
+fn tokenize_parse_test() {
+    let dir = temp_dir("xml");
+    let samples = Path::new("tests/samples/Square");
+    let out = dir.to_str().unwrap();
+    for command in ["tokenize", "parse"] {
+        let output = main(&[command, "-o", out, samples.to_str().unwrap()], "");
+        assert!(output.status.success());
+    }
+    for name in ["Main", "Square", "SquareGame"] {
+        for file in [format!("{}T.xml", name), format!("{}.xml", name)] {
+            let expected = fs::read_to_string(samples.join(&file)).unwrap();
+            let actual = fs::read_to_string(dir.join(&file)).unwrap();
+            assert!(text_eq(&actual, &expected), "{}", file);
+        }
+    }
+
+    // a class read from stdin is written to stdout
+    let output = main(&["tokenize", "-"], "class A {}");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("<identifier> A </identifier>"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+// Test the exit codes of check and build for valid and invalid programs and arguments
+fn exit_code_test() {
+    let output = main(&["check", "-"], MAIN);
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = main(
+        &["check", "-"],
+        "class A { function void f() { let x = 1; return; } }",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("stdin:1:35: error[undefined-variable]: Undefined variable 'x'"));
+
+    let output = main(
+        &["build", "-"],
+        "class A { function void f() { let x = ; } }",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("stdin:1:39: error[syntax-error]:"));
+
+    let output = main(&["build", "-"], MAIN);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("function Main.main 1\n"));
+
+    // -O1 reports how many commands it removed
+    let output = main(&["build", "-O1", "-"], MAIN);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Optimized "));
+
+    for args in [
+        &["build", "missing.jack"][..],
+        &["check", "-O1", "-"],
+        &["check", "-o", "out", "-"],
+        &["frob"],
+    ] {
+        let output = main(args, "");
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+    }
+}
+
+#[test]
+// Test that run compiles a program and runs it with stdin as the keyboard
+fn run_test() {
+    let dir = temp_dir("run");
+    fs::write(dir.join("Main.jack"), MAIN).unwrap();
+    let output = main(&["run", dir.to_str().unwrap()], "Ada\n7\n");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Name? Hello, Ada\nn? 5040"
+    );
+    // run doesn't write any files
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    fs::write(dir.join("Main.jack"), MAIN.replace("n - 1", "n / 0")).unwrap();
+    let output = main(&["run", dir.to_str().unwrap()], "Ada\n7\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "error: Main.fact: Division by zero\n"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+// Test that --message-format=json prints one JSON object per diagnostic
+fn message_format_test() {
+    let source = "class A {
+    field int x;
+    field int x;
+    method void f() { let y = 1; return; }
+}
+";
+    let output = main(&["check", "--message-format=json", "-"], source);
+    assert_eq!(output.status.code(), Some(1));
+    let lines = json_diagnostics(&output.stdout);
+    let member = |i: usize, key: &str| lines[i].get(key).cloned().unwrap();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(member(0, "code").as_str(), Some("duplicate-symbol"));
+    assert_eq!(member(0, "file").as_str(), Some("stdin"));
+    assert_eq!(member(0, "line").as_usize(), Some(3));
+    let notes = member(0, "notes");
+    let note = &notes.as_array().unwrap()[0];
+    assert_eq!(note.get("line").and_then(|x| x.as_usize()), Some(2));
+    assert_eq!(member(1, "severity").as_str(), Some("error"));
+    assert_eq!(member(1, "column").as_usize(), Some(27));
+    assert_eq!(member(1, "endColumn").as_usize(), Some(28));
+
+    // build errors are diagnostics too, on stderr
+    let output = main(&["build", "--message-format", "json", "-"], "class A {");
+    let errors = json_diagnostics(&output.stderr);
+    assert_eq!(
+        errors[0].get("code").and_then(|x| x.as_str()),
+        Some("syntax-error")
+    );
+
+    // suggested fixes are written with the span they replace
+    let diagnostic = Diagnostic::error("syntax-error", "Expected ';'".to_string(), Span::new(5, 5))
+        .with_suggestion("Insert ';'".to_string(), Span::new(5, 5), ";".to_string());
+    let json = diagnostic.to_json("A.jack", "let x\n");
+    assert_eq!(
+        json.get("fixes").unwrap().to_string(),
+        r#"[{"message":"Insert ';'","line":1,"column":6,"endLine":1,"endColumn":6,"replacement":";"}]"#
+    );
+}
+
+// The members of every JSON diagnostic, in order
+static DIAGNOSTIC_MEMBERS: [&str; 10] = [
+    "file",
+    "severity",
+    "code",
+    "message",
+    "line",
+    "column",
+    "endLine",
+    "endColumn",
+    "notes",
+    "fixes",
+];
+
+// Parses the JSON diagnostics of an output, one per line, checking that each has the members of
+// a diagnostic
+fn json_diagnostics(output: &[u8]) -> Vec<Json> {
+    String::from_utf8_lossy(output)
+        .lines()
+        .map(|x| {
+            let json = Json::parse(x).unwrap();
+            let Json::Object(members) = &json else {
+                panic!("not an object: {}", x)
+            };
+            let keys = members.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>();
+            assert_eq!(keys, DIAGNOSTIC_MEMBERS, "{}", x);
+            json
+        })
+        .collect()
+}
+
+#[test]
+// Test that build errors which aren't in the source of a file are written as diagnostics too
+fn build_error_json_test() {
+    let dir = temp_dir("build_error");
+    fs::write(dir.join("Main.jack"), MAIN).unwrap();
+    fs::write(dir.join("Broken.jack"), "class Broken {").unwrap();
+    let output = main(
+        &["build", "--message-format=json", dir.to_str().unwrap()],
+        "",
+    );
+    let errors = json_diagnostics(&output.stderr);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].get("code").and_then(|x| x.as_str()),
+        Some("syntax-error")
+    );
+
+    // the output directory is a file, so it can't be created
+    fs::remove_file(dir.join("Broken.jack")).unwrap();
+    let out_dir = dir.join("Main.jack").join("out");
+    let output = main(
+        &[
+            "build",
+            "--message-format=json",
+            "--out-dir",
+            out_dir.to_str().unwrap(),
+            dir.to_str().unwrap(),
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    let errors = json_diagnostics(&output.stderr);
+    assert_eq!(errors.len(), 1);
+    let member = |key: &str| errors[0].get(key).cloned().unwrap();
+    assert_eq!(member("code").as_str(), Some("build-error"));
+    assert_eq!(member("file").as_str(), out_dir.to_str());
+    assert_eq!(member("line"), Json::Null);
+    assert_eq!(member("notes"), Json::Array(vec![]));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+// Test that --fix rewrites files with the suggested fixes before checking them
+fn fix_files_test() {
+    let dir = temp_dir("fix");
+    let file = dir.join("A.jack");
+    fs::write(&file, "class A { function void f() { return } }").unwrap();
+    let output = main(&["check", "--fix", file.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        "class A { function void f() { return; } }"
+    );
+    assert_eq!(main(&["check", "--fix", "-"], "").status.code(), Some(2));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// Waits until a condition holds, checking it every few milliseconds for at most ten seconds
+fn wait_until(mut condition: impl FnMut() -> bool) -> bool {
+    for _ in 0..500 {
+        if condition() {
+            return true;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    false
+}
+
+#[test]
+// Test that build --watch builds again when a file changes, reporting the new diagnostics
+fn watch_test() {
+    let dir = temp_dir("watch");
+    let (main_file, util_file) = (dir.join("Main.jack"), dir.join("Util.jack"));
+    fs::write(&main_file, MAIN).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_main"))
+        .args(["build", "--watch", "--verbose", dir.to_str().unwrap()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stderr = std::sync::Arc::new(std::sync::Mutex::new(String::new()));
+    let mut pipe = child.stderr.take().unwrap();
+    let reader = {
+        let stderr = stderr.clone();
+        std::thread::spawn(move || {
+            let mut buffer = [0; 256];
+            while let Ok(n @ 1..) = std::io::Read::read(&mut pipe, &mut buffer) {
+                stderr
+                    .lock()
+                    .unwrap()
+                    .push_str(&String::from_utf8_lossy(&buffer[..n]));
+            }
+        })
+    };
+    let count = |text: &str| stderr.lock().unwrap().matches(text).count();
+
+    assert!(wait_until(|| count("Build succeeded") == 1));
+    assert!(dir.join("Main.vm").exists());
+
+    // an added file with an error is noticed
+    fs::write(&util_file, "class Util { function void f() { return } }").unwrap();
+    assert!(wait_until(|| count("Build failed") == 1));
+    assert_eq!(count("Util.jack:1:41: error[syntax-error]"), 1);
+
+    fs::write(&util_file, "class Util { function void f() { return; } }").unwrap();
+    assert!(wait_until(|| count("Build succeeded") == 2));
+    assert!(dir.join("Util.vm").exists());
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    reader.join().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+// Test that build writes a source map next to each .vm file when asked to
+fn source_map_test() {
+    let dir = temp_dir("source_map");
+    fs::write(dir.join("Main.jack"), MAIN).unwrap();
+    let output = main(
+        &[
+            "build",
+            "--source-map",
+            "--source-comments",
+            dir.to_str().unwrap(),
+        ],
+        "",
+    );
+    assert!(output.status.success());
+    let vm = fs::read_to_string(dir.join("Main.vm")).unwrap();
+    assert!(vm.starts_with("// function void main() {\nfunction Main.main 1\n"));
+    let source_map = Json::parse(&fs::read_to_string(dir.join("Main.vm.map")).unwrap()).unwrap();
+    let mappings = source_map.get("mappings").unwrap().as_array().unwrap();
+    // the commands of the recursive call are found from the line of the VM error
+    let line = vm.lines().position(|x| x == "call Main.fact 1").unwrap() + 1;
+    let mapping = mappings
+        .iter()
+        .find(|x| x.get("vmLine").and_then(|x| x.as_usize()) == Some(line))
+        .unwrap();
+    assert_eq!(mapping.get("line").and_then(|x| x.as_usize()), Some(8));
+    assert_eq!(
+        mapping.get("subroutine").and_then(|x| x.as_str()),
+        Some("Main.main")
+    );
+
+    // the option changes the outputs of files which are otherwise fresh
+    let output = main(&["build", "--verbose", dir.to_str().unwrap()], "");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("code generation options changed"));
+    assert!(!fs::read_to_string(dir.join("Main.vm"))
+        .unwrap()
+        .contains("//"));
+    assert_eq!(
+        main(&["build", "--source-map", "-"], "").status.code(),
+        Some(2)
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,25 @@
+// Helpers shared by the integration tests, each of which uses some of them
+#![allow(dead_code)]
+
+use lib::internal::jack_files_in;
+use lib::SourceFiles;
+use std::path::Path;
+
+// The names of the sample programs, each in a directory of tests/samples
+pub static PROGRAMS: [&str; 3] = ["ArrayTest", "ExpressionLessSquare", "Square"];
+
+// Returns the files of the sample programs and their expected token and parse trees, read into
+// memory under their paths, so the tests only use files kept in memory
+pub fn samples() -> SourceFiles {
+    SourceFiles::load("tests/samples").unwrap()
+}
+
+// Returns the sources of the .jack files of sample programs, in the order of their paths
+pub fn src_files(programs: &[&str]) -> Vec<String> {
+    let samples = samples();
+    programs
+        .iter()
+        .flat_map(|x| jack_files_in(&samples, &Path::new("tests/samples").join(x)).unwrap())
+        .map(|x| samples.get(x).unwrap())
+        .collect()
+}
@@ -0,0 +1,81 @@
+extern crate lib;
+
+use lib::internal::{complete, CompletionKind};
+
+static SQUARE: &str = include_str!("./samples/Square/Square.jack");
+
+// An unfinished class, which doesn't parse
+static GAME: &str = "class Game {
+    field Square square;
+    static int count;
+
+    method void run(int speed) {
+        var char key;
+        let key = Keyboard.
+        do square.
+        do Math.m
+        let
+";
+
+// Returns the labels of the completion candidates at the end of a marker in the source
+fn labels(source: &str, marker: &str, program: &[&str]) -> Vec<String> {
+    let offset = source.find(marker).unwrap() + marker.len();
+    complete(source, offset, program)
+        .into_iter()
+        .map(|x| x.label)
+        .collect()
+}
+
+#[test]
+fn member_completion_test() {
+    let methods = labels(GAME, "do square.", &[SQUARE]);
+    assert!(methods.contains(&"moveUp".to_string()));
+    assert!(methods.contains(&"dispose".to_string()));
+    assert!(!methods.contains(&"new".to_string()));
+
+    let functions = labels(GAME, "Keyboard.", &[]);
+    assert_eq!(
+        functions,
+        ["init", "keyPressed", "readChar", "readLine", "readInt"]
+    );
+    assert_eq!(labels(GAME, "Math.m", &[]), ["multiply", "min", "max"]);
+
+    let constructors = complete("class A { function void f() { do Square.", 40, &[SQUARE]);
+    assert_eq!(constructors.len(), 1);
+    assert_eq!(constructors[0].kind, CompletionKind::Subroutine);
+    assert_eq!(
+        constructors[0].detail,
+        "constructor Square new(int Ax, int Ay, int Asize)"
+    );
+}
+
+#[test]
+fn statement_completion_test() {
+    let statement = labels(GAME, "var char key;\n", &[]);
+    for label in [
+        "let", "do", "if", "while", "return", "var", "key", "speed", "square", "count",
+    ] {
+        assert!(statement.contains(&label.to_string()), "{}", label);
+    }
+    assert!(!statement.contains(&"this".to_string()));
+
+    let after_statement = labels(GAME, "Keyboard.\n", &[]);
+    assert!(!after_statement.contains(&"var".to_string()));
+
+    let variables = complete(GAME, GAME.len(), &[]);
+    let details = variables
+        .iter()
+        .map(|x| x.detail.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        details,
+        [
+            "int argument 1",
+            "char local 0",
+            "Square this 0",
+            "int static 0"
+        ]
+    );
+
+    assert_eq!(labels(GAME, "field Sq", &[SQUARE]), ["Square"]);
+}
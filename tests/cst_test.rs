@@ -0,0 +1,61 @@
+extern crate lib;
+
+mod common;
+
+use common::{src_files, PROGRAMS};
+use lib::internal::{parse_cst, ClassNode, LexemeKind};
+
+#[test]
+// Test that printing the concrete syntax tree reproduces the source exactly
+fn cst_lossless_test() {
+    for s in src_files(&PROGRAMS).iter() {
+        let root = parse_cst(s).unwrap();
+        assert_eq!(&root.to_string(), s);
+        for node in root.descendants() {
+            assert_eq!(node.text(), node.span().text(s));
+        }
+    }
+    let odd = "  /* lead */ class  A{ // x\r\n\tfunction void f( ) { return ; }}\n\n// tail";
+    assert_eq!(parse_cst(odd).unwrap().to_string(), odd);
+}
+
+#[test]
+// Test the typed accessors of the concrete syntax tree
+fn cst_accessors_test() {
+    let class = ClassNode::cast(parse_cst(&src_files(&PROGRAMS)[5]).unwrap()).unwrap();
+    assert_eq!(class.name().unwrap().text(), "Square");
+    let fields = class.var_decs();
+    assert_eq!(fields[0].kind(), "field");
+    assert_eq!(fields[0].type_name(), "int");
+    assert_eq!(
+        fields[0]
+            .names()
+            .iter()
+            .map(|x| x.text())
+            .collect::<Vec<_>>(),
+        ["x", "y"]
+    );
+    let subroutines = class.subroutines();
+    let constructor = &subroutines[0];
+    assert_eq!(constructor.kind(), "constructor");
+    assert_eq!(constructor.return_type(), "Square");
+    assert_eq!(constructor.name().unwrap().text(), "new");
+    assert_eq!(constructor.parameters().len(), 3);
+    let statements = constructor.statements();
+    assert_eq!(statements[0].kind(), "letStatement");
+    assert_eq!(statements.last().unwrap().kind(), "returnStatement");
+    let comments = class
+        .syntax()
+        .tokens()
+        .into_iter()
+        .filter(|x| x.kind() == LexemeKind::LineComment)
+        .count();
+    assert!(comments > 0);
+    let if_statement = subroutines
+        .iter()
+        .flat_map(|x| x.statements())
+        .find(|x| x.kind() == "ifStatement")
+        .unwrap();
+    assert_eq!(if_statement.expressions().len(), 1);
+    assert!(!if_statement.blocks()[0].is_empty());
+}
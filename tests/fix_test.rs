@@ -0,0 +1,73 @@
+extern crate lib;
+
+use lib::internal::{check_class, fix_source, parse_class};
+use lib::Diagnostic;
+
+// Returns the diagnostics of a class: its syntax error, or the errors found checking it
+fn diagnostics(contents: &str) -> Vec<Diagnostic> {
+    match parse_class(contents) {
+        Ok(class_grouping) => check_class(&class_grouping),
+        Err(diagnostic) => vec![diagnostic],
+    }
+}
+
+// Returns the contents of a function body with its first suggested fix applied
+fn first_fix(body: &str) -> String {
+    let contents = format!(
+        "class A {{\n    function void f() {{\n{}\n    }}\n}}\n",
+        body
+    );
+    let diagnostic = diagnostics(&contents).remove(0);
+    let suggestion = &diagnostic.suggestions[0];
+    let fixed = format!(
+        "{}{}{}",
+        &contents[..suggestion.span.start],
+        suggestion.replacement,
+        &contents[suggestion.span.end..]
+    );
+    fixed.lines().collect::<Vec<_>>()[2..].join("\n")
+}
+
+#[test]
+// Test the suggested fixes of common mistakes
+fn suggestions_test() {
+    assert!(first_fix("var int x;\nlet x = 1\nreturn;").starts_with("var int x;\nlet x = 1;\n"));
+    assert!(first_fix("if ((1 + 2) { }\nreturn;").starts_with("if ((1 + 2)) { }"));
+    assert!(first_fix("if (1 == 2) { }\nreturn;").starts_with("if (1 = 2) { }"));
+    assert!(first_fix("Output.println();\nreturn;").starts_with("do Output.println();"));
+    assert!(first_fix("var bool b;\nreturn;").starts_with("var boolean b;"));
+
+    // only fixes which get the parser past the error are suggested
+    let diagnostic = diagnostics("class A { function void f() { let = 1; } }").remove(0);
+    assert!(diagnostic.suggestions.is_empty());
+}
+
+#[test]
+// Test that fixing a class applies fixes until no more errors can be fixed
+fn fix_source_test() {
+    let contents = "class A {
+    function bool f() {
+        var int x
+        let x = 1
+        Output.printInt(x;
+        return x == 1;
+    }
+}
+";
+    let (fixed, count) = fix_source(contents);
+    assert_eq!(count, 6);
+    assert_eq!(
+        fixed,
+        "class A {
+    function boolean f() {
+        var int x;
+        let x = 1;
+        do Output.printInt(x);
+        return x = 1;
+    }
+}
+"
+    );
+    assert!(diagnostics(&fixed).is_empty());
+    assert_eq!(fix_source(&fixed), (fixed.clone(), 0));
+}
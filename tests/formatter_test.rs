@@ -0,0 +1,94 @@
+extern crate lib;
+
+mod common;
+
+use common::{src_files, PROGRAMS};
+use lib::internal::{format_source, lex, tokenize_into_xml, LexemeKind};
+use std::process::{Command, Stdio};
+
+// Returns the trimmed text of all comments in the source code
+fn comments(contents: &str) -> Vec<String> {
+    lex(contents)
+        .iter()
+        .filter(|x| x.kind.is_comment())
+        .map(|x| {
+            x.span
+                .text(contents)
+                .lines()
+                .next()
+                .unwrap()
+                .trim()
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+// Test that formatting keeps the tokens and comments, and is idempotent
+fn formatter_samples_test() {
+    for s in src_files(&PROGRAMS).iter() {
+        let formatted = format_source(s).unwrap();
+        assert_eq!(
+            tokenize_into_xml(&formatted).unwrap(),
+            tokenize_into_xml(s).unwrap()
+        );
+        assert_eq!(comments(&formatted), comments(s));
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+    }
+    assert!(lex(&src_files(&PROGRAMS)[0])
+        .iter()
+        .any(|x| x.kind == LexemeKind::BlockComment));
+}
+
+#[test]
+// Test the canonical layout of the formatter
+fn formatter_layout_test() {
+    let src = "class  Main{static int count ;field Array items;
+  function void main( ) { var int i,j ; var String s;
+    let i=-j+(2*count) ;   // trailing
+      if(~(i<0)){do Output.printInt(i ,j);}else{ let s[i]=s[j] ;}
+
+
+    while (i > 0) { /* spin */ let i = i - 1; }
+    return ;}}";
+    let expected = "class Main {
+    static int   count;
+    field  Array items;
+
+    function void main() {
+        var int    i, j;
+        var String s;
+        let i = -j + (2 * count); // trailing
+        if (~(i < 0)) {
+            do Output.printInt(i, j);
+        } else {
+            let s[i] = s[j];
+        }
+
+        while (i > 0) { /* spin */
+            let i = i - 1;
+        }
+        return;
+    }
+}
+";
+    assert_eq!(format_source(src).unwrap(), expected);
+}
+
+#[test]
+// Test that the check mode of jackfmt fails on unformatted input
+fn jackfmt_check_test() {
+    let check = |input: &str| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_jackfmt"))
+            .arg("--check")
+            .stdin(Stdio::piped())
+            .spawn()
+            .unwrap();
+        std::io::Write::write_all(&mut child.stdin.take().unwrap(), input.as_bytes()).unwrap();
+        child.wait().unwrap().success()
+    };
+    let square = &src_files(&PROGRAMS)[5];
+    let formatted = format_source(square).unwrap();
+    assert!(check(&formatted));
+    assert!(!check(square));
+}
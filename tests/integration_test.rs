@@ -1,60 +1,80 @@
 extern crate lib;
 
-use lib::{parse_into_xml, tokenize_into_xml};
+mod common;
 
-static src_files: [&'static str; 7] = [
-    include_str!("./samples/ArrayTest/Main.jack"),
-    include_str!("./samples/ExpressionLessSquare/Main.jack"),
-    include_str!("./samples/ExpressionLessSquare/Square.jack"),
-    include_str!("./samples/ExpressionLessSquare/SquareGame.jack"),
-    include_str!("./samples/Square/Main.jack"),
-    include_str!("./samples/Square/Square.jack"),
-    include_str!("./samples/Square/SquareGame.jack"),
-];
+use common::samples;
+use lib::internal::{parse_into_identifier_xml, parse_into_xml, tokenize_into_xml};
+use lib::SourceFiles;
 
-static token_files: [&'static str; 7] = [
-    include_str!("./samples/ArrayTest/MainT.xml"),
-    include_str!("./samples/ExpressionLessSquare/MainT.xml"),
-    include_str!("./samples/ExpressionLessSquare/SquareT.xml"),
-    include_str!("./samples/ExpressionLessSquare/SquareGameT.xml"),
-    include_str!("./samples/Square/MainT.xml"),
-    include_str!("./samples/Square/SquareT.xml"),
-    include_str!("./samples/Square/SquareGameT.xml"),
-];
-
-static parse_files: [&'static str; 7] = [
-    include_str!("./samples/ArrayTest/Main.xml"),
-    include_str!("./samples/ExpressionLessSquare/Main.xml"),
-    include_str!("./samples/ExpressionLessSquare/Square.xml"),
-    include_str!("./samples/ExpressionLessSquare/SquareGame.xml"),
-    include_str!("./samples/Square/Main.xml"),
-    include_str!("./samples/Square/Square.xml"),
-    include_str!("./samples/Square/SquareGame.xml"),
-];
+// Returns the .jack files of the samples, each with the contents of its file with a suffix
+// instead of the extension
+fn with_expected(samples: &SourceFiles, suffix: &str) -> Vec<(String, String)> {
+    samples
+        .paths()
+        .into_iter()
+        .filter(|x| x.extension().is_some_and(|x| x == "jack"))
+        .map(|x| {
+            let stem = x.file_stem().unwrap().to_string_lossy();
+            let expected = x.with_file_name(format!("{}{}", stem, suffix));
+            (samples.get(&x).unwrap(), samples.get(expected).unwrap())
+        })
+        .collect()
+}
 
 // Return true if the two strings are equal ignoring whitespace, case and newlines
 fn text_eq(str_1: &str, str_2: &str) -> bool {
-    let f = |s: &str| {
-        s.replace('\n', "")
-            .replace('\r', "")
-            .replace(' ', "")
-            .to_lowercase()
-    };
+    let f = |s: &str| s.replace(['\n', '\r', ' '], "").to_lowercase();
     f(str_1) == f(str_2)
 }
 
 #[test]
 // Test the tokenizing into XML process
 fn tokenizer_test() {
-    for (s, t) in src_files.iter().zip(token_files.iter()) {
-        assert!(text_eq(&tokenize_into_xml(s).unwrap(), t));
+    let samples = with_expected(&samples(), "T.xml");
+    assert_eq!(samples.len(), 7);
+    for (s, t) in samples {
+        assert!(text_eq(&tokenize_into_xml(&s).unwrap(), &t));
     }
 }
 
 #[test]
 // Test the parsing into XML process
 fn parser_test() {
-    for (s, p) in src_files.iter().zip(parse_files.iter()) {
-        assert!(text_eq(&parse_into_xml(s).unwrap(), p));
+    for (s, p) in with_expected(&samples(), ".xml") {
+        assert!(text_eq(&parse_into_xml(&s).unwrap(), &p));
+    }
+}
+
+#[test]
+// Test the identifier annotations of the extended XML parse tree
+fn identifier_xml_test() {
+    let samples = samples();
+    let square = samples.get("tests/samples/Square/Square.jack").unwrap();
+    let xml = parse_into_identifier_xml(&square).unwrap();
+    for expected in [
+        r#"<identifier category="class" usage="defined"> Square </identifier>"#,
+        r#"<identifier category="field" index="2" usage="defined"> size </identifier>"#,
+        r#"<identifier category="subroutine" usage="defined"> new </identifier>"#,
+        r#"<identifier category="argument" index="1" usage="defined"> Ay </identifier>"#,
+        r#"<identifier category="argument" index="1" usage="used"> Ay </identifier>"#,
+        r#"<identifier category="class" usage="used"> Screen </identifier>"#,
+        r#"<identifier category="subroutine" usage="used"> drawRectangle </identifier>"#,
+    ] {
+        assert!(xml.contains(expected), "{}", expected);
     }
+    // the annotations don't change the structure of the parse tree
+    let stripped = xml
+        .split("<identifier ")
+        .enumerate()
+        .map(|(i, x)| match i {
+            0 => x.to_string(),
+            _ => "<identifier".to_string() + &x[x.find('>').unwrap()..],
+        })
+        .collect::<String>();
+    let expected = samples.get("tests/samples/Square/Square.xml").unwrap();
+    assert!(text_eq(&stripped, &expected));
+    assert_eq!(
+        parse_into_identifier_xml("class A {\n function void f() { let y = 1; return; }\n}"),
+        Err("2:26: Undefined variable 'y'".to_string())
+    );
 }
@@ -0,0 +1,163 @@
+extern crate lib;
+
+use lib::internal::{lint, parse, parse_toml, Json, LintConfig};
+use std::process::Command;
+
+static SUSPICIOUS: &str = "class Main {
+    field int size, unused;
+
+    method int area(int w) {
+        var int size, h;
+        let h = size * size;
+        if (h = true) {
+        }
+        while (true) {
+            do Math.multiply(h, h);
+        }
+        return h;
+        let h = 0;
+    }
+}
+";
+
+// Returns the codes of the lint warnings of a source
+fn codes(contents: &str, config: &LintConfig) -> Vec<&'static str> {
+    let class = parse(contents).unwrap();
+    lint(contents, &[&class], config)
+        .unwrap()
+        .iter()
+        .map(|x| x.code)
+        .collect()
+}
+
+#[test]
+fn lint_rules_test() {
+    assert_eq!(
+        codes(SUSPICIOUS, &LintConfig::default()),
+        [
+            "unused-variable",
+            "unused-variable",
+            "unused-variable",
+            "shadowed-field",
+            "empty-if",
+            "compare-to-true",
+            "constant-condition",
+            "discarded-result",
+            "unreachable-code",
+        ]
+    );
+
+    let config = LintConfig::from_toml(
+        "# only the interesting ones
+[rules]
+unused-variable = false
+shadowed-field = false
+",
+    )
+    .unwrap();
+    assert_eq!(
+        codes(SUSPICIOUS, &config),
+        [
+            "empty-if",
+            "compare-to-true",
+            "constant-condition",
+            "discarded-result",
+            "unreachable-code",
+        ]
+    );
+    assert!(LintConfig::from_toml("[rules]\nno-such-rule = true").is_err());
+    assert!(LintConfig::from_toml("[rules]\nempty-if = 1").is_err());
+}
+
+#[test]
+fn lint_suppression_test() {
+    let suppressed = SUSPICIOUS
+        .replace(
+            "var int size, h;",
+            "var int size, h; // jacklint-disable-line shadowed-field",
+        )
+        .replace(
+            "        while (true) {",
+            "        /* jacklint-disable-next-line */\n        while (true) {",
+        );
+    assert_eq!(
+        codes(&suppressed, &LintConfig::default()),
+        [
+            "unused-variable",
+            "unused-variable",
+            "unused-variable",
+            "empty-if",
+            "compare-to-true",
+            "discarded-result",
+            "unreachable-code",
+        ]
+    );
+}
+
+#[test]
+fn parse_toml_test() {
+    let toml = parse_toml(
+        "name = 'demo'
+[build]
+sources = [
+    \"src\", # main sources
+    \"lib\",
+]
+opt.level = 1
+flags = { fast = true }
+",
+    )
+    .unwrap();
+    assert_eq!(toml.get("name").and_then(|x| x.as_str()), Some("demo"));
+    assert_eq!(
+        toml.path(&["build", "sources"]).and_then(|x| x.as_array()),
+        Some(&vec![Json::string("src"), Json::string("lib")])
+    );
+    assert_eq!(
+        toml.path(&["build", "opt", "level"])
+            .and_then(|x| x.as_usize()),
+        Some(1)
+    );
+    assert_eq!(
+        toml.path(&["build", "flags", "fast"])
+            .and_then(|x| x.as_bool()),
+        Some(true)
+    );
+    assert_eq!(
+        parse_toml("a = 1\na = 2").unwrap_err(),
+        "line 2: 'a' is defined twice"
+    );
+}
+
+#[test]
+// Test the JSON output of jacklint
+fn jacklint_json_test() {
+    let dir = std::env::temp_dir().join(format!("jacklint_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("Main.jack"), SUSPICIOUS).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_jacklint"))
+        .args(["--format", "json", "--disable", "unused-variable"])
+        .arg(&dir)
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let json = Json::parse(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    let results = json.as_array().unwrap();
+    assert_eq!(results.len(), 6);
+    let shadowed = &results[0];
+    assert_eq!(
+        shadowed.get("code").and_then(|x| x.as_str()),
+        Some("shadowed-field")
+    );
+    assert_eq!(shadowed.get("line").and_then(|x| x.as_usize()), Some(5));
+    assert_eq!(shadowed.get("column").and_then(|x| x.as_usize()), Some(17));
+    assert_eq!(
+        shadowed
+            .path(&["notes"])
+            .and_then(|x| x.as_array())
+            .map(|x| x.len()),
+        Some(1)
+    );
+}
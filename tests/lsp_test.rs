@@ -0,0 +1,197 @@
+extern crate lib;
+
+use lib::internal::{serve_lsp, Json};
+use std::io::{BufReader, Cursor};
+
+static SQUARE: &str = include_str!("./samples/Square/Square.jack");
+
+static MAIN: &str = "class Main {
+    function void main() {
+        var Square s;
+        let s = Square.new(0, 0, 30);
+        do s.draw();
+        let t = 5;
+        return;
+    }
+}
+";
+
+// Frames JSON-RPC messages with Content-Length headers
+fn frame(messages: &[Json]) -> Vec<u8> {
+    messages
+        .iter()
+        .map(|m| {
+            let body = m.to_string();
+            format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+        })
+        .collect::<String>()
+        .into_bytes()
+}
+
+// Splits the framed output of the server into JSON messages
+fn unframe(output: &[u8]) -> Vec<Json> {
+    let output = String::from_utf8(output.to_vec()).unwrap();
+    output
+        .split("Content-Length: ")
+        .skip(1)
+        .map(|x| Json::parse(&x[x.find("\r\n\r\n").unwrap() + 4..]).unwrap())
+        .collect()
+}
+
+// Builds a JSON-RPC message from its JSON text
+fn message(text: &str) -> Json {
+    Json::parse(text).unwrap()
+}
+
+// Builds a didOpen notification
+fn did_open(uri: &str, text: &str) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/didOpen".into()),
+        (
+            "params",
+            Json::object(vec![(
+                "textDocument",
+                Json::object(vec![
+                    ("uri", uri.into()),
+                    ("languageId", "jack".into()),
+                    ("version", 1.into()),
+                    ("text", text.into()),
+                ]),
+            )]),
+        ),
+    ])
+}
+
+// Returns the response to the request with the given id
+fn response(messages: &[Json], id: usize) -> &Json {
+    messages
+        .iter()
+        .find(|x| x.get("id").and_then(|x| x.as_usize()) == Some(id))
+        .unwrap()
+        .get("result")
+        .unwrap()
+}
+
+#[test]
+// Test diagnostics, document symbols, hover and go-to-definition over a session
+fn lsp_session_test() {
+    let position = |id: usize, method: &str, uri: &str, line: usize, character: usize| {
+        message(&format!(
+            r#"{{"jsonrpc":"2.0","id":{},"method":"{}","params":{{"textDocument":{{"uri":"{}"}},"position":{{"line":{},"character":{}}}}}}}"#,
+            id, method, uri, line, character
+        ))
+    };
+    let input = frame(&[
+        message(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"rootUri":null}}"#),
+        message(r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#),
+        did_open("file:///w/Square.jack", SQUARE),
+        did_open("file:///w/Main.jack", MAIN),
+        position(2, "textDocument/hover", "file:///w/Main.jack", 4, 12),
+        position(3, "textDocument/definition", "file:///w/Main.jack", 3, 24),
+        position(4, "textDocument/definition", "file:///w/Main.jack", 2, 13),
+        message(
+            r#"{"jsonrpc":"2.0","id":5,"method":"textDocument/documentSymbol","params":{"textDocument":{"uri":"file:///w/Square.jack"}}}"#,
+        ),
+        position(6, "textDocument/hover", "file:///w/Square.jack", 17, 10),
+        message(r#"{"jsonrpc":"2.0","id":7,"method":"shutdown"}"#),
+        message(r#"{"jsonrpc":"2.0","method":"exit"}"#),
+    ]);
+    let mut output = vec![];
+    serve_lsp(&mut BufReader::new(Cursor::new(input)), &mut output).unwrap();
+    let messages = unframe(&output);
+
+    let capabilities = response(&messages, 1).get("capabilities").unwrap();
+    assert_eq!(capabilities.get("hoverProvider"), Some(&Json::Bool(true)));
+
+    let diagnostics = messages
+        .iter()
+        .rfind(|x| {
+            x.path(&["params", "uri"]).and_then(|x| x.as_str()) == Some("file:///w/Main.jack")
+        })
+        .unwrap()
+        .path(&["params", "diagnostics"])
+        .unwrap()
+        .as_array()
+        .unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].get("message").unwrap().as_str(),
+        Some("Undefined variable 't'")
+    );
+    assert_eq!(
+        diagnostics[0]
+            .path(&["range", "start", "line"])
+            .unwrap()
+            .as_usize(),
+        Some(5)
+    );
+
+    let hover = response(&messages, 2)
+        .path(&["contents", "value"])
+        .unwrap()
+        .as_str()
+        .unwrap();
+    assert!(hover.contains("var Square s"));
+    assert!(hover.contains("local 0"));
+
+    let definition = response(&messages, 3);
+    assert_eq!(
+        definition.get("uri").unwrap().as_str(),
+        Some("file:///w/Square.jack")
+    );
+    assert_eq!(
+        definition
+            .path(&["range", "start", "line"])
+            .unwrap()
+            .as_usize(),
+        Some(14)
+    );
+    let class_definition = response(&messages, 4);
+    assert_eq!(
+        class_definition
+            .path(&["range", "start", "line"])
+            .unwrap()
+            .as_usize(),
+        Some(8)
+    );
+
+    let symbols = response(&messages, 5).as_array().unwrap();
+    assert_eq!(symbols[0].get("name").unwrap().as_str(), Some("Square"));
+    let children = symbols[0].get("children").unwrap().as_array().unwrap();
+    let names = children
+        .iter()
+        .map(|x| x.get("name").unwrap().as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(&names[..5], ["x", "y", "size", "new", "dispose"]);
+
+    let field_hover = response(&messages, 6)
+        .path(&["contents", "value"])
+        .unwrap()
+        .as_str()
+        .unwrap();
+    assert!(field_hover.contains("field int size"));
+    assert!(field_hover.contains("this 2"));
+}
+
+#[test]
+// Test that malformed messages are answered with errors rather than stopping the server
+fn lsp_malformed_message_test() {
+    let mut input = b"Content-Length: 8\r\n\r\n{\"id\": 1".to_vec();
+    input.extend(b"Content-Length: abc\r\n\r\n");
+    input.extend(frame(&[message(
+        r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#,
+    )]));
+    input.extend(b"Content-Length: 99999999999999\r\n\r\n{}");
+    let mut output = vec![];
+    serve_lsp(&mut BufReader::new(Cursor::new(input)), &mut output).unwrap();
+    let messages = unframe(&output);
+
+    let errors = messages
+        .iter()
+        .filter(|x| x.get("id") == Some(&Json::Null))
+        .map(|x| x.path(&["error", "code"]).unwrap().clone())
+        .collect::<Vec<_>>();
+    assert_eq!(errors, [-32700.0, -32600.0, -32600.0].map(Json::Number));
+    assert_eq!(response(&messages, 2), &Json::Null);
+}
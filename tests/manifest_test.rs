@@ -0,0 +1,139 @@
+extern crate lib;
+
+use lib::internal::{jack_files_recursive, Manifest};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+static MAIN: &str = "class Main {
+    function void main() {
+        do Output.printInt(Util.double(21));
+        return;
+    }
+}
+";
+
+static UTIL: &str = "class Util {
+    function int double(int x) {
+        return x + x;
+    }
+}
+";
+
+#[test]
+fn manifest_parse_test() {
+    let root = Path::new("/project");
+    let manifest = Manifest::parse(
+        "name = \"game\"
+sources = [\"src\"]
+libs = [\"lib\", \"../shared\"]
+out-dir = \"build\"
+target = \"vm\"
+extensions = []
+",
+        root,
+    )
+    .unwrap();
+    assert_eq!(manifest.name.as_deref(), Some("game"));
+    assert_eq!(manifest.sources, [root.join("src")]);
+    assert_eq!(manifest.libs, [root.join("lib"), root.join("../shared")]);
+    assert_eq!(manifest.out_dir, Some(root.join("build")));
+
+    let defaults = Manifest::parse("", root).unwrap();
+    assert_eq!(defaults.sources, [root]);
+    assert!(defaults.out_dir.is_none());
+
+    assert_eq!(
+        Manifest::parse("target = \"x86\"", root).unwrap_err(),
+        "Unknown target 'x86', expected vm"
+    );
+    // only VM code can be generated, and there are no language extensions yet
+    for target in ["asm", "hack"] {
+        assert_eq!(
+            Manifest::parse(&format!("target = \"{}\"", target), root).unwrap_err(),
+            format!("Target '{}' is not supported, only vm", target)
+        );
+    }
+    assert_eq!(
+        Manifest::parse("extensions = [\"for-loops\"]", root).unwrap_err(),
+        "Language extension 'for-loops' is not supported"
+    );
+    assert_eq!(
+        Manifest::parse("source = [\"src\"]", root).unwrap_err(),
+        "Unknown key 'source'"
+    );
+}
+
+#[test]
+// Test that main finds the manifest of the current directory and builds the project
+fn manifest_build_test() {
+    let dir = std::env::temp_dir().join(format!("jack_manifest_test_{}", std::process::id()));
+    for sub in ["src/nested", "lib"] {
+        fs::create_dir_all(dir.join(sub)).unwrap();
+    }
+    fs::write(
+        dir.join("jack.toml"),
+        "sources = [\"src\"]\nlibs = [\"lib\"]\nout-dir = \"build\"\n",
+    )
+    .unwrap();
+    fs::write(dir.join("src/Main.jack"), MAIN).unwrap();
+    fs::write(dir.join("lib/Util.jack"), UTIL).unwrap();
+    fs::write(dir.join("lib/Sys.vm"), "function Sys.init 0\n").unwrap();
+
+    let nested = dir.join("src/nested");
+    assert_eq!(Manifest::discover(&nested), Some(dir.join("jack.toml")));
+    let status = Command::new(env!("CARGO_BIN_EXE_main"))
+        .arg("build")
+        .current_dir(&nested)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let vm = fs::read_to_string(dir.join("build/Main.vm")).unwrap();
+    assert!(vm.contains("call Util.double 1"));
+    assert!(dir.join("build/Util.vm").exists());
+    assert!(dir.join("build/Sys.vm").exists());
+
+    // only the vm target can be built
+    fs::write(dir.join("jack.toml"), "target = \"asm\"\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_main"))
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Target 'asm' is not supported"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+// Test that the .jack files of a workspace are found in the order of their paths, without
+// following a symbolic link back to a parent directory
+fn jack_files_recursive_test() {
+    let dir = std::env::temp_dir().join(format!("jack_workspace_test_{}", std::process::id()));
+    for sub in ["b", "a/nested"] {
+        fs::create_dir_all(dir.join(sub)).unwrap();
+    }
+    for file in [
+        "Main.jack",
+        "b/Util.jack",
+        "a/Game.jack",
+        "a/nested/Ball.jack",
+        "a/Notes.txt",
+    ] {
+        fs::write(dir.join(file), "").unwrap();
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&dir, dir.join("a/loop")).unwrap();
+
+    let files = jack_files_recursive(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        files,
+        [
+            "Main.jack",
+            "a/Game.jack",
+            "a/nested/Ball.jack",
+            "b/Util.jack"
+        ]
+        .map(|x| dir.join(x))
+    );
+}
@@ -0,0 +1,28 @@
+extern crate lib;
+
+use lib::internal::{parse, parse_into_xml};
+
+#[test]
+// Test that syntax errors are located at the line and column where parsing failed
+fn syntax_error_test() {
+    assert_eq!(
+        parse("class A {\n    field int x y;\n}").err(),
+        Some("2:17: Expected ',' or ';', found 'y'".to_string())
+    );
+    assert_eq!(
+        parse("class A { function void f() { let x = 1 # 2; } }").err(),
+        Some(
+            "1:41: Unable to tokenize '#' - not an integer constant, a keyword nor an identifier"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+// Test that tokens after the end of the class are an error rather than ignored
+fn trailing_tokens_test() {
+    assert_eq!(
+        parse_into_xml("class A { }\nclass B { }"),
+        Err("2:1: Expected end of file, found 'class'".to_string())
+    );
+}
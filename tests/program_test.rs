@@ -0,0 +1,522 @@
+extern crate lib;
+
+mod common;
+
+use common::src_files;
+use lib::internal::{
+    check_types, codewrite, compile_class_with, line_col, parse, run_vm, ClassOutput, LineIndex,
+    Program, SubroutineKind, Type,
+};
+use lib::{CodegenOptions, TypeMode};
+
+static COUNTER: &str = "class Counter {
+    field int count;
+    static Counter shared;
+
+    constructor Counter new() {
+        let count = 0;
+        return this;
+    }
+
+    method int next(int step) {
+        let count = count + step;
+        return count;
+    }
+
+    function int twice(Counter c) {
+        var Array a;
+        let a = Array.new(2);
+        let a[0] = c.next(1);
+        if (a[0] > 1) {
+            let a[1] = 10 - 2 - 1;
+        } else {
+            do Counter.reset();
+        }
+        while (~(a[1] = 0)) {
+            let a[1] = a[1] - 1;
+        }
+        return a[0] + twice(c);
+    }
+
+    function void reset() {
+        return;
+    }
+}
+";
+
+#[test]
+fn program_interfaces_test() {
+    let groupings = src_files(&["Square"])
+        .iter()
+        .map(|x| parse(x).unwrap())
+        .collect::<Vec<_>>();
+    let program = Program::new(&groupings.iter().collect::<Vec<_>>());
+
+    let square = program.class("Square").unwrap();
+    assert_eq!(
+        square.fields,
+        [
+            ("x".to_string(), Type::Int),
+            ("y".to_string(), Type::Int),
+            ("size".to_string(), Type::Int)
+        ]
+    );
+    let new = program.signature("Square", "new").unwrap();
+    assert_eq!(new.kind, SubroutineKind::Constructor);
+    assert_eq!(new.return_type, Type::Class("Square".to_string()));
+    assert_eq!(new.parameters, [Type::Int, Type::Int, Type::Int]);
+    assert_eq!(
+        program.signature("SquareGame", "moveSquare").unwrap().kind,
+        SubroutineKind::Method
+    );
+    // the OS classes are part of every program
+    assert_eq!(
+        program.signature("Output", "printInt").unwrap().parameters,
+        [Type::Int]
+    );
+    for name in [
+        "Math", "String", "Array", "Output", "Screen", "Keyboard", "Memory", "Sys",
+    ] {
+        assert!(program.class(name).is_some(), "{}", name);
+    }
+    assert!(program.class("Game").is_none());
+}
+
+#[test]
+fn cross_file_call_check_test() {
+    let square = parse(&src_files(&["Square"])[1]).unwrap();
+    let game = parse(
+        "class Game {
+    function void run() {
+        var Square s;
+        let s = Square.new(0, 0);
+        do s.grow();
+        do Square.moveUp();
+        do Missing.run();
+        return;
+    }
+}
+",
+    )
+    .unwrap();
+    let messages = check_types(&game, &[&square], TypeMode::Lenient)
+        .iter()
+        .map(|x| format!("{}: {}", x.code, x.message))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        [
+            "argument-count: 'Square.new' expects 3 arguments, found 2",
+            "undefined-subroutine: Class Square has no subroutine 'grow'",
+            "method-without-object: 'Square.moveUp' is a method and must be called on an object",
+        ]
+    );
+}
+
+#[test]
+fn codewrite_test() {
+    let counter = parse(COUNTER).unwrap();
+    let expected = "function Counter.new 0
+push constant 1
+call Memory.alloc 1
+pop pointer 0
+push constant 0
+pop this 0
+push pointer 0
+return
+function Counter.next 0
+push argument 0
+pop pointer 0
+push this 0
+push argument 1
+add
+pop this 0
+push this 0
+return
+function Counter.twice 1
+push constant 2
+call Array.new 1
+pop local 0
+push constant 0
+push local 0
+add
+push argument 0
+push constant 1
+call Counter.next 2
+pop temp 0
+pop pointer 1
+push temp 0
+pop that 0
+push constant 0
+push local 0
+add
+pop pointer 1
+push that 0
+push constant 1
+gt
+not
+if-goto IF_ELSE0
+push constant 1
+push local 0
+add
+push constant 10
+push constant 2
+sub
+push constant 1
+sub
+pop temp 0
+pop pointer 1
+push temp 0
+pop that 0
+goto IF_END0
+label IF_ELSE0
+call Counter.reset 0
+pop temp 0
+label IF_END0
+label WHILE_START1
+push constant 1
+push local 0
+add
+pop pointer 1
+push that 0
+push constant 0
+eq
+not
+not
+if-goto WHILE_END1
+push constant 1
+push local 0
+add
+push constant 1
+push local 0
+add
+pop pointer 1
+push that 0
+push constant 1
+sub
+pop temp 0
+pop pointer 1
+push temp 0
+pop that 0
+goto WHILE_START1
+label WHILE_END1
+push constant 0
+push local 0
+add
+pop pointer 1
+push that 0
+push argument 0
+call Counter.twice 1
+add
+return
+function Counter.reset 0
+push constant 0
+return
+";
+    assert_eq!(codewrite(&counter, &[]).unwrap(), expected);
+
+    let src_files = src_files(&["Square"]);
+    let square = parse(&src_files[1]).unwrap();
+    let game = parse(&src_files[2]).unwrap();
+    let vm = codewrite(&game, &[&square]).unwrap();
+    assert!(vm.contains("push constant 30\ncall Square.new 3\npop this 0\n"));
+    assert!(vm.contains("push this 0\ncall Square.moveUp 1\npop temp 0\n"));
+}
+
+#[test]
+// Test that the source map gives the Jack position and subroutine of every VM command, and that
+// the source comments come before the commands of each statement
+fn source_map_test() {
+    let source = "class Counter {
+    field int count;
+
+    method void add(int n) {
+        let count = count + n;
+        return;
+    }
+}
+";
+    let counter = parse(source).unwrap();
+    let program = Program::new(&[&counter]);
+    let options = CodegenOptions {
+        source_comments: true,
+        source_map: true,
+        ..Default::default()
+    };
+    let output =
+        compile_class_with(&counter, &program, ("Counter.jack", source), &options).unwrap();
+    assert_eq!(
+        output.vm,
+        "// method void add(int n) {
+function Counter.add 0
+push argument 0
+pop pointer 0
+// let count = count + n;
+push this 0
+push argument 1
+add
+pop this 0
+// return;
+push constant 0
+return
+"
+    );
+    let source_map = output.source_map.unwrap();
+    assert_eq!(
+        source_map.get("source").and_then(|x| x.as_str()),
+        Some("Counter.jack")
+    );
+    let mappings = source_map.get("mappings").unwrap().as_array().unwrap();
+    let mapping = |i: usize| {
+        ["vmLine", "line", "column"]
+            .map(|key| mappings[i].get(key).and_then(|x| x.as_usize()).unwrap())
+    };
+    assert_eq!(mappings.len(), 9);
+    assert_eq!(mapping(0), [2, 4, 5]);
+    assert_eq!(mapping(3), [6, 5, 9]);
+    assert_eq!(mapping(8), [12, 6, 9]);
+    assert_eq!(
+        mappings[8].get("subroutine").and_then(|x| x.as_str()),
+        Some("Counter.add")
+    );
+
+    // without debug information the code is the same as codewrite's
+    let output = compile_class_with(
+        &counter,
+        &program,
+        ("Counter.jack", source),
+        &Default::default(),
+    )
+    .unwrap();
+    assert_eq!(output.vm, codewrite(&counter, &[]).unwrap());
+    assert!(output.source_map.is_none());
+}
+
+#[test]
+// Test that the line index of the source map finds the same positions as line_col
+fn line_index_test() {
+    let source = "class Main {\n    // é ü\n\n    field int x; }\n";
+    let index = LineIndex::new(source);
+    for offset in (0..=source.len() + 1).filter(|&i| i > source.len() || source.is_char_boundary(i))
+    {
+        assert_eq!(index.line_col(offset), line_col(source, offset));
+    }
+    assert_eq!(index.line_col(source.find('ü').unwrap()), (2, 10));
+}
+
+#[test]
+// Test that the reference mode names labels and shapes if statements like the official compiler
+fn reference_codegen_test() {
+    let source = "class A {
+    function int f(int x) {
+        while (x > 0) {
+            if (x = 1) { return 1; } else { let x = x - 1; }
+        }
+        if (true) { }
+        return 0;
+    }
+}
+";
+    let a = parse(source).unwrap();
+    let options = CodegenOptions {
+        reference: true,
+        ..Default::default()
+    };
+    let output =
+        compile_class_with(&a, &Program::new(&[&a]), ("A.jack", source), &options).unwrap();
+    assert_eq!(
+        output.vm,
+        "function A.f 0
+label WHILE_EXP0
+push argument 0
+push constant 0
+gt
+not
+if-goto WHILE_END0
+push argument 0
+push constant 1
+eq
+if-goto IF_TRUE0
+goto IF_FALSE0
+label IF_TRUE0
+push constant 1
+return
+goto IF_END0
+label IF_FALSE0
+push argument 0
+push constant 1
+sub
+pop argument 0
+label IF_END0
+goto WHILE_EXP0
+label WHILE_END0
+push constant 0
+not
+if-goto IF_TRUE1
+goto IF_FALSE1
+label IF_TRUE1
+label IF_FALSE1
+push constant 0
+return
+"
+    );
+}
+
+#[test]
+// Test that the peephole optimizations remove redundant commands without changing what the
+// program does
+fn optimize_test() {
+    let source = "class Main {
+    function void main() {
+        var int i, x, total;
+        let i = 0;
+        let x = 3;
+        while (i < 5) {
+            let x = x;
+            if (i = 2) { } else { let total = total + i + 0; }
+            let i = i + 1;
+            if (~(i > 3)) { let total = total + 1; }
+        }
+        do Output.printInt(total);
+        return;
+    }
+}
+";
+    let main = parse(source).unwrap();
+    let program = Program::new(&[&main]);
+    let run = |opt_level: u8| {
+        let options = CodegenOptions {
+            opt_level,
+            ..Default::default()
+        };
+        let output = compile_class_with(&main, &program, ("Main.jack", source), &options).unwrap();
+        let mut stdout = vec![];
+        run_vm(&[("Main.vm", &output.vm)], &mut &b""[..], &mut stdout).unwrap();
+        (output, String::from_utf8(stdout).unwrap())
+    };
+    let (unoptimized, expected) = run(0);
+    let (optimized, actual) = run(1);
+    assert_eq!((expected.as_str(), actual.as_str()), ("11", "11"));
+    assert_eq!(unoptimized.commands, 49);
+    assert_eq!(
+        (optimized.commands, optimized.unoptimized_commands),
+        (39, 49)
+    );
+    let vm = optimized.vm;
+    // push x; pop x and push constant 0; add
+    assert!(!vm.contains("push local 1\npop local 1") && !vm.contains("push constant 0\nadd"));
+    // the else branch is taken on the comparison itself
+    assert!(vm.contains("eq\nif-goto IF_END1\npush local 2"));
+    // not; not
+    assert!(vm.contains("push constant 3\ngt\nif-goto IF_END2\n"));
+    assert!(!vm.contains("IF_ELSE1") && !vm.contains("WHILE_END0"));
+    // the loop condition is tested at its end
+    assert!(vm.contains("goto WHILE_START0\nlabel WHILE_START0_BODY\n"));
+    assert!(vm.contains("push constant 5\nlt\nif-goto WHILE_START0_BODY\n"));
+}
+
+#[test]
+// Test that interned string literals are made once per class and then shared by every use
+fn intern_strings_test() {
+    let source = "class Main {
+    static int count;
+
+    function String label() {
+        return \"ab\";
+    }
+
+    function void main() {
+        var String s;
+        let s = Main.label();
+        do s.setCharAt(0, 120);
+        do Output.printString(Main.label());
+        do Output.printString(\"ab\");
+        if (s = Main.label()) {
+            do Output.printString(\"!\");
+        }
+        return;
+    }
+}
+";
+    let main = parse(source).unwrap();
+    let program = Program::new(&[&main]);
+    let run = |intern_strings: bool| {
+        let options = CodegenOptions {
+            intern_strings,
+            ..Default::default()
+        };
+        let output = compile_class_with(&main, &program, ("Main.jack", source), &options).unwrap();
+        let mut stdout = vec![];
+        run_vm(&[("Main.vm", &output.vm)], &mut &b""[..], &mut stdout).unwrap();
+        (output.vm, String::from_utf8(stdout).unwrap())
+    };
+    assert_eq!(run(false).1, "abab");
+    let (vm, stdout) = run(true);
+    // the changed literal is seen by its other uses, in every subroutine of the class
+    assert_eq!(stdout, "xbxb!");
+    // the literals come after the declared statics
+    assert!(vm.starts_with(
+        "function Main.label 0
+push static 1
+if-goto STRING_READY0
+push constant 2
+call String.new 1
+push constant 97
+call String.appendChar 2
+push constant 98
+call String.appendChar 2
+pop static 1
+label STRING_READY0
+push static 1
+return
+"
+    ));
+    assert!(vm.contains("push static 2\nif-goto STRING_READY1\npush constant 1\n"));
+}
+
+#[test]
+// Test that the peephole optimizations shorten the default VM code of every sample program, and
+// that the optimized ArrayTest does the same. The Square games wait for a key forever.
+fn optimize_samples_test() {
+    let compile = |program: &str, opt_level: u8| {
+        let sources = src_files(&[program]);
+        let classes = sources
+            .iter()
+            .map(|x| parse(x).unwrap())
+            .collect::<Vec<_>>();
+        let program = Program::new(&classes.iter().collect::<Vec<_>>());
+        let options = CodegenOptions {
+            opt_level,
+            ..Default::default()
+        };
+        classes
+            .iter()
+            .zip(&sources)
+            .map(|(class, source)| {
+                compile_class_with(class, &program, ("Main.jack", source), &options).unwrap()
+            })
+            .collect::<Vec<_>>()
+    };
+    for program in common::PROGRAMS {
+        let unoptimized = compile(program, 0);
+        let optimized = compile(program, 1);
+        let count = |outputs: &[ClassOutput]| outputs.iter().map(|x| x.commands).sum::<usize>();
+        assert!(count(&optimized) < count(&unoptimized), "{}", program);
+        assert_eq!(
+            optimized
+                .iter()
+                .map(|x| x.unoptimized_commands)
+                .sum::<usize>(),
+            count(&unoptimized)
+        );
+    }
+    let run = |opt_level: u8| {
+        let vm = &compile("ArrayTest", opt_level)[0].vm;
+        let mut stdout = vec![];
+        run_vm(&[("Main.vm", vm)], &mut &b"3\n4\n5\n9\n"[..], &mut stdout).unwrap();
+        String::from_utf8(stdout).unwrap()
+    };
+    let expected = run(0);
+    assert!(expected.ends_with("THE AVERAGE IS: 6\n"), "{}", expected);
+    assert_eq!(run(1), expected);
+}
@@ -0,0 +1,74 @@
+extern crate lib;
+
+use lib::internal::{find_references, rename};
+
+static SQUARE: &str = include_str!("./samples/Square/Square.jack");
+static SQUARE_GAME: &str = include_str!("./samples/Square/SquareGame.jack");
+static MAIN: &str = include_str!("./samples/Square/Main.jack");
+
+static FILES: &[(&str, &str)] = &[
+    ("Square/Square.jack", SQUARE),
+    ("Square/SquareGame.jack", SQUARE_GAME),
+    ("Square/Main.jack", MAIN),
+];
+
+// Returns the offset of the nth occurrence of a pattern in a source
+fn offset_of(source: &str, pattern: &str, n: usize) -> usize {
+    source.match_indices(pattern).nth(n).unwrap().0
+}
+
+#[test]
+fn find_references_test() {
+    let references =
+        find_references(FILES, "Square/Square.jack", offset_of(SQUARE, "size", 0)).unwrap();
+    assert!(references.len() > 5);
+    assert!(references
+        .iter()
+        .all(|x| x.path == "Square/Square.jack" && x.span.text(SQUARE) == "size"));
+
+    let references = find_references(
+        FILES,
+        "Square/SquareGame.jack",
+        offset_of(SQUARE_GAME, "moveUp", 0),
+    )
+    .unwrap();
+    let paths = references
+        .iter()
+        .map(|x| x.path.as_str())
+        .collect::<Vec<_>>();
+    assert!(paths.contains(&"Square/Square.jack"));
+    assert!(paths.contains(&"Square/SquareGame.jack"));
+}
+
+#[test]
+fn rename_test() {
+    let edits = rename(
+        FILES,
+        "Square/Main.jack",
+        offset_of(MAIN, "SquareGame", 0),
+        "Game",
+    )
+    .unwrap();
+    assert_eq!(edits.len(), 2);
+    let game = edits
+        .iter()
+        .find(|x| x.path == "Square/SquareGame.jack")
+        .unwrap();
+    assert_eq!(game.new_path.as_deref(), Some("Square/Game.jack"));
+    assert!(game.contents.contains("class Game {"));
+    let main = edits.iter().find(|x| x.path == "Square/Main.jack").unwrap();
+    assert_eq!(main.new_path, None);
+    assert!(main.contents.contains("var Game game;"));
+    assert!(main.contents.contains("let game = Game.new();"));
+    // classes can't be renamed to an OS class
+    let offset = offset_of(MAIN, "SquareGame", 0);
+    assert!(rename(FILES, "Square/Main.jack", offset, "Screen").is_err());
+
+    let offset = offset_of(SQUARE, "size", 0);
+    assert!(rename(FILES, "Square/Square.jack", offset, "while").is_err());
+    assert!(rename(FILES, "Square/Square.jack", offset, "x").is_err());
+    assert!(rename(FILES, "Square/Square.jack", offset, "Ax").is_err());
+    let edits = rename(FILES, "Square/Square.jack", offset, "width").unwrap();
+    assert_eq!(edits.len(), 1);
+    assert!(edits[0].contents.contains("field int width;"));
+}
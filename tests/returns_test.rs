@@ -0,0 +1,60 @@
+extern crate lib;
+
+use lib::internal::{check_returns, parse};
+
+static SQUARE: &str = include_str!("./samples/Square/Square.jack");
+
+static ERRORS: &str = "class Point {
+    field int x;
+
+    constructor int new() {
+        let x = 0;
+        return x;
+    }
+
+    method int sign() {
+        if (x < 0) {
+            return -1;
+        } else {
+            if (x > 0) {
+                return 1;
+            }
+        }
+    }
+
+    method int getX() {
+        while (true) {
+            return x;
+        }
+    }
+
+    method void reset() {
+        let x = 0;
+        return x;
+    }
+
+    function int zero() {
+        return;
+    }
+}
+";
+
+#[test]
+fn check_returns_test() {
+    assert!(check_returns(&parse(SQUARE).unwrap()).is_empty());
+
+    let messages = check_returns(&parse(ERRORS).unwrap())
+        .iter()
+        .map(|x| format!("{}: {}", x.code, x.message))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        [
+            "constructor-type: Constructor 'new' must return Point, not int",
+            "constructor-return: Constructor 'new' must return this",
+            "missing-return: Not every path through 'sign' returns a value of type int",
+            "void-return-value: Void subroutine 'reset' cannot return a value",
+            "missing-return-value: 'zero' must return a value of type int",
+        ]
+    );
+}
@@ -0,0 +1,209 @@
+extern crate lib;
+
+use lib::{
+    CodegenOptions, Session, SessionOptions, SourceFiles, TokenKind, TreeElement, TypeMode,
+    VariableKind,
+};
+
+static POINT: &str = "class Point {
+    field int x, y;
+    static int count;
+
+    constructor Point new(int ax, int ay) {
+        let x = ax;
+        let y = ay;
+        let count = count + 1;
+        return this;
+    }
+
+    method int distance(Point other) {
+        var int dx, dy;
+        let dx = x - other.x();
+        let dy = y - other.y();
+        return Math.abs(dx) + Math.abs(dy);
+    }
+
+    method int x() { return x; }
+    method int y() { return y; }
+}
+";
+
+#[test]
+// Test that a session gives the tokens, symbol tables and VM code of each class
+fn session_test() {
+    let compilation = Session::new()
+        .with_source("Point.jack", POINT)
+        .with_source(
+            "Main.jack",
+            "class Main { function void main() { var Point p; let p = Point.new(1, 2); return; } }",
+        )
+        .compile();
+    assert!(!compilation.has_errors());
+    let point = compilation.file("Point.jack").unwrap();
+    assert_eq!(point.tokens[0].kind, TokenKind::Keyword);
+    assert_eq!(point.tokens[1].text, "Point");
+    let tree = point.tree.as_ref().unwrap();
+    assert_eq!(tree.kind, "class");
+    assert_eq!(tree.span.text(POINT), POINT.trim_end());
+    let kinds = tree
+        .children
+        .iter()
+        .map(|x| match x {
+            TreeElement::Node(node) => node.kind.as_str(),
+            TreeElement::Token(token) => token.text.as_str(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            "class",
+            "Point",
+            "{",
+            "classVarDec",
+            "classVarDec",
+            "subroutineDec",
+            "subroutineDec",
+            "subroutineDec",
+            "subroutineDec",
+            "}"
+        ]
+    );
+    let interface = point.interface.as_ref().unwrap();
+    assert_eq!(interface.name, "Point");
+    assert_eq!(
+        interface.statics,
+        [("count".to_string(), "int".to_string())]
+    );
+    let distance = &interface.subroutines[1];
+    assert_eq!(
+        (distance.kind.as_str(), distance.return_type.as_str()),
+        ("method", "int")
+    );
+    assert_eq!(distance.parameters, ["Point"]);
+
+    let symbols = point.symbols.as_ref().unwrap();
+    let count = &symbols.class[2];
+    assert_eq!(
+        (count.name.as_str(), count.kind, count.index),
+        ("count", VariableKind::Static, 0)
+    );
+    let (name, distance) = &symbols.subroutines[1];
+    assert_eq!(name, "distance");
+    let names = distance.iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["this", "other", "dx", "dy"]);
+    assert_eq!(distance[1].var_type, "Point");
+    assert_eq!(distance[3].kind.segment(), "local");
+
+    let main = compilation.file("Main.jack").unwrap();
+    assert!(main
+        .vm
+        .as_ref()
+        .unwrap()
+        .contains("call Point.new 2\npop local 0\n"));
+}
+
+#[test]
+// Test that files with errors have diagnostics and no VM code, and that options are applied
+fn session_errors_test() {
+    let compilation = Session::new()
+        .with_source("A.jack", "class A { function void f() { return } }")
+        .with_source(
+            "B.jack",
+            "class B { function void f() { var char c; let c = 65; return; } }",
+        )
+        .compile();
+    assert!(compilation.has_errors());
+    let a = &compilation.files[0];
+    assert_eq!(a.diagnostics[0].code, "syntax-error");
+    assert!(a.tree.is_none() && a.vm.is_none());
+    assert!(!a.tokens.is_empty());
+    // lenient type checking allows integers as characters
+    assert!(compilation.files[1].vm.is_some());
+
+    let options = SessionOptions {
+        type_mode: TypeMode::Strict,
+        generate_vm: false,
+        ..Default::default()
+    };
+    let compilation = Session::new()
+        .with_source(
+            "B.jack",
+            "class B { function void f() { var char c; let c = 65; return; } }",
+        )
+        .with_options(options)
+        .compile();
+    let b = &compilation.files[0];
+    assert_eq!(b.diagnostics[0].code, "type-mismatch");
+    assert!(b.vm.is_none());
+
+    assert!(Session::new().with_path("missing.jack").is_err());
+    let compilation = Session::new()
+        .with_path("tests/samples/Square")
+        .unwrap()
+        .compile();
+    assert_eq!(compilation.files.len(), 3);
+    assert!(!compilation.has_errors());
+
+    // sources can also come from files kept in memory
+    let files = SourceFiles::new();
+    files.insert("src/Point.jack", POINT);
+    files.insert("src/README", "");
+    let compilation = Session::new()
+        .with_path_in(&files, "src")
+        .unwrap()
+        .compile();
+    assert_eq!(compilation.files.len(), 1);
+    assert!(compilation.file("src/Point.jack").unwrap().vm.is_some());
+}
+
+#[test]
+// Test that lint warnings and source maps are given when the options ask for them
+fn session_options_test() {
+    let source = "class A { function void f() { var int x; return; } }";
+    let options = SessionOptions {
+        lint: true,
+        codegen: CodegenOptions {
+            source_map: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let compilation = Session::new()
+        .with_source("A.jack", source)
+        .with_options(options.clone())
+        .compile();
+    let a = &compilation.files[0];
+    assert_eq!(a.diagnostics[0].code, "unused-variable");
+    assert_eq!((a.commands, a.unoptimized_commands), (3, 3));
+    assert!(a.source_map.as_ref().unwrap().contains("\"mappings\""));
+
+    let options = SessionOptions {
+        disabled_lints: vec!["unused-variable".to_string()],
+        ..options
+    };
+    let compilation = Session::new()
+        .with_source("A.jack", source)
+        .with_options(options)
+        .compile();
+    assert!(compilation.files[0].diagnostics.is_empty());
+}
+
+#[test]
+#[allow(deprecated)]
+// Test that the entry points of earlier versions give the same results as a session
+fn deprecated_entry_points_test() {
+    let compilation = Session::new().with_source("Point.jack", POINT).compile();
+    let point = lib::parse(POINT).unwrap();
+    assert_eq!(lib::codewrite(&point, &[]).ok(), compilation.files[0].vm);
+    assert_eq!(
+        lib::tokenize(POINT).unwrap().len(),
+        compilation.files[0].tokens.len()
+    );
+    assert!(lib::tokenize_into_xml(POINT)
+        .unwrap()
+        .starts_with("<tokens><keyword> class </keyword><identifier> Point </identifier>"));
+    assert!(lib::parse_into_xml(POINT)
+        .unwrap()
+        .starts_with("<class><keyword> class </keyword>"));
+    assert!(lib::parse("class Point {").is_err());
+}
@@ -0,0 +1,43 @@
+extern crate lib;
+
+use lib::internal::{codewrite, parse};
+
+static COUNTER: &str = "class Counter {
+    field int count;
+    static int total;
+
+    method void add(int n) {
+        var int sum;
+        let sum = count + n;
+        let count = sum;
+        let total = total + n;
+        return;
+    }
+}
+";
+
+#[test]
+// Test that the variables of a subroutine are resolved in its own scope, then in its class
+fn subroutine_scope_test() {
+    let class_grouping = parse(COUNTER).unwrap();
+    // count is a field, n the argument after this, sum a local and total a static
+    assert_eq!(
+        codewrite(&class_grouping, &[]).unwrap(),
+        "function Counter.add 1
+push argument 0
+pop pointer 0
+push this 0
+push argument 1
+add
+pop local 0
+push local 0
+pop this 0
+push static 0
+push argument 1
+add
+pop static 0
+push constant 0
+return
+"
+    );
+}
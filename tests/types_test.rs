@@ -0,0 +1,152 @@
+extern crate lib;
+
+mod common;
+
+use common::src_files;
+use lib::internal::{check_types, parse};
+use lib::TypeMode;
+
+static ERRORS: &str = "class Main {
+    field Square square;
+
+    method void run(int n, char c, boolean done, Array a) {
+        var int x;
+        let x = square;
+        let x = c;
+        let c = n + 1;
+        let a = Memory.alloc(n);
+        do Output.printString(x);
+        do x.draw();
+        if (n) { return; }
+        while (~done) { let done = n < 3; }
+        do Math.max(n);
+        do Output.printInt(null);
+        return;
+    }
+}
+";
+
+// Returns the codes and messages of the type errors of a class
+fn errors(source: &str, mode: TypeMode) -> Vec<String> {
+    let class_grouping = parse(source).unwrap();
+    check_types(&class_grouping, &[], mode)
+        .iter()
+        .map(|x| format!("{}: {}", x.code, x.message))
+        .collect()
+}
+
+#[test]
+fn samples_type_check_test() {
+    let groupings = src_files(&["ArrayTest", "Square"])
+        .iter()
+        .map(|x| parse(x).unwrap())
+        .collect::<Vec<_>>();
+    for g in &groupings {
+        let program = groupings.iter().collect::<Vec<_>>();
+        assert!(check_types(g, &program, TypeMode::Lenient).is_empty());
+    }
+}
+
+#[test]
+fn type_errors_test() {
+    assert_eq!(
+        errors(ERRORS, TypeMode::Lenient),
+        [
+            "method-on-primitive: Cannot call method 'draw' on 'x' of type int",
+            "condition-type: Condition must be boolean, found int",
+            "argument-count: 'Math.max' expects 2 arguments, found 1",
+        ]
+    );
+    let strict = errors(ERRORS, TypeMode::Strict);
+    assert_eq!(strict.len(), 8);
+    assert!(strict.contains(&"type-mismatch: Cannot assign Square to 'x' of type int".to_string()));
+    assert!(strict.contains(&"type-mismatch: Cannot assign char to 'x' of type int".to_string()));
+    assert!(strict.contains(&"type-mismatch: Cannot assign int to 'c' of type char".to_string()));
+    assert!(strict.contains(
+        &"argument-type: Argument 1 of 'Output.printInt' expects int, found null".to_string()
+    ));
+}
+
+#[test]
+// Test that calls of the OS are checked without the OS sources
+fn os_calls_test() {
+    let source = "class Main {
+    function void main() {
+        var int n;
+        var String s;
+        do Output.printString(true);
+        do Screen.drawRectangle(1, 2, 3);
+        do Output.printStrin(\"x\");
+        let n = Keyboard.readInt(\"n? \");
+        do Memory.deAlloc(Array.new(n));
+        let n = Math.sqrt(n, 2);
+        let s = String.new(n);
+        do s.appendChar(true);
+        do Sys.wait(n);
+        do Sys.stop();
+        return;
+    }
+}
+";
+    assert_eq!(
+        errors(source, TypeMode::Lenient),
+        [
+            "argument-type: Argument 1 of 'Output.printString' expects String, found boolean",
+            "argument-count: 'Screen.drawRectangle' expects 4 arguments, found 3",
+            "undefined-subroutine: Class Output has no subroutine 'printStrin'",
+            "argument-count: 'Math.sqrt' expects 1 arguments, found 2",
+            "argument-type: Argument 1 of 'String.appendChar' expects char, found boolean",
+            "undefined-subroutine: Class Sys has no subroutine 'stop'",
+        ]
+    );
+
+    // classes of the program take the place of OS classes of the same name
+    let output = parse("class Output { function void printString(int i) { return; } }").unwrap();
+    let main = parse(source).unwrap();
+    let codes = check_types(&main, &[&output], TypeMode::Lenient)
+        .iter()
+        .map(|x| x.code)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        codes,
+        [
+            "argument-type",
+            "argument-count",
+            "undefined-subroutine",
+            "argument-count",
+            "argument-type",
+            "undefined-subroutine",
+        ]
+    );
+}
+
+#[test]
+// Test that only lenient mode converts between int and objects
+fn int_object_conversion_test() {
+    let source = "class Main {
+    function void main(int n, Main m) {
+        var Main o;
+        var int i;
+        let o = n;
+        let i = m;
+        let o = Main.make(1);
+        let i = Main.count(m);
+        return;
+    }
+
+    function Main make(Main m) { return m; }
+
+    function int count(int i) { return i; }
+}
+";
+    assert!(errors(source, TypeMode::Lenient).is_empty());
+    assert_eq!(
+        errors(source, TypeMode::Strict),
+        [
+            "type-mismatch: Cannot assign int to 'o' of type Main",
+            "type-mismatch: Cannot assign Main to 'i' of type int",
+            "argument-type: Argument 1 of 'Main.make' expects Main, found int",
+            "argument-type: Argument 1 of 'Main.count' expects int, found Main",
+        ]
+    );
+}
@@ -0,0 +1,70 @@
+extern crate lib;
+
+use lib::internal::run_vm;
+
+static MAIN_VM: &str = "function Main.main 1
+push constant 3
+pop local 0
+label LOOP
+push local 0
+if-goto BODY
+goto END
+label BODY
+call Counter.increment 0
+pop temp 0
+push local 0
+push constant 1
+sub
+pop local 0
+goto LOOP
+label END
+push constant 7
+pop static 0
+call Counter.increment 0
+call Output.printInt 1
+pop temp 0
+push static 0
+call Output.printInt 1
+pop temp 0
+push constant 0
+return
+";
+
+static COUNTER_VM: &str = "// statics are separate for each file
+function Counter.increment 0
+push static 0
+push constant 1
+add
+pop static 0
+push static 0
+return
+";
+
+#[test]
+// Test that the VM runs loops and calls, with separate statics for each file
+fn run_vm_test() {
+    let mut output = vec![];
+    run_vm(
+        &[("Main.vm", MAIN_VM), ("Counter.vm", COUNTER_VM)],
+        &mut "".as_bytes(),
+        &mut output,
+    )
+    .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "47");
+
+    let error = run_vm(
+        &[("Main.vm", "function Main.main 0\npush local")],
+        &mut "".as_bytes(),
+        &mut vec![],
+    );
+    assert_eq!(
+        error.unwrap_err(),
+        "Main.vm:2: Unknown command 'push local'"
+    );
+    let error = run_vm(
+        &[("Main.vm", "function Main.main 0\ncall Main.main 0\n")],
+        &mut "".as_bytes(),
+        &mut vec![],
+    );
+    assert_eq!(error.unwrap_err(), "Main.main: Stack overflow");
+}